@@ -0,0 +1,751 @@
+//! Headless commands, run instead of the viewer when arguments are given.
+use std::path::Path;
+
+use crate::color::Color;
+use crate::config::{
+    self,
+    export::{export_palette, ExportFormat},
+    revision::RevisionStore,
+    writer::color_to_string,
+};
+use crate::extract::{self, Quantizer};
+use crate::palette::{
+    diff::PaletteDiff,
+    lint::{LintOptions, Severity},
+    AnsiNaming, Assignment, ColorNamer, Deficiency, Palette, Query, SortOrder, Transform, Variant,
+    VisionOptions,
+};
+
+const USAGE: &str = "Usage: color_palette [COMMAND]
+
+Without a command the palette viewer is opened.
+
+Commands:
+  lint [PALETTE...]    Check palettes for problems, all palettes if none are named
+  diff FROM TO [--format table|unified|json]
+                       Show what changed between two palettes, each a palette
+                       name or the path of a .toml palette file
+  variant PALETTE      Print the light variant of a dark palette, or the dark
+                       variant of a light one, as TOML
+  extract IMAGE [--count N] [--method median-cut|k-means|octree]
+          [--terminal [--light]] [--name NAME] [--force]
+                       Extract a palette from a PNG or JPEG image into the
+                       palettes folder, as a terminal scheme with --terminal
+  import FILE [--name NAME] [--force]
+                       Import a list of hex colours, one per line as in pywal's
+                       colors file, into the palettes folder, each colour named
+                       after its nearest descriptive name
+  name COLOR...        Print the nearest descriptive name of each hex colour
+  export PALETTE [--format json|css|sh] [--separator SEP] [--complete-ansi]
+         [--on-colors wcag|apca]
+                       Print a palette for use elsewhere, grouped colours
+                       joined into one name with SEP, terminal schemes
+                       completed as by `ansi` with --complete-ansi, each
+                       colour followed by an on-NAME entry holding its most
+                       readable text colour with --on-colors
+  ansi PALETTE [--naming numbered|named]
+                       Print a terminal scheme as TOML with its missing ANSI,
+                       cursor and selection colours filled in and its colours
+                       renamed to color0..color15 or black..bright-white
+  query PALETTE QUERY  List the colours matching QUERY, e.g.
+                       'lightness > 0.6 and hue in 180..240', 'name ~ color1*',
+                       'role = accent' or 'contrast(bg) < 4.5'
+  transform PALETTE TRANSFORM... [--where QUERY] [--name NAME]
+                       Print a copy of a palette as TOML with each TRANSFORM
+                       applied to the colours matching QUERY: hue-shift=DEG,
+                       chroma=FACTOR, lightness-curve=GAMMA, temperature=AMOUNT,
+                       posterize=LEVELS, grayscale or clamp-contrast=RATIO
+  sort PALETTE ORDER   Print a palette as TOML with its colours sorted by hue,
+                       lightness, luminance, chroma, name, hilbert or
+                       nearest-neighbor
+  revisions PALETTE [--diff N [--format table|unified|json]] [--restore N]
+                       List the snapshots kept each time a palette was saved,
+                       newest first, show what changed since snapshot N or
+                       restore it
+  extend PALETTE [--osc]
+                       Print a terminal scheme as TOML with colours 16-255
+                       interpolated from its background, foreground and ANSI
+                       colours, or as OSC 4 sequences setting them with --osc
+  recolor FROM TO [--assign nearest|one-to-one] [--format table|json|csv]
+                       Map each colour of FROM onto its counterpart in TO, by
+                       role where both have it and by ΔE otherwise
+  vision PALETTE [--deficiency NAME]... [--threshold DELTA_E] [--fix]
+                       List colours that become indistinguishable with
+                       protanopia, deuteranopia, tritanopia or achromatopsia;
+                       with --fix print the palette as TOML with them moved apart
+  stats [PALETTE...]   Summarise palettes for comparison: lightness, chroma,
+                       colour differences, gamut coverage and hues
+  help                 Show this message
+
+Exit status:
+  0  Success, with nothing to report
+  1  The command failed, or found what it checks for: lint found errors, diff
+     and revisions --diff found changes, query matched no colours, vision found
+     confusable colours or could not separate them all with --fix, and ansi
+     could not fill in every ANSI colour
+  2  The command line was not understood";
+
+/// Runs the command in `args`, returning the process exit code.
+pub fn run(args: &[String]) -> i32 {
+    let (command, rest) = match args.split_first() {
+        Some((command, rest)) => (command.as_str(), rest),
+        None => return usage_error("No command given"),
+    };
+    let result = match command {
+        "lint" => lint(rest),
+        "diff" => diff(rest),
+        "variant" => variant(rest),
+        "extract" => extract(rest),
+        "import" => import(rest),
+        "name" => name(rest),
+        "export" => export(rest),
+        "ansi" => ansi(rest),
+        "query" => query(rest),
+        "transform" => transform(rest),
+        "sort" => sort(rest),
+        "revisions" => revisions(rest),
+        "extend" => extend(rest),
+        "recolor" => recolor(rest),
+        "vision" => vision(rest),
+        "stats" => stats(rest),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(0)
+        }
+        other => return usage_error(&format!("Unknown command `{}`", other)),
+    };
+    match result {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("Error: {:#}", error);
+            1
+        }
+    }
+}
+
+fn usage_error(message: &str) -> i32 {
+    eprintln!("{}\n\n{}", message, USAGE);
+    2
+}
+
+/// Loads every palette from the config folder.
+pub fn load_palettes() -> anyhow::Result<Vec<Palette>> {
+    config::reader::read_colour_palettes(&config::config_palette_folder())
+}
+
+/// The palettes named in `names`, or all of them if none are named.
+fn select_palettes(palettes: Vec<Palette>, names: &[String]) -> anyhow::Result<Vec<Palette>> {
+    if names.is_empty() {
+        return Ok(palettes);
+    }
+    let mut selected = Vec::new();
+    let mut palettes = palettes;
+    for name in names {
+        match palettes
+            .iter()
+            .position(|palette| palette.name.eq_ignore_ascii_case(name))
+        {
+            Some(index) => selected.push(palettes.remove(index)),
+            None => anyhow::bail!("No palette named '{}'", name),
+        }
+    }
+    Ok(selected)
+}
+
+fn lint(args: &[String]) -> anyhow::Result<i32> {
+    let palettes = select_palettes(load_palettes()?, args)?;
+    let mut code = 0;
+    for palette in &palettes {
+        let diagnostics = palette.lint();
+        match diagnostics.len() {
+            0 => println!("{}: no problems", palette.name),
+            1 => println!("{}: 1 problem", palette.name),
+            count => println!("{}: {} problems", palette.name, count),
+        }
+        for diagnostic in &diagnostics {
+            println!("  {}", diagnostic);
+            if diagnostic.severity == Severity::Error {
+                code = 1;
+            }
+        }
+    }
+    Ok(code)
+}
+
+/// A palette by name, or the first palette in a `.toml` file.
+fn find_palette(palettes: &[Palette], name: &str) -> anyhow::Result<Palette> {
+    if name.ends_with(".toml") {
+        let path = Path::new(name);
+        return config::reader::read_palette_file(path)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("No palettes in {}", path.display()));
+    }
+    palettes
+        .iter()
+        .find(|palette| palette.name.eq_ignore_ascii_case(name))
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("No palette named '{}'", name))
+}
+
+fn diff(args: &[String]) -> anyhow::Result<i32> {
+    let mut format = "table";
+    let mut names = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next() {
+                Some(value) => format = value,
+                None => anyhow::bail!("--format needs a value"),
+            },
+            _ => names.push(arg.as_str()),
+        }
+    }
+    let (from, to) = match names[..] {
+        [from, to] => (from, to),
+        _ => return Ok(usage_error("diff takes two palettes")),
+    };
+    let palettes = if from.ends_with(".toml") && to.ends_with(".toml") {
+        Vec::new()
+    } else {
+        load_palettes()?
+    };
+    let diff = find_palette(&palettes, from)?.diff(&find_palette(&palettes, to)?);
+    print_diff(&diff, format)
+}
+
+/// Prints `diff` as a table, unified diff or JSON; exits with 1 if anything changed.
+fn print_diff(diff: &PaletteDiff, format: &str) -> anyhow::Result<i32> {
+    match format {
+        "table" => print!("{}", diff.to_table()),
+        "unified" => print!("{}", diff.to_unified()),
+        "json" => println!("{:#}", diff.to_json()),
+        other => anyhow::bail!("Unknown diff format `{}`", other),
+    }
+    Ok(if diff.is_empty() { 0 } else { 1 })
+}
+
+fn variant(args: &[String]) -> anyhow::Result<i32> {
+    let name = match args {
+        [name] => name,
+        _ => return Ok(usage_error("variant takes one palette")),
+    };
+    let palettes = if name.ends_with(".toml") {
+        Vec::new()
+    } else {
+        load_palettes()?
+    };
+    let (variant, adjustments) =
+        find_palette(&palettes, name)?.opposite_variant(&LintOptions::default());
+    for adjustment in &adjustments {
+        eprintln!(
+            "Raised contrast of `{}` from {:.2} to {:.2}",
+            adjustment.name, adjustment.before, adjustment.after
+        );
+    }
+    print!("{}", config::writer::palettes_to_string(&[&variant])?);
+    Ok(0)
+}
+
+fn extract(args: &[String]) -> anyhow::Result<i32> {
+    let mut count = None;
+    let mut quantizer = Quantizer::default();
+    let mut terminal = false;
+    let mut variant = Variant::Dark;
+    let mut name = None;
+    let mut force = false;
+    let mut image = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow::anyhow!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "--count" => count = Some(value()?.parse::<usize>()?),
+            "--method" => quantizer = value()?.parse().map_err(anyhow::Error::msg)?,
+            "--terminal" => terminal = true,
+            "--light" => variant = Variant::Light,
+            "--name" => name = Some(value()?.clone()),
+            "--force" => force = true,
+            _ if image.is_none() => image = Some(Path::new(arg)),
+            _ => return Ok(usage_error("extract takes one image")),
+        }
+    }
+    let image = match image {
+        Some(image) => image,
+        None => return Ok(usage_error("extract needs an image")),
+    };
+    if variant == Variant::Light && !terminal {
+        return Ok(usage_error("--light only applies with --terminal"));
+    }
+    let name = name.unwrap_or_else(|| {
+        image
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Wallpaper".to_string())
+    });
+
+    let pixels = extract::load_pixels(image)?;
+    let swatches = extract::quantize(&pixels, count.unwrap_or(16), quantizer);
+    let mut palette = if terminal {
+        extract::terminal_palette(
+            &name,
+            &swatches,
+            variant,
+            LintOptions::default().min_contrast,
+        )
+    } else {
+        extract::swatch_palette(&name, &swatches)
+    };
+    palette.metadata.wallpaper = Some(image.canonicalize()?);
+    save_new_palette(palette, force)
+}
+
+/// Saves a palette made by a command into the palettes folder, named after it.
+fn save_new_palette(mut palette: Palette, force: bool) -> anyhow::Result<i32> {
+    let file_name = palette
+        .name
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-");
+    // The name must not lead the file out of the palettes folder.
+    if file_name.is_empty() || file_name.starts_with('.') || file_name.contains(['/', '\\', ':']) {
+        anyhow::bail!(
+            "'{}' cannot be used as a file name, choose another with --name",
+            palette.name
+        );
+    }
+    let path = config::config_palette_folder()
+        .join("palettes")
+        .join(format!("{}.toml", file_name));
+    if path.exists() && !force {
+        anyhow::bail!(
+            "{} already exists, pass --force to replace it",
+            path.display()
+        );
+    }
+    // A replaced palette keeps its id, and so its revisions.
+    if let Some(existing) = path
+        .exists()
+        .then(|| config::reader::read_palette_file(&path))
+        .and_then(Result::ok)
+        .and_then(|existing| {
+            existing
+                .into_iter()
+                .find(|found| found.name == palette.name)
+        })
+    {
+        palette.uuid = existing.uuid;
+    }
+    palette.save_to(&path)?;
+    println!("Saved {} colours to {}", palette.len(), path.display());
+    Ok(0)
+}
+
+fn import(args: &[String]) -> anyhow::Result<i32> {
+    let mut name = None;
+    let mut force = false;
+    let mut file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow::anyhow!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "--name" => name = Some(value()?.clone()),
+            "--force" => force = true,
+            _ if file.is_none() => file = Some(Path::new(arg)),
+            _ => return Ok(usage_error("import takes one file")),
+        }
+    }
+    let file = match file {
+        Some(file) => file,
+        None => return Ok(usage_error("import needs a file")),
+    };
+    let name = name.unwrap_or_else(|| {
+        file.file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Imported".to_string())
+    });
+    let palette = config::reader::read_color_list(file, &name)?;
+    save_new_palette(palette, force)
+}
+
+fn name(args: &[String]) -> anyhow::Result<i32> {
+    if args.is_empty() {
+        return Ok(usage_error("name needs a colour"));
+    }
+    let namer = ColorNamer::global();
+    for arg in args {
+        let color = Color::try_from_hex(arg)
+            .ok_or_else(|| anyhow::anyhow!("`{}` is not a hex colour", arg))?;
+        match namer.name(&color) {
+            Some(name) => println!(
+                "{}  {}  ({}, ΔE {:.2})",
+                color.hex(),
+                name,
+                name.dictionary,
+                name.delta_e
+            ),
+            None => println!("{}  -", color.hex()),
+        }
+    }
+    Ok(0)
+}
+
+fn export(args: &[String]) -> anyhow::Result<i32> {
+    let mut format = ExportFormat::Json;
+    let mut separator = None;
+    let mut complete_ansi = false;
+    let mut on_colors = None;
+    let mut name = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow::anyhow!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "--format" => format = value()?.parse().map_err(anyhow::Error::msg)?,
+            "--separator" => separator = Some(value()?.as_str()),
+            "--complete-ansi" => complete_ansi = true,
+            "--on-colors" => on_colors = Some(value()?.parse().map_err(anyhow::Error::msg)?),
+            _ if name.is_none() => name = Some(arg.as_str()),
+            _ => return Ok(usage_error("export takes one palette")),
+        }
+    }
+    let name = match name {
+        Some(name) => name,
+        None => return Ok(usage_error("export needs a palette")),
+    };
+    let palettes = if name.ends_with(".toml") {
+        Vec::new()
+    } else {
+        load_palettes()?
+    };
+    let mut palette = find_palette(&palettes, name)?;
+    if complete_ansi {
+        palette = palette.normalize_ansi(AnsiNaming::default()).0;
+    }
+    if let Some(method) = on_colors {
+        palette.add_on_colors(method, true)?;
+    }
+    print!("{}", export_palette(&palette, format, separator));
+    Ok(0)
+}
+
+fn ansi(args: &[String]) -> anyhow::Result<i32> {
+    let mut naming = AnsiNaming::default();
+    let mut name = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow::anyhow!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "--naming" => naming = value()?.parse().map_err(anyhow::Error::msg)?,
+            _ if name.is_none() => name = Some(arg.as_str()),
+            _ => return Ok(usage_error("ansi takes one palette")),
+        }
+    }
+    let name = match name {
+        Some(name) => name,
+        None => return Ok(usage_error("ansi needs a palette")),
+    };
+    let palettes = if name.ends_with(".toml") {
+        Vec::new()
+    } else {
+        load_palettes()?
+    };
+    let palette = find_palette(&palettes, name)?;
+    if !palette.is_terminal() {
+        anyhow::bail!("{} has no ANSI colours", palette.name);
+    }
+    let (normalized, report) = palette.normalize_ansi(naming);
+    eprintln!("{}", report);
+    print!("{}", config::writer::palettes_to_string(&[&normalized])?);
+    Ok(if report.missing.is_empty() { 0 } else { 1 })
+}
+
+fn query(args: &[String]) -> anyhow::Result<i32> {
+    let (name, query) = match args {
+        [name, query] => (name, query),
+        _ => return Ok(usage_error("query takes a palette and a query")),
+    };
+    let query = query
+        .parse::<Query>()
+        .map_err(|error| anyhow::anyhow!("Bad query: {}", error))?;
+    let palettes = if name.ends_with(".toml") {
+        Vec::new()
+    } else {
+        load_palettes()?
+    };
+    let palette = find_palette(&palettes, name)?;
+    let selected = palette.select(&query).map_err(anyhow::Error::msg)?;
+    let width = selected.iter().map(|name| name.len()).max().unwrap_or(0);
+    for name in &selected {
+        let color = palette.colors[*name];
+        println!(
+            "{:<width$}  {}",
+            name,
+            color_to_string(&color),
+            width = width
+        );
+    }
+    Ok(if selected.is_empty() { 1 } else { 0 })
+}
+
+fn transform(args: &[String]) -> anyhow::Result<i32> {
+    let mut query = None;
+    let mut name = None;
+    let mut palette_name = None;
+    let mut transforms = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow::anyhow!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "--where" => {
+                let text = value()?;
+                let parsed = text
+                    .parse::<Query>()
+                    .map_err(|error| anyhow::anyhow!("Bad query: {}", error))?;
+                query = Some(parsed);
+            }
+            "--name" => name = Some(value()?.clone()),
+            _ if palette_name.is_none() => palette_name = Some(arg.as_str()),
+            _ => transforms.push(arg.parse::<Transform>().map_err(anyhow::Error::msg)?),
+        }
+    }
+    let palette_name = match palette_name {
+        Some(palette_name) if !transforms.is_empty() => palette_name,
+        _ => return Ok(usage_error("transform takes a palette and transforms")),
+    };
+    let palettes = if palette_name.ends_with(".toml") {
+        Vec::new()
+    } else {
+        load_palettes()?
+    };
+    let palette = find_palette(&palettes, palette_name)?;
+    let mut transformed = palette
+        .transformed(&transforms, query.as_ref())
+        .map_err(anyhow::Error::msg)?;
+    if let Some(name) = name {
+        transformed.name = name;
+    }
+    let changed = palette.diff(&transformed).changes.len();
+    eprintln!("Changed {} of {} colours", changed, palette.len());
+    print!("{}", config::writer::palettes_to_string(&[&transformed])?);
+    Ok(0)
+}
+
+fn sort(args: &[String]) -> anyhow::Result<i32> {
+    let (name, order) = match args {
+        [name, order] => (name, order),
+        _ => return Ok(usage_error("sort takes a palette and an order")),
+    };
+    let order = order.parse::<SortOrder>().map_err(anyhow::Error::msg)?;
+    let palettes = if name.ends_with(".toml") {
+        Vec::new()
+    } else {
+        load_palettes()?
+    };
+    let mut palette = find_palette(&palettes, name)?;
+    palette.sort_colors(order);
+    print!("{}", config::writer::palettes_to_string(&[&palette])?);
+    Ok(0)
+}
+
+fn revisions(args: &[String]) -> anyhow::Result<i32> {
+    let mut format = "table";
+    let mut diff = None;
+    let mut restore = None;
+    let mut name = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow::anyhow!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "--diff" => diff = Some(value()?.parse::<usize>()?),
+            "--restore" => restore = Some(value()?.parse::<usize>()?),
+            "--format" => format = value()?.as_str(),
+            _ if name.is_none() => name = Some(arg.as_str()),
+            _ => return Ok(usage_error("revisions takes one palette")),
+        }
+    }
+    let name = match name {
+        Some(name) => name,
+        None => return Ok(usage_error("revisions needs a palette")),
+    };
+    let palettes = if name.ends_with(".toml") {
+        Vec::new()
+    } else {
+        load_palettes()?
+    };
+    let palette = find_palette(&palettes, name)?;
+    let store = RevisionStore::from_program_config();
+    let revisions = store.list(&palette.uuid)?;
+    let revision = |number: usize| {
+        number
+            .checked_sub(1)
+            .and_then(|index| revisions.get(index))
+            .ok_or_else(|| anyhow::anyhow!("{} has no revision {}", palette.name, number))
+    };
+    if let Some(number) = restore {
+        let restored = store.restore(&palette, revision(number)?)?;
+        println!(
+            "Restored {} to revision {} ({})",
+            restored.name,
+            number,
+            revision(number)?.datetime()
+        );
+        return Ok(0);
+    }
+    if let Some(number) = diff {
+        return print_diff(&revision(number)?.diff(&palette)?, format);
+    }
+    if revisions.is_empty() {
+        println!("{} has no revisions", palette.name);
+    }
+    for (index, revision) in revisions.iter().enumerate() {
+        let summary = revision.summary(&palette);
+        println!("{:>3}  {}  {}", index + 1, revision.datetime(), summary);
+    }
+    Ok(0)
+}
+
+fn extend(args: &[String]) -> anyhow::Result<i32> {
+    let mut osc = false;
+    let mut name = None;
+    for arg in args {
+        match arg.as_str() {
+            "--osc" => osc = true,
+            _ if name.is_none() => name = Some(arg.as_str()),
+            _ => return Ok(usage_error("extend takes one palette")),
+        }
+    }
+    let name = match name {
+        Some(name) => name,
+        None => return Ok(usage_error("extend needs a palette")),
+    };
+    let palettes = if name.ends_with(".toml") {
+        Vec::new()
+    } else {
+        load_palettes()?
+    };
+    let palette = find_palette(&palettes, name)?;
+    if osc {
+        print!("{}", palette.osc4_sequences().map_err(anyhow::Error::msg)?);
+    } else {
+        let extended = palette.extended_256().map_err(anyhow::Error::msg)?;
+        print!("{}", config::writer::palettes_to_string(&[&extended])?);
+    }
+    Ok(0)
+}
+
+fn recolor(args: &[String]) -> anyhow::Result<i32> {
+    let mut assignment = Assignment::default();
+    let mut format = "table";
+    let mut names = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow::anyhow!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "--assign" => assignment = value()?.parse().map_err(anyhow::Error::msg)?,
+            "--format" => format = value()?.as_str(),
+            _ => names.push(arg.as_str()),
+        }
+    }
+    let (from, to) = match names[..] {
+        [from, to] => (from, to),
+        _ => return Ok(usage_error("recolor takes two palettes")),
+    };
+    let palettes = if from.ends_with(".toml") && to.ends_with(".toml") {
+        Vec::new()
+    } else {
+        load_palettes()?
+    };
+    let map = find_palette(&palettes, from)?.recolor_map(&find_palette(&palettes, to)?, assignment);
+    match format {
+        "table" => print!("{}", map.to_table()),
+        "json" => println!("{:#}", map.to_json()),
+        "csv" => print!("{}", map.to_csv()),
+        other => anyhow::bail!("Unknown recolor format `{}`", other),
+    }
+    Ok(0)
+}
+
+fn vision(args: &[String]) -> anyhow::Result<i32> {
+    let mut options = VisionOptions::default();
+    let mut deficiencies = Vec::new();
+    let mut fix = false;
+    let mut name = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow::anyhow!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "--deficiency" => {
+                deficiencies.push(value()?.parse::<Deficiency>().map_err(anyhow::Error::msg)?)
+            }
+            "--threshold" => options.min_delta_e = value()?.parse::<f64>()?,
+            "--fix" => fix = true,
+            _ if name.is_none() => name = Some(arg.as_str()),
+            _ => return Ok(usage_error("vision takes one palette")),
+        }
+    }
+    let name = match name {
+        Some(name) => name,
+        None => return Ok(usage_error("vision needs a palette")),
+    };
+    if !deficiencies.is_empty() {
+        options.deficiencies = deficiencies;
+    }
+    let palettes = if name.ends_with(".toml") {
+        Vec::new()
+    } else {
+        load_palettes()?
+    };
+    let palette = find_palette(&palettes, name)?;
+    if fix {
+        let (safe, report) = palette.colorblind_safe(&options);
+        eprintln!("{}", report);
+        print!("{}", config::writer::palettes_to_string(&[&safe])?);
+        return Ok(if report.remaining.is_empty() { 0 } else { 1 });
+    }
+    let pairs = palette.vision_check(&options);
+    match pairs.len() {
+        0 => println!("{}: no confusable colours", palette.name),
+        1 => println!("{}: 1 confusable pair", palette.name),
+        count => println!("{}: {} confusable pairs", palette.name, count),
+    }
+    for pair in &pairs {
+        println!("  {}", pair);
+    }
+    Ok(if pairs.is_empty() { 0 } else { 1 })
+}
+
+fn stats(args: &[String]) -> anyhow::Result<i32> {
+    let palettes = select_palettes(load_palettes()?, args)?;
+    for (index, palette) in palettes.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        println!("{}", palette.name);
+        print!("{}", palette.stats());
+    }
+    Ok(0)
+}
@@ -1,13 +1,48 @@
 use super::ColorType;
-use num::Float;
 use slint::format;
 use slint::Color as Slint_Color;
 use std::ops::Rem;
+use std::str::FromStr;
 use winnow::stream::SliceLen;
 pub const PRECISION_VAL: u32 = 5;
 
+pub const BLACK: Color = Color {
+    red: 0,
+    green: 0,
+    blue: 0,
+    opacity: 255,
+};
+pub const WHITE: Color = Color {
+    red: 255,
+    green: 255,
+    blue: 255,
+    opacity: 255,
+};
+
+/// Contrast algorithm used when judging text readability.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ContrastMethod {
+    /// WCAG 2.x contrast ratio, 1.0-21.0.
+    #[default]
+    Wcag,
+    /// APCA lightness contrast (Lc), reported as an absolute value 0-~108.
+    Apca,
+}
+
+impl FromStr for ContrastMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "wcag" => Ok(ContrastMethod::Wcag),
+            "apca" => Ok(ContrastMethod::Apca),
+            other => Err(std::format!("Unknown contrast method: {}", other)),
+        }
+    }
+}
+
 /// # Color Struct
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct Color {
     /// red: 0-255
     pub red: u8,
@@ -19,7 +54,6 @@ pub struct Color {
     pub opacity: u8,
 }
 
-#[allow(dead_code)]
 impl Color {
     /// If you are using solid colors, use [`Self::new_solid`].
     pub fn new(red: u8, green: u8, blue: u8, opacity: u8) -> Self {
@@ -43,6 +77,7 @@ impl Color {
         (self.red, self.green, self.blue)
     }
 
+    #[allow(dead_code)]
     pub fn str_rgb(&self) -> String {
         format!("{},{},{}", self.red, self.blue, self.blue).to_string()
     }
@@ -101,6 +136,7 @@ impl Color {
         let a = round_to(self.opacity as f64 / 255_f64, PRECISION_VAL);
         (h, s, l, a)
     }
+    #[allow(dead_code)]
     pub fn str_hsl(&self) -> String {
         let hsl = self.hsl();
         format!("hsl({},{},{})", hsl.0, hsl.1, hsl.2).to_string()
@@ -130,6 +166,7 @@ impl Color {
         .to_string()
     }
 
+    #[allow(dead_code)]
     pub fn from_rgba(red: u8, green: u8, blue: u8, alpha: u8) -> Self {
         Color {
             red,
@@ -138,6 +175,7 @@ impl Color {
             opacity: alpha,
         }
     }
+    #[allow(dead_code)]
     pub fn from_rgb(red: u8, green: u8, blue: u8) -> Self {
         Color {
             red,
@@ -147,6 +185,15 @@ impl Color {
         }
     }
 
+    /// Colour from `#RGB`, `#RRGGBB` or `#RRGGBBAA`, the `#` optional, or `None` if `hex`
+    /// is not one of those.
+    pub fn try_from_hex(hex: &str) -> Option<Self> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        let valid =
+            matches!(digits.len(), 3 | 6 | 8) && digits.chars().all(|c| c.is_ascii_hexdigit());
+        valid.then(|| Color::from_hex(digits))
+    }
+
     pub fn from_hex(hex: &str) -> Self {
         let hex_color: String = hex.trim_start_matches("#").to_uppercase();
 
@@ -182,32 +229,341 @@ impl Color {
         colour
     }
 
-    pub fn from_hsl() -> Self {
-        todo!();
+    /// `hue` in degrees, `saturation` and `lightness` from 0.0 to 1.0.
+    #[allow(dead_code)]
+    pub fn from_hsl(hue: f64, saturation: f64, lightness: f64) -> Self {
+        Self::from_hsla(hue, saturation, lightness, 1.0)
     }
 
-    pub fn from_hsla() -> Self {
-        todo!();
+    /// `hue` in degrees, `saturation`, `lightness` and `alpha` from 0.0 to 1.0.
+    pub fn from_hsla(hue: f64, saturation: f64, lightness: f64, alpha: f64) -> Self {
+        let hue = hue.rem_euclid(360.0);
+        let saturation = saturation.clamp(0.0, 1.0);
+        let lightness = lightness.clamp(0.0, 1.0);
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        let x = chroma * (1.0 - ((hue / 60.0).rem(2.0) - 1.0).abs());
+        let m = lightness - chroma / 2.0;
+        let (r, g, b) = match hue {
+            h if h < 60.0 => (chroma, x, 0.0),
+            h if h < 120.0 => (x, chroma, 0.0),
+            h if h < 180.0 => (0.0, chroma, x),
+            h if h < 240.0 => (0.0, x, chroma),
+            h if h < 300.0 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let to_u8 = |c: f64| ((c + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+        Color::new(to_u8(r), to_u8(g), to_u8(b), unit_to_u8(alpha))
     }
 
     #[allow(clippy::wrong_self_convention)]
-    pub fn to_colortype(&self, name: &str) -> ColorType {
+    pub fn to_colortype(&self, name: &str, text_color: Color) -> ColorType {
         ColorType {
             color: self.to_slint(),
+            text_color: text_color.to_slint(),
             name: name.into(),
             rgb: self.str_rgba().into(),
             hex: self.hex().into(),
             hsl: self.str_hsla().into(),
+            role: "".into(),
+            expression: "".into(),
+            description: "".into(),
         }
     }
 
+    #[allow(clippy::wrong_self_convention)]
     pub fn to_slint(&self) -> Slint_Color {
         Slint_Color::from_argb_u8(self.opacity, self.red, self.green, self.blue)
     }
 
+    #[allow(clippy::wrong_self_convention)]
     pub fn to_opaque(&self) -> Self {
         Self::new_solid(self.red, self.green, self.blue)
     }
+
+    /// Raises HSL lightness by `amount` (0.0-1.0), like Sass `lighten`.
+    pub fn lighten(&self, amount: f64) -> Self {
+        let (h, s, l, a) = self.hsla();
+        Self::from_hsla(h, s, l + amount, a)
+    }
+
+    /// Lowers HSL lightness by `amount` (0.0-1.0), like Sass `darken`.
+    pub fn darken(&self, amount: f64) -> Self {
+        self.lighten(-amount)
+    }
+
+    /// Raises HSL saturation by `amount` (0.0-1.0).
+    pub fn saturate(&self, amount: f64) -> Self {
+        let (h, s, l, a) = self.hsla();
+        Self::from_hsla(h, s + amount, l, a)
+    }
+
+    /// Lowers HSL saturation by `amount` (0.0-1.0).
+    pub fn desaturate(&self, amount: f64) -> Self {
+        self.saturate(-amount)
+    }
+
+    /// Blends towards `other` in sRGB; `weight` is the proportion of `other`, 0.0-1.0.
+    pub fn mix(&self, other: &Color, weight: f64) -> Self {
+        let weight = weight.clamp(0.0, 1.0);
+        let blend = |a: u8, b: u8| (a as f64 * (1.0 - weight) + b as f64 * weight).round() as u8;
+        Color::new(
+            blend(self.red, other.red),
+            blend(self.green, other.green),
+            blend(self.blue, other.blue),
+            blend(self.opacity, other.opacity),
+        )
+    }
+
+    /// The same colour with opacity set to `alpha`, 0.0-1.0.
+    pub fn with_alpha(&self, alpha: f64) -> Self {
+        Color::new(self.red, self.green, self.blue, unit_to_u8(alpha))
+    }
+
+    /// Linear-light sRGB channels, 0.0-1.0.
+    pub fn linear_rgb(&self) -> (f64, f64, f64) {
+        (
+            srgb_to_linear(self.red),
+            srgb_to_linear(self.green),
+            srgb_to_linear(self.blue),
+        )
+    }
+
+    /// Colour from linear-light sRGB channels, clipped to the sRGB gamut.
+    pub fn from_linear_rgb(red: f64, green: f64, blue: f64, opacity: u8) -> Self {
+        Color::new(
+            linear_to_srgb(red),
+            linear_to_srgb(green),
+            linear_to_srgb(blue),
+            opacity,
+        )
+    }
+
+    /// CIE XYZ (D65), Y from 0.0 to 1.0.
+    pub fn xyz(&self) -> (f64, f64, f64) {
+        let (r, g, b) = self.linear_rgb();
+        (
+            0.4124564 * r + 0.3575761 * g + 0.1804375 * b,
+            0.2126729 * r + 0.7151522 * g + 0.0721750 * b,
+            0.0193339 * r + 0.1191920 * g + 0.9503041 * b,
+        )
+    }
+
+    /// CIELAB (D65): L from 0.0 to 100.0.
+    pub fn lab(&self) -> (f64, f64, f64) {
+        let (x, y, z) = self.xyz();
+        let f = |t: f64| {
+            if t > 216.0 / 24389.0 {
+                t.cbrt()
+            } else {
+                (24389.0 / 27.0 * t + 16.0) / 116.0
+            }
+        };
+        let (fx, fy, fz) = (f(x / D65.0), f(y / D65.1), f(z / D65.2));
+        (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+    }
+
+    /// Colour from CIELAB (D65), clipped to the sRGB gamut.
+    pub fn from_lab(lightness: f64, a: f64, b: f64) -> Self {
+        let fy = (lightness + 16.0) / 116.0;
+        let fx = fy + a / 500.0;
+        let fz = fy - b / 200.0;
+        let f_inv = |t: f64| {
+            if t.powi(3) > 216.0 / 24389.0 {
+                t.powi(3)
+            } else {
+                (116.0 * t - 16.0) / (24389.0 / 27.0)
+            }
+        };
+        let (x, y, z) = (f_inv(fx) * D65.0, f_inv(fy) * D65.1, f_inv(fz) * D65.2);
+        Color::from_linear_rgb(
+            3.2404542 * x - 1.5371385 * y - 0.4985314 * z,
+            -0.9692660 * x + 1.8760108 * y + 0.0415560 * z,
+            0.0556434 * x - 0.2040259 * y + 1.0572252 * z,
+            255,
+        )
+    }
+
+    /// Oklab: L from 0.0 to 1.0, a and b roughly -0.4 to 0.4.
+    pub fn oklab(&self) -> (f64, f64, f64) {
+        let (r, g, b) = self.linear_rgb();
+        let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+        let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+        let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+        (
+            0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+            1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+            0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+        )
+    }
+
+    /// Colour from Oklab, clipped to the sRGB gamut.
+    pub fn from_oklab(lightness: f64, a: f64, b: f64) -> Self {
+        let (r, g, b) = oklab_to_linear_rgb(lightness, a, b);
+        Color::from_linear_rgb(r, g, b, 255)
+    }
+
+    /// Oklch: lightness 0.0-1.0, chroma 0.0-~0.37 and hue in degrees.
+    pub fn oklch(&self) -> (f64, f64, f64) {
+        let (l, a, b) = self.oklab();
+        let chroma = (a * a + b * b).sqrt();
+        let hue = if chroma < 1e-6 {
+            0.0
+        } else {
+            b.atan2(a).to_degrees().rem_euclid(360.0)
+        };
+        (l, chroma, hue)
+    }
+
+    /// Colour from Oklch, reducing chroma until it fits the sRGB gamut so that
+    /// lightness and hue are kept.
+    pub fn from_oklch(lightness: f64, chroma: f64, hue: f64) -> Self {
+        let lightness = lightness.clamp(0.0, 1.0);
+        let to_lab = |chroma: f64| {
+            let hue = hue.to_radians();
+            (lightness, chroma * hue.cos(), chroma * hue.sin())
+        };
+        let in_gamut = |chroma: f64| {
+            let (l, a, b) = to_lab(chroma);
+            let (r, g, b) = oklab_to_linear_rgb(l, a, b);
+            [r, g, b].iter().all(|c| (-1e-4..=1.0 + 1e-4).contains(c))
+        };
+        let mut chroma = chroma.max(0.0);
+        if !in_gamut(chroma) {
+            let (mut low, mut high) = (0.0, chroma);
+            for _ in 0..24 {
+                let mid = (low + high) / 2.0;
+                if in_gamut(mid) {
+                    low = mid;
+                } else {
+                    high = mid;
+                }
+            }
+            chroma = low;
+        }
+        let (l, a, b) = to_lab(chroma);
+        Color::from_oklab(l, a, b)
+    }
+
+    /// CIEDE2000 colour difference; about 1.0 is just noticeable, above 5.0 clearly different.
+    pub fn delta_e(&self, other: &Color) -> f64 {
+        ciede2000(self.lab(), other.lab())
+    }
+
+    /// Euclidean distance in Oklab, roughly 0.02 is just noticeable.
+    pub fn delta_e_ok(&self, other: &Color) -> f64 {
+        let (l1, a1, b1) = self.oklab();
+        let (l2, a2, b2) = other.oklab();
+        ((l1 - l2).powi(2) + (a1 - a2).powi(2) + (b1 - b2).powi(2)).sqrt()
+    }
+
+    /// WCAG relative luminance, 0.0 (black) to 1.0 (white). Opacity is ignored.
+    pub fn relative_luminance(&self) -> f64 {
+        let (r, g, b) = self.linear_rgb();
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+
+    /// WCAG 2.x contrast ratio between two colours, 1.0 to 21.0.
+    pub fn contrast_ratio(&self, other: &Color) -> f64 {
+        let l1 = self.relative_luminance();
+        let l2 = other.relative_luminance();
+        (l1.max(l2) + 0.05) / (l1.min(l2) + 0.05)
+    }
+
+    /// APCA (SAPC 0.0.98G) lightness contrast of `self` as text on `background`.
+    ///
+    /// Positive for dark text on a light background, negative for light text on a dark one.
+    pub fn apca_contrast(&self, background: &Color) -> f64 {
+        let screen_luminance = |color: &Color| {
+            let c = |channel: u8| (channel as f64 / 255_f64).powf(2.4);
+            0.2126729 * c(color.red) + 0.7151522 * c(color.green) + 0.0721750 * c(color.blue)
+        };
+        let soft_clamp = |y: f64| {
+            if y > 0.022 {
+                y
+            } else {
+                y + (0.022 - y).powf(1.414)
+            }
+        };
+        let text_y = soft_clamp(screen_luminance(self));
+        let bg_y = soft_clamp(screen_luminance(background));
+        if (bg_y - text_y).abs() < 0.0005 {
+            return 0.0;
+        }
+        let contrast = if bg_y > text_y {
+            let sapc = (bg_y.powf(0.56) - text_y.powf(0.57)) * 1.14;
+            if sapc < 0.1 {
+                0.0
+            } else {
+                sapc - 0.027
+            }
+        } else {
+            let sapc = (bg_y.powf(0.65) - text_y.powf(0.62)) * 1.14;
+            if sapc > -0.1 {
+                0.0
+            } else {
+                sapc + 0.027
+            }
+        };
+        contrast * 100.0
+    }
+
+    /// Readability score of `self` as text on `background`; higher is more readable.
+    pub fn contrast(&self, background: &Color, method: ContrastMethod) -> f64 {
+        match method {
+            ContrastMethod::Wcag => self.contrast_ratio(background),
+            ContrastMethod::Apca => self.apca_contrast(background).abs(),
+        }
+    }
+
+    /// `self` moved in Oklch lightness away from `background` until its contrast reaches
+    /// `target`, keeping hue, chroma and opacity. Stops at black or white if it cannot.
+    pub fn with_contrast(&self, background: &Color, target: f64, method: ContrastMethod) -> Self {
+        let background = background.to_opaque();
+        let step = if background.oklab().0 < 0.5 {
+            0.005
+        } else {
+            -0.005
+        };
+        let (mut lightness, chroma, hue) = self.oklch();
+        let mut adjusted = *self;
+        while adjusted.to_opaque().contrast(&background, method) < target
+            && (0.0..=1.0).contains(&(lightness + step))
+        {
+            lightness += step;
+            adjusted = Color {
+                opacity: self.opacity,
+                ..Color::from_oklch(lightness, chroma, hue)
+            };
+        }
+        adjusted
+    }
+
+    /// Picks the most readable text colour for `self` used as a background.
+    ///
+    /// Candidates are compared as opaque colours; with no candidates the choice is
+    /// between [`BLACK`] and [`WHITE`].
+    pub fn best_text_color(&self, candidates: &[Color], method: ContrastMethod) -> Color {
+        let background = self.to_opaque();
+        let default = [BLACK, WHITE];
+        let candidates = if candidates.is_empty() {
+            &default[..]
+        } else {
+            candidates
+        };
+        candidates
+            .iter()
+            .map(|candidate| candidate.to_opaque())
+            .map(|candidate| (candidate, candidate.contrast(&background, method)))
+            .fold(
+                None,
+                |best: Option<(Color, f64)>, (candidate, score)| match best {
+                    Some((_, best_score)) if best_score >= score => best,
+                    _ => Some((candidate, score)),
+                },
+            )
+            .map(|(color, _)| color)
+            .unwrap_or(BLACK)
+    }
 }
 
 fn double_letter(letter: &str) -> String {
@@ -217,6 +573,107 @@ fn double_letter(letter: &str) -> String {
     format!("{}{}", letter, letter).to_string()
 }
 
+/// D65 reference white in CIE XYZ.
+const D65: (f64, f64, f64) = (0.95047, 1.0, 1.08883);
+
+fn srgb_to_linear(channel: u8) -> f64 {
+    let c = channel as f64 / 255_f64;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f64) -> u8 {
+    let value = value.clamp(0.0, 1.0);
+    let c = if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    };
+    unit_to_u8(c)
+}
+
+/// CIEDE2000 difference between two CIELAB colours.
+pub(crate) fn ciede2000(first: (f64, f64, f64), second: (f64, f64, f64)) -> f64 {
+    let (l1, a1, b1) = first;
+    let (l2, a2, b2) = second;
+    let c1 = (a1 * a1 + b1 * b1).sqrt();
+    let c2 = (a2 * a2 + b2 * b2).sqrt();
+    let c_bar = (c1 + c2) / 2.0;
+    let g = 0.5 * (1.0 - (c_bar.powi(7) / (c_bar.powi(7) + 25_f64.powi(7))).sqrt());
+    let a1p = a1 * (1.0 + g);
+    let a2p = a2 * (1.0 + g);
+    let c1p = (a1p * a1p + b1 * b1).sqrt();
+    let c2p = (a2p * a2p + b2 * b2).sqrt();
+    let hue = |b: f64, a: f64| {
+        if a == 0.0 && b == 0.0 {
+            0.0
+        } else {
+            b.atan2(a).to_degrees().rem_euclid(360.0)
+        }
+    };
+    let h1p = hue(b1, a1p);
+    let h2p = hue(b2, a2p);
+
+    let delta_lp = l2 - l1;
+    let delta_cp = c2p - c1p;
+    let delta_hp = if c1p * c2p == 0.0 {
+        0.0
+    } else if (h2p - h1p).abs() <= 180.0 {
+        h2p - h1p
+    } else if h2p - h1p > 180.0 {
+        h2p - h1p - 360.0
+    } else {
+        h2p - h1p + 360.0
+    };
+    let delta_big_hp = 2.0 * (c1p * c2p).sqrt() * (delta_hp / 2.0).to_radians().sin();
+
+    let l_bar_p = (l1 + l2) / 2.0;
+    let c_bar_p = (c1p + c2p) / 2.0;
+    let h_bar_p = if c1p * c2p == 0.0 {
+        h1p + h2p
+    } else if (h1p - h2p).abs() <= 180.0 {
+        (h1p + h2p) / 2.0
+    } else if h1p + h2p < 360.0 {
+        (h1p + h2p + 360.0) / 2.0
+    } else {
+        (h1p + h2p - 360.0) / 2.0
+    };
+    let t = 1.0 - 0.17 * (h_bar_p - 30.0).to_radians().cos()
+        + 0.24 * (2.0 * h_bar_p).to_radians().cos()
+        + 0.32 * (3.0 * h_bar_p + 6.0).to_radians().cos()
+        - 0.20 * (4.0 * h_bar_p - 63.0).to_radians().cos();
+    let delta_theta = 30.0 * (-((h_bar_p - 275.0) / 25.0).powi(2)).exp();
+    let r_c = 2.0 * (c_bar_p.powi(7) / (c_bar_p.powi(7) + 25_f64.powi(7))).sqrt();
+    let s_l = 1.0 + (0.015 * (l_bar_p - 50.0).powi(2)) / (20.0 + (l_bar_p - 50.0).powi(2)).sqrt();
+    let s_c = 1.0 + 0.045 * c_bar_p;
+    let s_h = 1.0 + 0.015 * c_bar_p * t;
+    let r_t = -(2.0 * delta_theta).to_radians().sin() * r_c;
+
+    ((delta_lp / s_l).powi(2)
+        + (delta_cp / s_c).powi(2)
+        + (delta_big_hp / s_h).powi(2)
+        + r_t * (delta_cp / s_c) * (delta_big_hp / s_h))
+        .sqrt()
+}
+
+fn oklab_to_linear_rgb(lightness: f64, a: f64, b: f64) -> (f64, f64, f64) {
+    let l = (lightness + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m = (lightness - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s = (lightness - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+    (
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    )
+}
+
+fn unit_to_u8(value: f64) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
 fn round_to(number: f64, n: u32) -> f64 {
     let precision: f64 = 10_u32.pow(n) as f64;
     (number * precision).round() / precision
@@ -296,4 +753,116 @@ mod test_colour {
         assert_eq!(Color::from_hex("#FFFFFFFF").rgba(), (255, 255, 255, 255));
         assert_eq!(Color::from_hex("#7F1F00").rgb(), (127, 31, 0));
     }
+
+    #[test]
+    fn from_hsl() {
+        assert_eq!(Color::from_hsl(0.0, 0.0, 0.0), BLACK);
+        assert_eq!(Color::from_hsl(0.0, 0.0, 1.0), WHITE);
+        assert_eq!(Color::from_hsl(120.0, 1.0, 0.25).rgb(), (0, 128, 0));
+        assert_eq!(Color::from_hsl(-60.0, 1.0, 0.5).rgb(), (255, 0, 255));
+        assert_eq!(
+            Color::from_hsla(240.0, 1.0, 0.5, 0.5).rgba(),
+            (0, 0, 255, 128)
+        );
+        let color = Color::from_hex("#5A6592");
+        let (h, s, l) = color.hsl();
+        assert_eq!(Color::from_hsl(h, s, l), color);
+    }
+
+    #[test]
+    fn adjustments() {
+        let red = Color::from_hex("#F00");
+        assert_eq!(red.lighten(0.25).hex(), "#FF8080");
+        assert_eq!(red.darken(0.25).hex(), "#800000");
+        assert_eq!(red.darken(1.0), BLACK);
+        assert_eq!(red.desaturate(1.0).hex(), "#808080");
+        assert_eq!(Color::from_hex("#BF4040").saturate(0.5).hex(), "#FF0000");
+        assert_eq!(BLACK.mix(&WHITE, 0.2).hex(), "#333333");
+        assert_eq!(red.with_alpha(0.5).hexa(), "#FF000080");
+    }
+
+    #[test]
+    fn lab() {
+        let (l, a, b) = Color::from_hex("#FF0000").lab();
+        assert_eq!(
+            (round_to(l, 2), round_to(a, 2), round_to(b, 2)),
+            (53.24, 80.09, 67.2)
+        );
+        assert_eq!(round_to(WHITE.lab().0, 2), 100.0);
+        let color = Color::from_hex("#5A6592");
+        let (l, a, b) = color.lab();
+        assert_eq!(Color::from_lab(l, a, b), color);
+    }
+
+    #[test]
+    fn oklab() {
+        let (l, a, b) = WHITE.oklab();
+        assert_eq!(
+            (round_to(l, 3), round_to(a, 3), round_to(b, 3)),
+            (1.0, 0.0, 0.0)
+        );
+        let (l, c, h) = Color::from_hex("#FF0000").oklch();
+        assert_eq!(
+            (round_to(l, 3), round_to(c, 3), round_to(h, 1)),
+            (0.628, 0.258, 29.2)
+        );
+        let color = Color::from_hex("#C14039");
+        let (l, a, b) = color.oklab();
+        assert_eq!(Color::from_oklab(l, a, b), color);
+        let (l, c, h) = color.oklch();
+        assert_eq!(Color::from_oklch(l, c, h), color);
+        let (l, _, h) = Color::from_oklch(0.7, 0.4, 150.0).oklch();
+        assert_eq!((round_to(l, 2), round_to(h, 0)), (0.7, 150.0));
+    }
+
+    #[test]
+    fn delta_e() {
+        // Pairs from Sharma, Wu and Dalal's CIEDE2000 test data.
+        let pairs = [
+            ((50.0, 2.6772, -79.7751), (50.0, 0.0, -82.7485), 2.0425),
+            ((50.0, -1.3802, -84.2814), (50.0, 0.0, -82.7485), 1.0),
+            ((50.0, 2.5, 0.0), (50.0, 0.0, -2.5), 4.3065),
+            ((50.0, 2.5, 0.0), (73.0, 25.0, -18.0), 27.1492),
+            ((2.0776, 0.0795, -1.135), (0.9033, -0.0636, -0.5514), 0.9082),
+        ];
+        for (first, second, expected) in pairs {
+            assert_eq!(round_to(ciede2000(first, second), 4), expected);
+        }
+        assert_eq!(BLACK.delta_e(&BLACK), 0.0);
+        assert_eq!(round_to(BLACK.delta_e(&WHITE), 1), 100.0);
+        assert_eq!(round_to(BLACK.delta_e_ok(&WHITE), 3), 1.0);
+    }
+
+    #[test]
+    fn contrast_ratio() {
+        assert_eq!(round_to(BLACK.contrast_ratio(&WHITE), 2), 21.0);
+        assert_eq!(WHITE.contrast_ratio(&WHITE), 1.0);
+        assert_eq!(
+            round_to(Color::from_hex("#777").contrast_ratio(&WHITE), 2),
+            4.48
+        );
+    }
+
+    #[test]
+    fn apca_contrast() {
+        assert_eq!(round_to(BLACK.apca_contrast(&WHITE), 1), 106.0);
+        assert_eq!(round_to(WHITE.apca_contrast(&BLACK), 1), -107.9);
+        assert_eq!(
+            round_to(Color::from_hex("#888").apca_contrast(&WHITE), 1),
+            63.1
+        );
+        assert_eq!(WHITE.apca_contrast(&WHITE), 0.0);
+    }
+
+    #[test]
+    fn best_text_color() {
+        let yellow = Color::from_hex("#FFFF00");
+        let navy = Color::from_hex("#000080");
+        assert_eq!(yellow.best_text_color(&[], ContrastMethod::Wcag), BLACK);
+        assert_eq!(navy.best_text_color(&[], ContrastMethod::Apca), WHITE);
+        assert_eq!(
+            yellow.best_text_color(&[WHITE, navy], ContrastMethod::Wcag),
+            navy
+        );
+    }
 }
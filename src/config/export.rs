@@ -0,0 +1,144 @@
+use std::{fmt, str::FromStr};
+
+use serde_json::{Map, Value};
+
+use super::writer::color_to_string;
+use crate::palette::{ColorRole, Palette};
+
+/// Roles exported under their own name when no colour is called that, as terminal
+/// theme formats expect `background`, `foreground` and `cursor` entries.
+const EXPORTED_ROLES: [ColorRole; 6] = [
+    ColorRole::Background,
+    ColorRole::Foreground,
+    ColorRole::Cursor,
+    ColorRole::CursorText,
+    ColorRole::SelectionBackground,
+    ColorRole::SelectionForeground,
+];
+
+/// Formats a palette can be exported to for use outside this program.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ExportFormat {
+    /// A flat JSON object of name to hex colour.
+    Json,
+    /// CSS custom properties on `:root`.
+    Css,
+    /// Shell variable assignments, as in pywal's `colors.sh`.
+    Shell,
+}
+
+impl ExportFormat {
+    /// Separator joining group and colour names when none is given.
+    pub fn default_separator(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => ".",
+            ExportFormat::Css => "-",
+            ExportFormat::Shell => "_",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "css" => Ok(ExportFormat::Css),
+            "sh" | "shell" => Ok(ExportFormat::Shell),
+            other => Err(format!("Unknown export format: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportFormat::Json => write!(f, "json"),
+            ExportFormat::Css => write!(f, "css"),
+            ExportFormat::Shell => write!(f, "sh"),
+        }
+    }
+}
+
+/// The palette in `format`, grouped colours flattened with `separator` or the format's default.
+/// The colours filling the terminal roles follow, named after their role.
+pub fn export_palette(palette: &Palette, format: ExportFormat, separator: Option<&str>) -> String {
+    let mut colors = palette.flattened(separator.unwrap_or(format.default_separator()));
+    for role in EXPORTED_ROLES {
+        if let Some(color) = palette.role(role) {
+            colors.entry(role.to_string()).or_insert(color);
+        }
+    }
+    match format {
+        ExportFormat::Json => {
+            let object = colors
+                .iter()
+                .map(|(name, color)| (name.clone(), Value::String(color_to_string(color))))
+                .collect::<Map<_, _>>();
+            format!("{:#}\n", Value::Object(object))
+        }
+        ExportFormat::Css => {
+            let properties = colors
+                .iter()
+                .map(|(name, color)| format!("    --{}: {};\n", name, color_to_string(color)))
+                .collect::<String>();
+            format!(":root {{\n{}}}\n", properties)
+        }
+        ExportFormat::Shell => colors
+            .iter()
+            .map(|(name, color)| {
+                let variable = name
+                    .chars()
+                    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                    .collect::<String>();
+                format!("{}='{}'\n", variable, color_to_string(color))
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod export_tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn flattens_groups() {
+        let palette = testing::palette("Grouped", &[("background", "#000"), ("ui.border", "#333")]);
+        assert_eq!(
+            export_palette(&palette, ExportFormat::Css, None),
+            ":root {\n    --background: #000000;\n    --ui-border: #333333;\n}\n"
+        );
+        assert_eq!(
+            export_palette(&palette, ExportFormat::Shell, None),
+            "background='#000000'\nui_border='#333333'\n"
+        );
+        assert_eq!(
+            export_palette(&palette, ExportFormat::Json, Some("/")),
+            "{\n  \"background\": \"#000000\",\n  \"ui/border\": \"#333333\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn exports_roles() {
+        let mut palette = testing::palette(
+            "Roles",
+            &[
+                ("base", "#1E1E2E"),
+                ("text", "#CDD6F4"),
+                ("ui.caret", "#F5E0DC"),
+            ],
+        );
+        palette.assign_role(ColorRole::Background, "base");
+        palette.assign_role(ColorRole::Cursor, "ui.caret");
+        assert_eq!(
+            export_palette(&palette, ExportFormat::Shell, None),
+            "base='#1E1E2E'\ntext='#CDD6F4'\nui_caret='#F5E0DC'\n\
+             background='#1E1E2E'\nforeground='#CDD6F4'\ncursor='#F5E0DC'\n"
+        );
+        let css = export_palette(&palette, ExportFormat::Css, None);
+        assert!(css.contains("    --background: #1E1E2E;\n"), "{}", css);
+        assert!(!css.contains("--selection-background"), "{}", css);
+    }
+}
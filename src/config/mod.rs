@@ -4,15 +4,16 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use reader::get_pallete_filesnames;
-use slint::format;
-
+pub mod export;
 pub mod reader;
+pub mod revision;
+pub mod state;
 pub mod writer;
 
 pub const DEFAULT_CONFIG_PATH: &str = "~/.config";
 pub const PROGRAM_NAME: &str = "color-palette";
 
+#[allow(dead_code)]
 pub struct Config {
     current_settings: BTreeMap<String, String>,
     default_settings: BTreeMap<String, String>,
@@ -32,17 +33,25 @@ impl Default for Config {
 pub fn config_palette_folder() -> PathBuf {
     let conf_path = find_config_path().unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH));
     let config_folder = ensure_config_folder_exists(&conf_path).expect("Unable to create a config folder, please set 'XDG_CONFIG_HOME' in environment variables before running again.");
-    let program_config_file = ensure_program_config_file_exists(&config_folder).expect("");
-    let palette_folder =
+    let _program_config_file = ensure_program_config_file_exists(&config_folder).expect("");
+    let _palette_folder =
         ensure_palette_folder_exists(&config_folder).expect("Folder should be good");
 
     config_folder
 }
+/// Path of the program settings file, created if missing.
+pub fn program_config_file() -> PathBuf {
+    let conf_path = find_config_path().unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH));
+    let config_folder = ensure_config_folder_exists(&conf_path).expect("Unable to create a config folder, please set 'XDG_CONFIG_HOME' in environment variables before running again.");
+    ensure_program_config_file_exists(&config_folder).expect("")
+}
+
+#[allow(dead_code)]
 pub fn config() {
     let conf_path = find_config_path().unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH));
     let config_folder = ensure_config_folder_exists(&conf_path).expect("Unable to create a config folder, please set 'XDG_CONFIG_HOME' in environment variables before running again.");
     let program_config_file = ensure_program_config_file_exists(&config_folder).expect("");
-    let palette_folder =
+    let _palette_folder =
         ensure_palette_folder_exists(&config_folder).expect("Folder should be good");
     // reader::read_program_config_file(program_config_file);
     let _settings = reader::read_program_config_file(&program_config_file).unwrap();
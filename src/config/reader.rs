@@ -1,13 +1,22 @@
+use anyhow::{bail, Context};
 use config::{Config, File, FileFormat, Map};
-use glob::glob;
 use std::{
-    collections::BTreeMap,
     fs,
     path::{Path, PathBuf},
 };
 
+use super::writer::color_to_string;
 use crate::color::Color;
-use crate::palette::Palette;
+use crate::palette::expression::parse_entry;
+use crate::palette::inheritance::{Inheritance, EXTENDS_KEY};
+use toml::{Table, Value};
+use uuid::Uuid;
+
+use crate::palette::{
+    ColorRole, MergeReport, MergeStrategy, Metadata, Palette, GROUP_SEPARATOR, ID_KEY,
+    METADATA_KEY, ROLES_KEY,
+};
+use crate::parser::Expr;
 
 pub const DEFAULT_SETTINGS: &str = "
 [General]
@@ -23,54 +32,385 @@ pub fn read_program_config_file(file_path: &Path) -> Option<Config> {
         .add_source(File::from_str(DEFAULT_SETTINGS, FileFormat::Toml))
         .add_source(config::File::with_name(file_path.to_str()?));
 
-    match settings_builder.build() {
-        Ok(settings) => Some(settings),
-        Err(_) => None,
-    }
+    settings_builder.build().ok()
 }
 
-pub fn read_colour_palettes(config_folder: &Path) -> Option<Vec<Palette>> {
-    let palette_folder = config_folder.join("palettes/").to_str().unwrap().to_owned();
-    println!("{:?}", &palette_folder);
-    let pattern = palette_folder + "*.toml";
+/// Reads every palette in the config folder's `palettes` folder.
+///
+/// Palettes with the same name in several files are merged into the first one read, later
+/// files winning conflicts; each merge is reported on stderr.
+pub fn read_colour_palettes(config_folder: &Path) -> anyhow::Result<Vec<Palette>> {
+    let (palettes, reports) = read_colour_palettes_with(config_folder, MergeStrategy::KeepRight)?;
+    for report in reports {
+        eprintln!("Warning: {}", report);
+    }
+    Ok(palettes)
+}
 
-    let files = glob(pattern.as_str())
-        .unwrap()
-        .map(|path| File::from(path.unwrap()))
+/// Reads every palette in the config folder, merging same-named palettes by `strategy`.
+pub fn read_colour_palettes_with(
+    config_folder: &Path,
+    strategy: MergeStrategy,
+) -> anyhow::Result<(Vec<Palette>, Vec<MergeReport>)> {
+    let mut paths = get_pallete_filesnames(config_folder.join("palettes"))
+        .map_err(anyhow::Error::msg)?
+        .into_iter()
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "toml"))
         .collect::<Vec<_>>();
+    paths.sort();
 
-    println!("{:?}", &files);
+    let mut tables = Map::new();
+    let mut duplicates = Vec::new();
+    for path in paths {
+        let file_tables =
+            read_palette_tables(&path).with_context(|| format!("In {}", path.display()))?;
+        // Palettes are the same whatever the case of their names.
+        for (name, table) in file_tables {
+            let key = name.to_lowercase();
+            if tables.contains_key(&key) {
+                duplicates.push(table);
+            } else {
+                tables.insert(key, table);
+            }
+        }
+    }
+    if tables.is_empty() {
+        bail!(
+            "No palettes found in {}",
+            config_folder.join("palettes").display()
+        );
+    }
+
+    let mut resolver = InheritanceResolver {
+        tables,
+        palette_folder: config_folder.join("palettes"),
+        external: Map::new(),
+    };
+    let mut palettes = build_palettes(&mut resolver)?;
+    let mut reports = Vec::new();
+    for table in duplicates {
+        let path = table.path.display();
+        let duplicate = build_palette(&table, &mut resolver)
+            .with_context(|| format!("In palette '{}' of {}", table.name, path))?;
+        let palette = palettes
+            .iter_mut()
+            .find(|palette| palette.name.to_lowercase() == table.name.to_lowercase())
+            .expect("duplicates are only collected for palettes already read");
+        reports.push(
+            palette
+                .merge(&duplicate, strategy)
+                .with_context(|| format!("In {}", path))?,
+        );
+    }
+    Ok((palettes, reports))
+}
 
-    let settings_builder = Config::builder().add_source(files);
+/// Reads the palettes in a single palette file, which may live outside the config folder.
+///
+/// `extends` paths are resolved relative to the file's folder.
+pub fn read_palette_file(file_path: &Path) -> anyhow::Result<Vec<Palette>> {
+    if !file_path.is_file() {
+        bail!("No palette file at {}", file_path.display());
+    }
+    let tables =
+        read_palette_tables(file_path).with_context(|| format!("In {}", file_path.display()))?;
+    let mut resolver = InheritanceResolver {
+        tables,
+        palette_folder: file_path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+        external: Map::new(),
+    };
+    build_palettes(&mut resolver)
+}
 
-    let palette_config = match settings_builder.build() {
-        Ok(settings) => settings,
-        Err(_) => {
-            return None;
+/// Reads a bare list of colours, one hex colour such as `#110f1e` per line, as pywal's
+/// `colors` file is written. Each colour is named after its nearest descriptive name.
+pub fn read_color_list(file_path: &Path, name: &str) -> anyhow::Result<Palette> {
+    let text = fs::read_to_string(file_path)
+        .with_context(|| format!("Unable to read {}", file_path.display()))?;
+    let mut palette = Palette::new(name, None);
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
         }
+        let color = Color::try_from_hex(line).with_context(|| {
+            format!(
+                "In {}: line {} is not a hex colour: {}",
+                file_path.display(),
+                number + 1,
+                line
+            )
+        })?;
+        palette.add_named_color(color)?;
+    }
+    if palette.colors.is_empty() {
+        bail!("No colours in {}", file_path.display());
+    }
+    Ok(palette)
+}
+
+fn build_palettes(resolver: &mut InheritanceResolver) -> anyhow::Result<Vec<Palette>> {
+    let tables = resolver.tables.values().cloned().collect::<Vec<_>>();
+    tables
+        .iter()
+        .map(|table| {
+            build_palette(table, resolver).with_context(|| format!("In palette '{}'", table.name))
+        })
+        .collect()
+}
+
+/// A palette table as written in a file, before inheritance and expressions are resolved.
+#[derive(Debug, Clone, Default)]
+struct PaletteTable {
+    name: String,
+    /// Colour entries and their source, a hex colour or an expression.
+    entries: Map<String, String>,
+    roles: Map<ColorRole, String>,
+    metadata: Metadata,
+    extends: Option<String>,
+    /// File the table was read from.
+    path: PathBuf,
+    /// Persisted identity from the metadata table.
+    id: Option<Uuid>,
+}
+
+/// Reads the palette tables of one file. Each file is read on its own so that palettes
+/// sharing a name are merged explicitly. The file is parsed as TOML directly rather than
+/// through the config crate, which lowercases palette and colour names.
+fn read_palette_tables(path: &Path) -> anyhow::Result<Map<String, PaletteTable>> {
+    let palettes = fs::read_to_string(path)
+        .context("Unable to read the palette files")?
+        .parse::<Table>()
+        .context("Unable to read the palette files")?;
+
+    palettes
+        .into_iter()
+        .map(|(palette, values)| {
+            let values = match values {
+                Value::Table(values) => values,
+                _ => bail!("Palette files should only contain tables of colours"),
+            };
+            let mut table = read_palette_table(&palette, values)
+                .with_context(|| format!("In palette '{}'", palette))?;
+            table.path = path.to_path_buf();
+            Ok((palette, table))
+        })
+        .collect()
+}
+
+fn read_palette_table(palette: &str, values: Table) -> anyhow::Result<PaletteTable> {
+    let mut table = PaletteTable {
+        name: palette.to_string(),
+        ..Default::default()
     };
+    for (name, value) in values {
+        match name.as_str() {
+            METADATA_KEY => {
+                table.id = read_id(&value)?;
+                table.metadata = read_metadata(value);
+            }
+            ROLES_KEY => table.roles = read_roles(value),
+            EXTENDS_KEY => {
+                table.extends = Some(
+                    value
+                        .as_str()
+                        .context("`extends` should name a palette or a palette file")?
+                        .to_string(),
+                )
+            }
+            _ => read_entry(&mut table, name, value)?,
+        }
+    }
+    Ok(table)
+}
 
-    let palettes_map = palette_config
-        .clone()
-        .try_deserialize::<Map<String, Map<String, String>>>()
-        .unwrap();
+/// Adds a colour entry to `table`, or every entry of a group such as `[MyTheme.ui]`
+/// under names like `ui.border`.
+fn read_entry(table: &mut PaletteTable, name: String, value: Value) -> anyhow::Result<()> {
+    match value {
+        Value::Table(group) => {
+            for (entry, value) in group {
+                read_entry(
+                    table,
+                    format!("{}{}{}", name, GROUP_SEPARATOR, entry),
+                    value,
+                )?;
+            }
+        }
+        Value::String(value) => {
+            let source = match parse_entry(&name, &value)? {
+                Expr::Color(color) => color_to_string(&color),
+                _ => value,
+            };
+            table.entries.insert(name, source);
+        }
+        other => bail!("`{}`: expected a colour, found `{}`", name, other),
+    }
+    Ok(())
+}
+
+/// Looks up the palettes named by `extends`, loading palette files on demand.
+struct InheritanceResolver {
+    tables: Map<String, PaletteTable>,
+    palette_folder: PathBuf,
+    /// Palettes read from files named by `extends`, by the `extends` value.
+    external: Map<String, PaletteTable>,
+}
 
-    if palettes_map.is_empty() {
-        return None;
+impl InheritanceResolver {
+    fn base(&mut self, extends: &str) -> anyhow::Result<PaletteTable> {
+        // Palette names are matched ignoring case, as same-named palettes are merged.
+        if let Some(table) = self
+            .tables
+            .values()
+            .find(|table| table.name.eq_ignore_ascii_case(extends))
+        {
+            return Ok(table.clone());
+        }
+        if let Some(table) = self.external.get(extends) {
+            return Ok(table.clone());
+        }
+        if !extends.ends_with(".toml") {
+            bail!("extends unknown palette '{}'", extends);
+        }
+        let path = self.palette_folder.join(extends);
+        if !path.is_file() {
+            bail!("extends missing palette file {}", path.display());
+        }
+        let table = read_palette_tables(&path)?
+            .into_values()
+            .next()
+            .with_context(|| format!("No palettes found in {}", path.display()))?;
+        self.external.insert(extends.to_string(), table.clone());
+        Ok(table)
     }
 
-    let mut palettes: Vec<Palette> = Vec::new();
-    let palette_names = palettes_map.keys().cloned();
-    for palette in palette_names {
-        let mut pal = Palette::new(palette.as_str(), None);
-        let filename = palette_config.clone();
-        println!("{:?}", filename);
-        for (name, color) in &palettes_map[&palette] {
-            pal.add_color(name.to_owned(), Color::from_hex(color));
+    /// Entries and roles of `table` with everything it inherits, base entries first.
+    fn flatten(
+        &mut self,
+        table: &PaletteTable,
+        stack: &mut Vec<String>,
+    ) -> anyhow::Result<(Map<String, String>, Map<ColorRole, String>)> {
+        let extends = match &table.extends {
+            None => return Ok((table.entries.clone(), table.roles.clone())),
+            Some(extends) => extends,
+        };
+        if stack.iter().any(|name| name.eq_ignore_ascii_case(extends)) {
+            stack.push(extends.clone());
+            bail!("Palette inheritance forms a cycle: {}", stack.join(" -> "));
+        }
+        stack.push(extends.clone());
+        let base = self.base(extends)?;
+        let (mut entries, mut roles) = self.flatten(&base, stack)?;
+        stack.pop();
+        for (name, source) in &table.entries {
+            entries.insert(name.clone(), source.clone());
         }
-        palettes.push(pal);
+        for (role, name) in &table.roles {
+            roles.insert(*role, name.clone());
+        }
+        Ok((entries, roles))
+    }
+
+    /// What `table` inherits from its base, if it has one.
+    fn inheritance(&mut self, table: &PaletteTable) -> anyhow::Result<Option<Inheritance>> {
+        let extends = match &table.extends {
+            None => return Ok(None),
+            Some(extends) => extends,
+        };
+        let base = self.base(extends)?;
+        let mut stack = vec![table.name.clone(), extends.clone()];
+        let (colors, roles) = self.flatten(&base, &mut stack)?;
+        Ok(Some(Inheritance {
+            base: extends.clone(),
+            colors,
+            roles,
+        }))
+    }
+}
+
+fn build_palette(
+    table: &PaletteTable,
+    resolver: &mut InheritanceResolver,
+) -> anyhow::Result<Palette> {
+    let mut stack = vec![table.name.clone()];
+    let (entries, roles) = resolver.flatten(table, &mut stack)?;
+    let mut pal = Palette::new(&table.name, Some(table.path.clone()));
+    if let Some(id) = table.id {
+        pal.uuid = id;
+    }
+    pal.metadata = table.metadata.clone();
+    pal.inheritance = resolver.inheritance(table)?;
+    for (name, source) in entries {
+        match parse_entry(&name, &source)? {
+            // Expressions may refer to entries further down, so nothing is resolved
+            // until every entry is in.
+            Expr::Color(color) => {
+                pal.colors.insert(name, color);
+            }
+            _ => pal.add_expression(name, &source),
+        }
+    }
+    pal.resolve_expressions()?;
+    pal.roles = roles;
+    pal.roles.retain(|_, color| pal.colors.contains_key(color));
+    Ok(pal)
+}
+
+/// The palette's persisted id from its metadata table, if it has one.
+fn read_id(meta: &Value) -> anyhow::Result<Option<Uuid>> {
+    let id = match meta.get(ID_KEY) {
+        Some(Value::String(id)) => id,
+        Some(other) => bail!("`{}` is not a valid palette id", other),
+        None => return Ok(None),
+    };
+    let id = Uuid::parse_str(id).with_context(|| format!("`{}` is not a valid palette id", id))?;
+    Ok(Some(id))
+}
+
+/// Reads a palette's metadata table, ignoring unknown keys and malformed values.
+pub fn read_metadata(value: Value) -> Metadata {
+    let mut metadata = Metadata::default();
+    let table = match value {
+        Value::Table(table) => table,
+        _ => return metadata,
+    };
+    let string = |key: &str| table.get(key).and_then(Value::as_str).map(str::to_string);
+    let datetime = |key: &str| match table.get(key) {
+        Some(Value::Datetime(datetime)) => Some(*datetime),
+        Some(Value::String(datetime)) => datetime.parse().ok(),
+        _ => None,
+    };
+    metadata.author = string("author");
+    metadata.description = string("description");
+    metadata.license = string("license");
+    metadata.source = string("source");
+    metadata.wallpaper = string("wallpaper").map(PathBuf::from);
+    metadata.variant = string("variant").and_then(|v| v.parse().ok());
+    metadata.created = datetime("created");
+    metadata.modified = datetime("modified");
+    metadata.tags = table
+        .get("tags")
+        .and_then(Value::as_array)
+        .map(|tags| {
+            tags.iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    metadata
+}
+
+/// Reads a palette's roles table of `role = "colour name"`, ignoring unknown roles.
+pub fn read_roles(value: Value) -> Map<ColorRole, String> {
+    match value {
+        Value::Table(table) => table
+            .into_iter()
+            .filter_map(|(role, name)| Some((role.parse().ok()?, name.as_str()?.to_string())))
+            .collect(),
+        _ => Map::new(),
     }
-    Some(palettes)
 }
 
 pub fn get_pallete_filesnames(palette_folder: PathBuf) -> Result<Vec<PathBuf>, String> {
@@ -83,3 +423,178 @@ pub fn get_pallete_filesnames(palette_folder: PathBuf) -> Result<Vec<PathBuf>, S
     }
     Ok(palette_files)
 }
+
+#[cfg(test)]
+mod reader_tests {
+    use super::*;
+    use crate::config::writer::palettes_to_string;
+    use crate::palette::{derived_id, find_by_id};
+    use crate::testing;
+
+    fn palette_folder(files: &[(&str, &str)]) -> PathBuf {
+        let config_folder = testing::config_folder();
+        fs::create_dir_all(config_folder.join("palettes/bases")).unwrap();
+        for (name, contents) in files {
+            fs::write(config_folder.join("palettes").join(name), contents).unwrap();
+        }
+        config_folder
+    }
+
+    #[test]
+    fn multi_level_inheritance() {
+        let config_folder = palette_folder(&[
+            (
+                "base.toml",
+                "[Base]\nbg = \"#000\"\nfg = \"#FFF\"\ncolor1 = \"#F00\"\ncolor9 = \"lighten($color1, 25%)\"\n[Base.roles]\naccent = \"color1\"\n",
+            ),
+            (
+                "variants.toml",
+                "[Dim]\nextends = \"Base\"\nfg = \"#AAA\"\n\n[\"Dim Blue\"]\nextends = \"Dim\"\ncolor1 = \"#00F\"\nextra = \"$fg\"\n",
+            ),
+        ]);
+        let palettes = read_colour_palettes(&config_folder).unwrap();
+        let dim_blue = palettes.iter().find(|p| p.name == "Dim Blue").unwrap();
+        assert_eq!(
+            dim_blue.color_names(),
+            vec!["bg", "fg", "color1", "color9", "extra"]
+        );
+        assert_eq!(dim_blue.get_color("fg").unwrap().hex(), "#AAAAAA");
+        assert_eq!(dim_blue.get_color("extra").unwrap().hex(), "#AAAAAA");
+        assert_eq!(dim_blue.get_color("color9").unwrap().hex(), "#8080FF");
+        assert_eq!(dim_blue.role_name(ColorRole::Accent), Some("color1"));
+        assert_eq!(dim_blue.inheritance.as_ref().unwrap().base, "Dim");
+
+        assert_eq!(
+            palettes_to_string(&[dim_blue]).unwrap(),
+            format!(
+                "[\"Dim Blue\"]\nextends = \"Dim\"\ncolor1 = \"#0000FF\"\nextra = \"$fg\"\n\n[\"Dim Blue\".meta]\nid = \"{}\"\n",
+                dim_blue.uuid
+            )
+        );
+        fs::remove_dir_all(config_folder).unwrap();
+    }
+
+    #[test]
+    fn extends_palette_file() {
+        let config_folder = palette_folder(&[(
+            "light.toml",
+            "[Light]\nextends = \"bases/base.toml\"\nbg = \"#FFF\"\n",
+        )]);
+        fs::write(
+            config_folder.join("palettes/bases/base.toml"),
+            "[Base]\nbg = \"#000\"\nfg = \"#777\"\n",
+        )
+        .unwrap();
+        let palettes = read_colour_palettes(&config_folder).unwrap();
+        assert_eq!(palettes.len(), 1);
+        assert_eq!(palettes[0].get_color("bg").unwrap().hex(), "#FFFFFF");
+        assert_eq!(palettes[0].get_color("fg").unwrap().hex(), "#777777");
+        fs::remove_dir_all(config_folder).unwrap();
+    }
+
+    #[test]
+    fn inheritance_errors() {
+        let config_folder = palette_folder(&[(
+            "cycle.toml",
+            "[A]\nextends = \"B\"\n[B]\nextends = \"C\"\n[C]\nextends = \"A\"\n",
+        )]);
+        let error = read_colour_palettes(&config_folder).unwrap_err();
+        assert_eq!(
+            format!("{:#}", error),
+            "In palette 'A': Palette inheritance forms a cycle: A -> B -> C -> A"
+        );
+        fs::remove_dir_all(config_folder).unwrap();
+
+        let config_folder = palette_folder(&[("unknown.toml", "[A]\nextends = \"Missing\"\n")]);
+        let error = read_colour_palettes(&config_folder).unwrap_err();
+        assert_eq!(
+            format!("{:#}", error),
+            "In palette 'A': extends unknown palette 'Missing'"
+        );
+        fs::remove_dir_all(config_folder).unwrap();
+    }
+
+    #[test]
+    fn same_named_palettes_are_merged() {
+        let config_folder = palette_folder(&[
+            ("a.toml", "[Theme]\nbg = \"#000\"\nred = \"#C00\"\n"),
+            ("b.toml", "[Theme]\nred = \"#D00\"\nblue = \"#00C\"\n"),
+        ]);
+        let (palettes, reports) =
+            read_colour_palettes_with(&config_folder, MergeStrategy::RenameConflicts).unwrap();
+        assert_eq!(palettes.len(), 1);
+        assert_eq!(
+            palettes[0].color_names(),
+            vec!["bg", "red", "blue", "red-2"]
+        );
+        assert_eq!(
+            palettes[0].filename,
+            Some(config_folder.join("palettes/a.toml"))
+        );
+        assert_eq!(reports[0].added, vec!["blue"]);
+        assert_eq!(
+            reports[0].renamed,
+            vec![("red".to_string(), "red-2".to_string())]
+        );
+
+        let error = read_colour_palettes_with(&config_folder, MergeStrategy::Fail).unwrap_err();
+        assert!(format!("{:#}", error).contains("`red` is #CC0000 and #DD0000"));
+        fs::remove_dir_all(config_folder).unwrap();
+    }
+
+    #[test]
+    fn ids_survive_renames() {
+        let config_folder = palette_folder(&[("theme.toml", "[Theme]\nbg = \"#000\"\n")]);
+        let path = config_folder.join("palettes/theme.toml");
+        let mut palettes = read_colour_palettes(&config_folder).unwrap();
+        let id = palettes[0].uuid;
+        assert_eq!(id, derived_id(&path, "Theme"));
+        assert_eq!(read_colour_palettes(&config_folder).unwrap()[0].uuid, id);
+
+        palettes[0].save_to(&path).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.starts_with("[Theme]\n"), "{}", written);
+        let renamed = written.replace("Theme", "Renamed");
+        fs::write(&path, renamed).unwrap();
+        let palettes = read_colour_palettes(&config_folder).unwrap();
+        assert_eq!(find_by_id(&palettes, &id).unwrap().name, "Renamed");
+
+        fs::write(&path, "[A]\nbg = \"#000\"\n[A.meta]\nid = \"nope\"\n").unwrap();
+        let error = read_colour_palettes(&config_folder).unwrap_err();
+        assert!(format!("{:#}", error).contains("`nope` is not a valid palette id"));
+        fs::remove_dir_all(config_folder).unwrap();
+    }
+
+    #[test]
+    fn color_list_is_named() {
+        let palette = read_color_list(Path::new("test/colors"), "Wal").unwrap();
+        assert_eq!(palette.len(), 16);
+        assert_eq!(palette.colors[0].hex(), "#110F1E");
+        let names = palette.color_names();
+        assert_eq!(names[0], "black");
+        let mut unique = names.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), 16);
+
+        let path = testing::temp_path("colors");
+        fs::write(&path, "#000000\nnot a colour\n").unwrap();
+        let error = read_color_list(&path, "Bad").unwrap_err();
+        assert!(format!("{:#}", error).contains("line 2 is not a hex colour"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn names_keep_their_case() {
+        let contents =
+            "[\"My Theme\"]\nBackground = \"#000\"\n\n[\"My Theme\".UI]\nBorder = \"#111\"\n";
+        let config_folder = palette_folder(&[("theme.toml", contents)]);
+        let palettes = read_colour_palettes(&config_folder).unwrap();
+        assert_eq!(palettes[0].name, "My Theme");
+        assert_eq!(palettes[0].color_names(), vec!["Background", "UI.Border"]);
+        let written = palettes_to_string(&[&palettes[0]]).unwrap();
+        assert!(written
+            .starts_with("[\"My Theme\"]\nBackground = \"#000000\"\n\n[\"My Theme\".UI]\nBorder"));
+        fs::remove_dir_all(config_folder).unwrap();
+    }
+}
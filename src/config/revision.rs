@@ -0,0 +1,349 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use config::Config;
+use toml::value::Datetime;
+use uuid::Uuid;
+
+use super::{reader::read_palette_file, PROGRAM_NAME};
+use crate::palette::{diff::PaletteDiff, metadata::datetime_from_unix, Palette};
+use crate::RevisionType;
+
+/// Folder in the config folder holding a folder of snapshots per palette id.
+pub const REVISIONS_FOLDER: &str = "revisions";
+/// Table of the program settings configuring how many snapshots are kept.
+pub const REVISIONS_SETTINGS_KEY: &str = "Revisions";
+
+/// How many snapshots of a palette are kept; older ones are removed when a new one is taken.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RevisionLimits {
+    pub max_count: usize,
+    pub max_age: Duration,
+}
+
+impl Default for RevisionLimits {
+    fn default() -> Self {
+        Self {
+            max_count: 50,
+            max_age: Duration::from_secs(90 * 86_400),
+        }
+    }
+}
+
+impl RevisionLimits {
+    /// The defaults overridden by `max_count` and `max_age_days` in the `[Revisions]`
+    /// table of the program settings.
+    pub fn from_settings(settings: &Config) -> Self {
+        let mut limits = Self::default();
+        let setting = |key: &str| {
+            settings
+                .get_int(&format!("{}.{}", REVISIONS_SETTINGS_KEY, key))
+                .ok()
+                .and_then(|value| u64::try_from(value).ok())
+        };
+        if let Some(count) = setting("max_count") {
+            limits.max_count = count as usize;
+        }
+        if let Some(days) = setting("max_age_days") {
+            limits.max_age = Duration::from_secs(days * 86_400);
+        }
+        limits
+    }
+}
+
+/// A snapshot of a palette file, taken when it was saved.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Revision {
+    pub path: PathBuf,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+impl Revision {
+    pub fn datetime(&self) -> Datetime {
+        datetime_from_unix(self.timestamp / 1000)
+    }
+
+    /// The palette `current` as it was in the snapshot, found by its id or else its name
+    /// among the other palettes of its file.
+    pub fn palette(&self, current: &Palette) -> anyhow::Result<Palette> {
+        let mut palettes = read_palette_file(&self.path)?;
+        let index = palettes
+            .iter()
+            .position(|palette| palette.uuid == current.uuid)
+            .or_else(|| {
+                palettes
+                    .iter()
+                    .position(|palette| palette.name == current.name)
+            })
+            .ok_or_else(|| anyhow::anyhow!("No {} in {}", current.name, self.path.display()))?;
+        Ok(palettes.swap_remove(index))
+    }
+
+    /// What changed from this revision to `current`.
+    pub fn diff(&self, current: &Palette) -> anyhow::Result<PaletteDiff> {
+        Ok(self.palette(current)?.diff(current))
+    }
+
+    /// A one-line summary of what changed from this revision to `current`.
+    pub fn summary(&self, current: &Palette) -> String {
+        self.diff(current)
+            .map(|diff| diff.summary())
+            .unwrap_or_else(|error| format!("unreadable: {:#}", error))
+    }
+
+    pub fn to_slint(&self, current: &Palette) -> RevisionType {
+        RevisionType {
+            datetime: self.datetime().to_string().into(),
+            summary: self.summary(current).into(),
+        }
+    }
+}
+
+/// Timestamped copies of palette files, one folder per palette id.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RevisionStore {
+    folder: PathBuf,
+    limits: RevisionLimits,
+}
+
+impl RevisionStore {
+    /// The store in `config_folder`.
+    pub fn new(config_folder: &Path, limits: RevisionLimits) -> Self {
+        Self {
+            folder: config_folder.join(REVISIONS_FOLDER),
+            limits,
+        }
+    }
+
+    /// The store in the program's config folder, limited by its settings.
+    pub fn from_program_config() -> Self {
+        let limits = super::reader::read_program_config_file(&super::program_config_file())
+            .map(|settings| RevisionLimits::from_settings(&settings))
+            .unwrap_or_default();
+        Self::new(&super::config_palette_folder(), limits)
+    }
+
+    /// The store of the config folder whose `palettes` folder holds `file`, limited by
+    /// that folder's settings. Files kept anywhere else have no revisions.
+    pub fn for_file(file: &Path) -> Option<Self> {
+        let palettes = file.parent()?;
+        if palettes.file_name()? != "palettes" {
+            return None;
+        }
+        let config_folder = palettes.parent()?;
+        let settings = config_folder.join(format!("{}.toml", PROGRAM_NAME));
+        let limits = super::reader::read_program_config_file(&settings)
+            .map(|settings| RevisionLimits::from_settings(&settings))
+            .unwrap_or_default();
+        Some(Self::new(config_folder, limits))
+    }
+
+    fn palette_folder(&self, id: &Uuid) -> PathBuf {
+        self.folder.join(id.to_string())
+    }
+
+    /// The palette's snapshots, newest first.
+    pub fn list(&self, id: &Uuid) -> anyhow::Result<Vec<Revision>> {
+        let folder = self.palette_folder(id);
+        if !folder.exists() {
+            return Ok(Vec::new());
+        }
+        let entries = fs::read_dir(&folder)
+            .with_context(|| format!("Unable to read revisions in {}", folder.display()))?;
+        let mut revisions = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter_map(|path| {
+                let timestamp = path.file_stem()?.to_str()?.parse::<u64>().ok()?;
+                Some(Revision { path, timestamp })
+            })
+            .collect::<Vec<_>>();
+        revisions.sort_by_key(|revision| std::cmp::Reverse(revision.timestamp));
+        Ok(revisions)
+    }
+
+    /// Copies `file`, the saved file of palette `id`, as a new snapshot, then removes
+    /// snapshots beyond the limits. The newest snapshot is always kept.
+    pub fn snapshot(&self, id: &Uuid, file: &Path) -> anyhow::Result<Revision> {
+        let folder = self.palette_folder(id);
+        fs::create_dir_all(&folder)
+            .with_context(|| format!("Unable to create {}", folder.display()))?;
+        let mut timestamp = now_millis();
+        // Two saves within a millisecond still get a snapshot each.
+        while folder.join(format!("{}.toml", timestamp)).exists() {
+            timestamp += 1;
+        }
+        let path = folder.join(format!("{}.toml", timestamp));
+        fs::copy(file, &path).with_context(|| {
+            format!(
+                "Unable to snapshot {} to {}",
+                file.display(),
+                path.display()
+            )
+        })?;
+        self.prune(id)?;
+        Ok(Revision { path, timestamp })
+    }
+
+    fn prune(&self, id: &Uuid) -> anyhow::Result<()> {
+        let max_age = self.limits.max_age.as_millis() as u64;
+        let oldest = now_millis().saturating_sub(max_age);
+        for (index, revision) in self.list(id)?.iter().enumerate() {
+            let expired = index >= self.limits.max_count || revision.timestamp < oldest;
+            if index > 0 && expired {
+                fs::remove_file(&revision.path).with_context(|| {
+                    format!("Unable to remove revision {}", revision.path.display())
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves the palette to `file_path` and snapshots the result. A file saved for the
+    /// first time since it was last snapshotted has its old contents kept first.
+    pub fn save(&self, palette: &mut Palette, file_path: &Path) -> anyhow::Result<Revision> {
+        if file_path.exists() && self.list(&palette.uuid)?.is_empty() {
+            self.snapshot(&palette.uuid, file_path)?;
+        }
+        palette.write_to(file_path)?;
+        self.snapshot(&palette.uuid, file_path)
+    }
+
+    /// Writes the palette as it was in `revision` back to its table in its file, keeping its
+    /// id, and snapshots it as the newest revision so the restore itself can be undone.
+    /// Returns the restored palette.
+    pub fn restore(&self, palette: &Palette, revision: &Revision) -> anyhow::Result<Palette> {
+        let file = palette
+            .filename
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("{} has no file to restore to", palette.name))?;
+        let mut restored = revision.palette(palette)?;
+        restored.uuid = palette.uuid;
+        self.save(&mut restored, file)?;
+        Ok(restored)
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod revision_tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::testing;
+
+    #[test]
+    fn save_list_and_restore() {
+        let config_folder = testing::config_folder();
+        let path = config_folder.join("palettes/theme.toml");
+        fs::write(&path, "[Theme]\nbg = \"#000\"\n").unwrap();
+        let store = RevisionStore::new(&config_folder, RevisionLimits::default());
+        let mut palette = read_palette_file(&path).unwrap().remove(0);
+
+        palette
+            .add_color("fg".to_string(), Color::from_hex("#FFF"))
+            .unwrap();
+        store.save(&mut palette, &path).unwrap();
+        let revisions = store.list(&palette.uuid).unwrap();
+        assert_eq!(revisions.len(), 2);
+        assert!(revisions[0].timestamp > revisions[1].timestamp);
+        assert_eq!(
+            revisions[1].palette(&palette).unwrap().color_names(),
+            vec!["bg"]
+        );
+
+        let diff = revisions[1].diff(&palette).unwrap();
+        assert_eq!(diff.changes.len(), 1);
+        let restored = store.restore(&palette, &revisions[1]).unwrap();
+        assert_eq!(restored.color_names(), vec!["bg"]);
+        assert_eq!(restored.uuid, palette.uuid);
+        assert_eq!(store.list(&palette.uuid).unwrap().len(), 3);
+        assert_eq!(
+            read_palette_file(&path).unwrap()[0].color_names(),
+            vec!["bg"]
+        );
+        fs::remove_dir_all(config_folder).unwrap();
+    }
+
+    #[test]
+    fn restores_one_palette_of_many() {
+        let config_folder = testing::config_folder();
+        let path = config_folder.join("palettes/themes.toml");
+        fs::write(&path, "[Dark]\nbg = \"#000\"\n\n[Light]\nbg = \"#FFF\"\n").unwrap();
+        let store = RevisionStore::new(&config_folder, RevisionLimits::default());
+        let mut light = read_palette_file(&path).unwrap().remove(1);
+
+        light
+            .add_color("fg".to_string(), Color::from_hex("#111"))
+            .unwrap();
+        light.save_to(&path).unwrap();
+        let revisions = store.list(&light.uuid).unwrap();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[1].palette(&light).unwrap().name, "Light");
+
+        let restored = store.restore(&light, &revisions[1]).unwrap();
+        assert_eq!(restored.color_names(), vec!["bg"]);
+        let palettes = read_palette_file(&path).unwrap();
+        assert_eq!(palettes.len(), 2);
+        assert_eq!(palettes[0].name, "Dark");
+        assert_eq!(palettes[1].uuid, light.uuid);
+        assert_eq!(palettes[1].get_color("bg").unwrap().hex(), "#FFFFFF");
+        assert_eq!(palettes[1].color_names(), vec!["bg"]);
+        fs::remove_dir_all(config_folder).unwrap();
+    }
+
+    #[test]
+    fn bounded() {
+        let config_folder = testing::config_folder();
+        let path = config_folder.join("palettes/theme.toml");
+        let limits = RevisionLimits {
+            max_count: 2,
+            ..RevisionLimits::default()
+        };
+        let store = RevisionStore::new(&config_folder, limits);
+        let mut palette = Palette::new("Theme", None);
+        for hex in ["#100", "#200", "#300"] {
+            palette
+                .add_color("bg".to_string(), Color::from_hex(hex))
+                .unwrap();
+            store.save(&mut palette, &path).unwrap();
+        }
+        let revisions = store.list(&palette.uuid).unwrap();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(
+            revisions[0]
+                .palette(&palette)
+                .unwrap()
+                .get_color("bg")
+                .unwrap()
+                .hex(),
+            "#330000"
+        );
+
+        let expired = RevisionStore::new(
+            &config_folder,
+            RevisionLimits {
+                max_count: 10,
+                max_age: Duration::ZERO,
+            },
+        );
+        let id = Uuid::new_v4();
+        fs::create_dir_all(expired.palette_folder(&id)).unwrap();
+        fs::write(expired.palette_folder(&id).join("1000.toml"), "[Theme]\n").unwrap();
+        expired.snapshot(&id, &path).unwrap();
+        let revisions = expired.list(&id).unwrap();
+        assert_eq!(revisions.len(), 1);
+        assert!(revisions[0].timestamp > 1000);
+        fs::remove_dir_all(config_folder).unwrap();
+    }
+}
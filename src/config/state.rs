@@ -0,0 +1,128 @@
+use std::{fs, path::Path};
+
+use anyhow::Context;
+use toml::{Table, Value};
+use uuid::Uuid;
+
+/// File in the config folder remembering the viewer's state between launches.
+///
+/// It is kept apart from the settings file: that one is the user's to edit and is only
+/// read, while the state is rewritten whenever a palette is selected or marked as a
+/// favourite, which would lose the layout and comments of hand-written settings.
+pub const STATE_FILE: &str = "state.toml";
+
+const LAST_PALETTE_KEY: &str = "last_palette";
+const FAVORITES_KEY: &str = "favorites";
+const RECENT_KEY: &str = "recent";
+
+/// What the viewer remembers between launches.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct State {
+    /// Id of the palette shown last, which survives the palette being renamed.
+    pub last_palette: Option<Uuid>,
+    /// Ids of the palettes marked as favourites, in the order they were marked.
+    pub favorites: Vec<Uuid>,
+    /// Ids of the palettes shown most recently, newest first.
+    pub recent: Vec<Uuid>,
+}
+
+impl State {
+    /// Reads the state from `config_folder`, defaulting anything missing or unreadable.
+    /// Each key is read on its own, so one spoilt by hand doesn't lose the others.
+    pub fn load(config_folder: &Path) -> Self {
+        let contents = fs::read_to_string(config_folder.join(STATE_FILE)).unwrap_or_default();
+        let table = contents.parse::<Table>().unwrap_or_else(|_| {
+            contents
+                .lines()
+                .filter_map(|line| line.parse::<Table>().ok())
+                .flatten()
+                .collect()
+        });
+        let last_palette = table
+            .get(LAST_PALETTE_KEY)
+            .and_then(Value::as_str)
+            .and_then(|id| Uuid::parse_str(id).ok());
+        let ids = |key: &str| {
+            table
+                .get(key)
+                .and_then(Value::as_array)
+                .map(|ids| {
+                    ids.iter()
+                        .filter_map(Value::as_str)
+                        .filter_map(|id| Uuid::parse_str(id).ok())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        Self {
+            last_palette,
+            favorites: ids(FAVORITES_KEY),
+            recent: ids(RECENT_KEY),
+        }
+    }
+
+    /// Writes the state to `config_folder`, replacing the previous state.
+    pub fn save(&self, config_folder: &Path) -> anyhow::Result<()> {
+        let mut table = Table::new();
+        if let Some(id) = self.last_palette {
+            table.insert(LAST_PALETTE_KEY.to_string(), Value::String(id.to_string()));
+        }
+        for (key, ids) in [(FAVORITES_KEY, &self.favorites), (RECENT_KEY, &self.recent)] {
+            if !ids.is_empty() {
+                let ids = ids.iter().map(|id| Value::String(id.to_string()));
+                table.insert(key.to_string(), Value::Array(ids.collect()));
+            }
+        }
+        let path = config_folder.join(STATE_FILE);
+        fs::write(&path, table.to_string())
+            .with_context(|| format!("Unable to write state to {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod state_tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn round_trip() {
+        let config_folder = testing::config_folder();
+        assert_eq!(State::load(&config_folder), State::default());
+
+        let state = State {
+            last_palette: Some(Uuid::new_v4()),
+            favorites: vec![Uuid::new_v4(), Uuid::new_v4()],
+            recent: vec![Uuid::new_v4()],
+        };
+        state.save(&config_folder).unwrap();
+        assert_eq!(State::load(&config_folder), state);
+
+        fs::write(config_folder.join(STATE_FILE), "last_palette = \"nope\"\n").unwrap();
+        assert_eq!(State::load(&config_folder), State::default());
+        fs::remove_dir_all(config_folder).unwrap();
+    }
+
+    #[test]
+    fn keeps_readable_keys() {
+        let config_folder = testing::config_folder();
+        let [favorite, recent] = [Uuid::new_v4(), Uuid::new_v4()];
+        let state = format!(
+            "last_palette = nope\nfavorites = [\"{}\", \"bad\"]\nrecent = [\"{}\"]\n",
+            favorite, recent
+        );
+        fs::write(config_folder.join(STATE_FILE), state).unwrap();
+        assert_eq!(
+            State::load(&config_folder),
+            State {
+                last_palette: None,
+                favorites: vec![favorite],
+                recent: vec![recent],
+            }
+        );
+
+        let state = format!("last_palette = 7\nfavorites = [\"{}\"]\n", favorite);
+        fs::write(config_folder.join(STATE_FILE), state).unwrap();
+        assert_eq!(State::load(&config_folder).favorites, vec![favorite]);
+        fs::remove_dir_all(config_folder).unwrap();
+    }
+}
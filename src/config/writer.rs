@@ -0,0 +1,442 @@
+use std::{fs, path::Path};
+
+use anyhow::Context;
+use toml::{Table, Value};
+use toml_edit::{DocumentMut, Item};
+
+use crate::color::Color;
+use crate::palette::{
+    group::split_group, Metadata, Palette, EXTENDS_KEY, GROUP_SEPARATOR, ID_KEY, METADATA_KEY,
+    ROLES_KEY,
+};
+
+/// Hex string used when writing a colour, `#RRGGBB` or `#RRGGBBAA` if translucent.
+pub fn color_to_string(color: &Color) -> String {
+    match color.opacity {
+        255 => color.hex(),
+        _ => color.hexa(),
+    }
+}
+
+/// The palette's metadata as a TOML table, leaving out unset fields.
+pub fn metadata_to_table(metadata: &Metadata) -> Table {
+    let mut table = Table::new();
+    let strings = [
+        ("author", &metadata.author),
+        ("description", &metadata.description),
+        ("license", &metadata.license),
+    ];
+    for (key, value) in strings {
+        if let Some(value) = value {
+            table.insert(key.to_string(), Value::String(value.clone()));
+        }
+    }
+    if !metadata.tags.is_empty() {
+        let tags = metadata.tags.iter().cloned().map(Value::String).collect();
+        table.insert("tags".to_string(), Value::Array(tags));
+    }
+    if let Some(variant) = metadata.variant {
+        table.insert("variant".to_string(), Value::String(variant.to_string()));
+    }
+    if let Some(source) = &metadata.source {
+        table.insert("source".to_string(), Value::String(source.clone()));
+    }
+    if let Some(wallpaper) = &metadata.wallpaper {
+        let wallpaper = wallpaper.display().to_string();
+        table.insert("wallpaper".to_string(), Value::String(wallpaper));
+    }
+    if let Some(created) = metadata.created {
+        table.insert("created".to_string(), Value::Datetime(created));
+    }
+    if let Some(modified) = metadata.modified {
+        table.insert("modified".to_string(), Value::Datetime(modified));
+    }
+    table
+}
+
+/// The palette as a TOML table: colours in palette order, then the roles and metadata tables,
+/// the metadata including the palette's id if it has a file.
+///
+/// A palette that extends another only gets the entries and roles that differ from its base.
+pub fn palette_to_table(palette: &Palette) -> Table {
+    let mut table = Table::new();
+    if let Some(inheritance) = &palette.inheritance {
+        table.insert(
+            EXTENDS_KEY.to_string(),
+            Value::String(inheritance.base.clone()),
+        );
+    }
+    for (name, color) in &palette.colors {
+        let value = match palette.expression(name) {
+            Some(expression) => expression.to_string(),
+            None => color_to_string(color),
+        };
+        let inherited = palette
+            .inheritance
+            .as_ref()
+            .is_some_and(|inheritance| inheritance.is_inherited(name, &value));
+        if !inherited {
+            insert_entry(&mut table, name, Value::String(value));
+        }
+    }
+    let roles = palette
+        .roles
+        .iter()
+        .filter(|(role, name)| {
+            !palette
+                .inheritance
+                .as_ref()
+                .is_some_and(|inheritance| inheritance.is_inherited_role(role, name))
+        })
+        .map(|(role, name)| (role.to_string(), Value::String(name.clone())))
+        .collect::<Table>();
+    if !roles.is_empty() {
+        table.insert(ROLES_KEY.to_string(), Value::Table(roles));
+    }
+    // Only palettes kept in a file have an identity worth persisting.
+    let mut metadata = Table::new();
+    if palette.filename.is_some() {
+        metadata.insert(ID_KEY.to_string(), Value::String(palette.uuid.to_string()));
+    }
+    metadata.extend(metadata_to_table(&palette.metadata));
+    if !metadata.is_empty() {
+        table.insert(METADATA_KEY.to_string(), Value::Table(metadata));
+    }
+    table
+}
+
+/// Inserts a colour, nesting grouped names like `ui.border` in a `ui` table. A name
+/// whose group clashes with a colour is kept as a quoted dotted key, which reads back the same.
+fn insert_entry(table: &mut Table, name: &str, value: Value) {
+    let (group, leaf) = split_group(name);
+    let group = match group {
+        Some(group) => group,
+        None => {
+            table.insert(name.to_string(), value);
+            return;
+        }
+    };
+    let mut current = &mut *table;
+    for part in group.split(GROUP_SEPARATOR) {
+        if !matches!(current.get(part), None | Some(Value::Table(_))) {
+            table.insert(name.to_string(), value);
+            return;
+        }
+        current = match current
+            .entry(part.to_string())
+            .or_insert_with(|| Value::Table(Table::new()))
+        {
+            Value::Table(nested) => nested,
+            _ => unreachable!("checked to be a table"),
+        };
+    }
+    current.insert(leaf.to_string(), value);
+}
+
+/// Serialises the palettes to a TOML document, one table per palette.
+pub fn palettes_to_string(palettes: &[&Palette]) -> anyhow::Result<String> {
+    let document = palettes
+        .iter()
+        .map(|palette| {
+            (
+                palette.name.clone(),
+                Value::Table(palette_to_table(palette)),
+            )
+        })
+        .collect::<Table>();
+    toml::to_string(&document).context("Unable to serialise palettes")
+}
+
+/// Writes the palette to `file_path`. In an existing file only the palette's own table is
+/// replaced, found by its id or else its name, and the other palettes are kept in place
+/// with their comments and formatting.
+pub fn write_palette(palette: &Palette, file_path: &Path) -> anyhow::Result<()> {
+    let write = |contents: String| {
+        fs::write(file_path, contents)
+            .with_context(|| format!("Unable to write palette to {}", file_path.display()))
+    };
+    if !file_path.exists() {
+        return write(palettes_to_string(&[palette])?);
+    }
+    let mut document = fs::read_to_string(file_path)
+        .map_err(anyhow::Error::from)
+        .and_then(|contents| Ok(contents.parse::<DocumentMut>()?))
+        .with_context(|| format!("Unable to read {} to update it", file_path.display()))?;
+    let mut table = palettes_to_string(&[palette])?
+        .parse::<DocumentMut>()
+        .ok()
+        .and_then(|mut written| written.remove(&palette.name))
+        .and_then(|item| item.into_table().ok())
+        .context("Unable to serialise palettes")?;
+    let id = palette.uuid.to_string();
+    let existing = document
+        .iter()
+        .find(|(_, table)| {
+            table
+                .get(METADATA_KEY)
+                .and_then(|meta| meta.get(ID_KEY))
+                .and_then(Item::as_str)
+                == Some(id.as_str())
+        })
+        .map(|(name, _)| name.to_string())
+        .or_else(|| {
+            document
+                .contains_key(&palette.name)
+                .then(|| palette.name.clone())
+        });
+    // Tables are written in the order of their position in the file, so the palette takes
+    // the place of the table it replaces, or goes after every other one.
+    let mut position = usize::MAX;
+    if let Some(replaced) = existing
+        .and_then(|name| document.remove(&name))
+        .and_then(|item| item.into_table().ok())
+    {
+        position = replaced.position().unwrap_or(position);
+        *table.decor_mut() = replaced.decor().clone();
+    }
+    set_position(&mut table, position);
+    document.insert(&palette.name, Item::Table(table));
+    write(document.to_string())
+}
+
+/// Places the table and the tables nested in it at `position` in their document.
+fn set_position(table: &mut toml_edit::Table, position: usize) {
+    table.set_position(position);
+    for (_, item) in table.iter_mut() {
+        if let Item::Table(nested) = item {
+            set_position(nested, position);
+        }
+    }
+}
+
+#[cfg(test)]
+mod writer_tests {
+    use super::*;
+    use crate::config::reader::read_colour_palettes;
+    use crate::testing;
+
+    #[test]
+    fn keeps_color_order() {
+        let palette = testing::palette(
+            "Test Palette",
+            &[("color2", "#F00"), ("color10", "#0F0"), ("bg", "#0000007D")],
+        );
+        assert_eq!(
+            palettes_to_string(&[&palette]).unwrap(),
+            "[\"Test Palette\"]\ncolor2 = \"#FF0000\"\ncolor10 = \"#00FF00\"\nbg = \"#0000007D\"\n"
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let config_folder = testing::config_folder();
+        let input = fs::read_to_string("test/test.toml").expect("File in place.");
+        fs::write(config_folder.join("palettes/test.toml"), &input).unwrap();
+
+        let palettes = read_colour_palettes(&config_folder).unwrap();
+        assert_eq!(
+            palettes[0].color_names()[..4],
+            ["background", "foreground", "color0", "color1"]
+        );
+        assert_eq!(palettes[0].color_names()[12], "color10");
+
+        write_palette(&palettes[0], &config_folder.join("palettes/test.toml")).unwrap();
+        let reread = read_colour_palettes(&config_folder).unwrap();
+        assert_eq!(reread[0].color_names(), palettes[0].color_names());
+        assert_eq!(reread[0].colors, palettes[0].colors);
+        fs::remove_dir_all(config_folder).unwrap();
+    }
+
+    #[test]
+    fn keeps_expressions() {
+        let mut palette = Palette::new("Test Palette", None);
+        palette
+            .add_color("color1".to_string(), Color::from_hex("#F00"))
+            .unwrap();
+        palette
+            .set_expression("color9", "lighten($color1, 10%)")
+            .unwrap();
+        assert_eq!(
+            palettes_to_string(&[&palette]).unwrap(),
+            "[\"Test Palette\"]\ncolor1 = \"#FF0000\"\ncolor9 = \"lighten($color1, 10%)\"\n"
+        );
+    }
+
+    #[test]
+    fn groups_round_trip() {
+        let config_folder = testing::config_folder();
+        fs::write(
+            config_folder.join("palettes/grouped.toml"),
+            "[grouped]\nbg = \"#000\"\n\n[grouped.ansi]\nred = \"#C00\"\nbright-red = \"lighten($ansi.red, 20%)\"\n\n[grouped.syntax.keyword]\ncontrol = \"#C0C\"\n",
+        )
+        .unwrap();
+        let palettes = read_colour_palettes(&config_folder).unwrap();
+        assert_eq!(
+            palettes[0].color_names(),
+            vec![
+                "bg",
+                "ansi.red",
+                "ansi.bright-red",
+                "syntax.keyword.control"
+            ]
+        );
+        assert_eq!(
+            palettes[0].get_color("ansi.bright-red").unwrap().hex(),
+            "#FF3333"
+        );
+
+        let mut palette = palettes[0].clone();
+        palette
+            .add_color("ui".to_string(), Color::from_hex("#111"))
+            .unwrap();
+        palette
+            .add_color("ui.border".to_string(), Color::from_hex("#333"))
+            .unwrap();
+        assert_eq!(
+            palettes_to_string(&[&palette]).unwrap(),
+            format!(
+                "[grouped]\nbg = \"#000000\"\nui = \"#111111\"\n\"ui.border\" = \"#333333\"\n\n[grouped.ansi]\nred = \"#CC0000\"\nbright-red = \"lighten($ansi.red, 20%)\"\n\n[grouped.syntax.keyword]\ncontrol = \"#CC00CC\"\n\n[grouped.meta]\nid = \"{}\"\n",
+                palette.uuid
+            )
+        );
+        write_palette(&palette, &config_folder.join("palettes/grouped.toml")).unwrap();
+        let reread = read_colour_palettes(&config_folder).unwrap();
+        assert_eq!(reread[0].colors.len(), palette.colors.len());
+        assert_eq!(
+            reread[0].get_color("ui.border"),
+            palette.get_color("ui.border")
+        );
+        fs::remove_dir_all(config_folder).unwrap();
+    }
+
+    #[test]
+    fn metadata_round_trip() {
+        let config_folder = testing::config_folder();
+        let mut palette = Palette::new("Meta", None);
+        palette
+            .add_color("bg".to_string(), Color::from_hex("#000"))
+            .unwrap();
+        palette.metadata = Metadata {
+            author: Some("Ozy".to_string()),
+            description: Some("A test palette".to_string()),
+            license: Some("MIT".to_string()),
+            tags: vec!["dark".to_string(), "terminal".to_string()],
+            variant: Some(crate::palette::Variant::Dark),
+            source: Some("https://example.com/meta".to_string()),
+            wallpaper: Some("/tmp/wallpaper.png".into()),
+            created: None,
+            modified: None,
+        };
+        palette
+            .save_to(&config_folder.join("palettes/meta.toml"))
+            .unwrap();
+        assert!(palette.assign_role(crate::palette::ColorRole::Background, "bg"));
+        palette
+            .save_to(&config_folder.join("palettes/meta.toml"))
+            .unwrap();
+        let written = fs::read_to_string(config_folder.join("palettes/meta.toml")).unwrap();
+        assert!(written.contains("[Meta.roles]\nbackground = \"bg\""));
+        assert!(written.contains(&format!(
+            "[Meta.meta]\nid = \"{}\"\nauthor = \"Ozy\"",
+            palette.uuid
+        )));
+
+        let reread = read_colour_palettes(&config_folder).unwrap();
+        assert_eq!(reread[0].uuid, palette.uuid);
+        assert_eq!(reread[0].metadata, palette.metadata);
+        assert_eq!(reread[0].roles, palette.roles);
+        assert_eq!(reread[0].color_names(), vec!["bg"]);
+        fs::remove_dir_all(config_folder).unwrap();
+    }
+
+    #[test]
+    fn keeps_other_palettes() {
+        let config_folder = testing::config_folder();
+        let path = config_folder.join("palettes/themes.toml");
+        fs::write(
+            &path,
+            "[Dark]\nbg = \"#000\"\n\n[Light]\nbg = \"#FFF\"\n\n[Dim]\nbg = \"#333\"\n",
+        )
+        .unwrap();
+        let mut light = read_colour_palettes(&config_folder).unwrap().remove(1);
+        light
+            .add_color("fg".to_string(), Color::from_hex("#111"))
+            .unwrap();
+        light.save_to(&path).unwrap();
+        light.name = "Bright".to_string();
+        light.save_to(&path).unwrap();
+
+        let reread = read_colour_palettes(&config_folder).unwrap();
+        let names = reread.iter().map(|palette| palette.name.as_str());
+        assert_eq!(names.collect::<Vec<_>>(), vec!["Dark", "Bright", "Dim"]);
+        assert_eq!(reread[1].uuid, light.uuid);
+        assert_eq!(reread[1].color_names(), vec!["bg", "fg"]);
+        assert_eq!(reread[2].get_color("bg").unwrap().hex(), "#333333");
+        fs::remove_dir_all(config_folder).unwrap();
+    }
+
+    #[test]
+    fn keeps_comments_of_other_palettes() {
+        let config_folder = testing::config_folder();
+        let path = config_folder.join("palettes/themes.toml");
+        let dark = "# After the original theme\n[Dark]\nbg = \"#000\" # pure black\n";
+        let dim = "\n# Dim\n[Dim]\nbg   = \"#333\"\n";
+        fs::write(
+            &path,
+            format!("{}\n# Light\n[Light]\nbg = \"#FFF\"\n{}", dark, dim),
+        )
+        .unwrap();
+        let mut light = read_colour_palettes(&config_folder).unwrap().remove(1);
+        light
+            .add_color("fg".to_string(), Color::from_hex("#111"))
+            .unwrap();
+        light.save_to(&path).unwrap();
+
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.starts_with(dark), "{}", written);
+        assert!(written.ends_with(dim), "{}", written);
+        assert!(
+            written.contains("\n# Light\n[Light]\nbg = \"#FFFFFF\"\nfg = \"#111111\"\n"),
+            "{}",
+            written
+        );
+        let mut extra = Palette::new("Extra", Some(path.clone()));
+        extra
+            .add_color("bg".to_string(), Color::from_hex("#444"))
+            .unwrap();
+        extra.save_to(&path).unwrap();
+        let appended = fs::read_to_string(&path).unwrap();
+        assert!(appended.starts_with(&written), "{}", appended);
+        assert!(
+            appended.contains("\n[Extra]\nbg = \"#444444\"\n"),
+            "{}",
+            appended
+        );
+
+        let reread = read_colour_palettes(&config_folder).unwrap();
+        assert_eq!(reread[1].uuid, light.uuid);
+        assert_eq!(reread[3].name, "Extra");
+        fs::remove_dir_all(config_folder).unwrap();
+    }
+
+    #[test]
+    fn datetime_metadata_round_trip() {
+        let config_folder = testing::config_folder();
+        let mut palette = Palette::new("Dated", None);
+        palette
+            .add_color("bg".to_string(), Color::from_hex("#000"))
+            .unwrap();
+        palette.metadata.created = Some("2023-04-05T06:07:08Z".parse().unwrap());
+        palette.metadata.modified = Some("2024-11-12T13:14:15+02:00".parse().unwrap());
+        let path = config_folder.join("palettes/dated.toml");
+        write_palette(&palette, &path).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.contains("created = 2023-04-05T06:07:08Z\n"));
+        assert!(written.contains("modified = 2024-11-12T13:14:15+02:00\n"));
+
+        let reread = read_colour_palettes(&config_folder).unwrap();
+        assert_eq!(reread[0].metadata.created, palette.metadata.created);
+        assert_eq!(reread[0].metadata.modified, palette.metadata.modified);
+        fs::remove_dir_all(config_folder).unwrap();
+    }
+}
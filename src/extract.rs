@@ -0,0 +1,601 @@
+//! Palette extraction from images, e.g. turning a wallpaper into a terminal scheme.
+use std::{collections::HashMap, fmt, path::Path, str::FromStr};
+
+use anyhow::Context;
+
+use crate::color::{Color, ContrastMethod};
+use crate::palette::{Palette, Variant};
+
+/// Images are scaled down to fit this many pixels a side before quantizing.
+const SAMPLE_SIZE: u32 = 256;
+const KMEANS_ITERATIONS: usize = 24;
+/// Contrast of the foreground on the background in a terminal scheme.
+const FOREGROUND_CONTRAST: f64 = 7.0;
+/// Contrast of bright black, typically used for comments, on the background.
+const DIM_CONTRAST: f64 = 3.0;
+/// Oklch lightness between a normal ANSI colour and its bright counterpart.
+const BRIGHT_STEP: f64 = 0.08;
+/// Oklch hues of ANSI red, green, yellow, blue, magenta and cyan.
+const ANSI_HUES: [f64; 6] = [29.0, 142.0, 110.0, 264.0, 328.0, 195.0];
+/// How far in hue an ANSI colour may stray from its canonical hue, in degrees.
+const MAX_HUE_OFFSET: f64 = 30.0;
+/// Colours with less Oklch chroma than this are treated as grey when matching hues.
+const MIN_CHROMA: f64 = 0.03;
+
+/// Colour quantization algorithm.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Quantizer {
+    MedianCut,
+    /// k-means clustering in Oklab, seeded from median cut.
+    #[default]
+    KMeans,
+    Octree,
+}
+
+impl FromStr for Quantizer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "median-cut" => Ok(Quantizer::MedianCut),
+            "k-means" | "kmeans" => Ok(Quantizer::KMeans),
+            "octree" => Ok(Quantizer::Octree),
+            other => Err(format!("Unknown quantizer: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for Quantizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Quantizer::MedianCut => write!(f, "median-cut"),
+            Quantizer::KMeans => write!(f, "k-means"),
+            Quantizer::Octree => write!(f, "octree"),
+        }
+    }
+}
+
+/// A colour found in an image and how many sampled pixels it stands for.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Swatch {
+    pub color: Color,
+    pub population: usize,
+}
+
+/// The opaque pixels of a PNG or JPEG image, scaled down to at most [`SAMPLE_SIZE`] a side.
+pub fn load_pixels(path: &Path) -> anyhow::Result<Vec<Color>> {
+    let mut image =
+        image::open(path).with_context(|| format!("Unable to read image {}", path.display()))?;
+    if image.width() > SAMPLE_SIZE || image.height() > SAMPLE_SIZE {
+        image = image.thumbnail(SAMPLE_SIZE, SAMPLE_SIZE);
+    }
+    let image = image.to_rgba8();
+    Ok(image
+        .pixels()
+        .filter(|pixel| pixel[3] > 127)
+        .map(|pixel| Color::new_solid(pixel[0], pixel[1], pixel[2]))
+        .collect())
+}
+
+/// Reduces `pixels` to at most `count` colours, most common first.
+pub fn quantize(pixels: &[Color], count: usize, quantizer: Quantizer) -> Vec<Swatch> {
+    let histogram = histogram(pixels);
+    if count == 0 || histogram.is_empty() {
+        return Vec::new();
+    }
+    let mut swatches = match quantizer {
+        Quantizer::MedianCut => median_cut(&histogram, count),
+        Quantizer::KMeans => k_means(&histogram, count),
+        Quantizer::Octree => octree(&histogram, count),
+    };
+    swatches.sort_by_key(|swatch| std::cmp::Reverse(swatch.population));
+    swatches
+}
+
+/// Distinct pixel colours with their counts, in a stable order.
+fn histogram(pixels: &[Color]) -> Vec<(Color, usize)> {
+    let mut counts: HashMap<Color, usize> = HashMap::new();
+    for pixel in pixels {
+        *counts.entry(pixel.to_opaque()).or_default() += 1;
+    }
+    let mut histogram = counts.into_iter().collect::<Vec<_>>();
+    histogram.sort_by_key(|(color, _)| (color.red, color.green, color.blue));
+    histogram
+}
+
+fn channels(color: &Color) -> [u8; 3] {
+    [color.red, color.green, color.blue]
+}
+
+/// Population weighted average of some histogram entries.
+fn average(entries: &[(Color, usize)]) -> Swatch {
+    let population = entries.iter().map(|(_, count)| count).sum::<usize>();
+    let mut sums = [0_f64; 3];
+    for (color, count) in entries {
+        for (sum, channel) in sums.iter_mut().zip(channels(color)) {
+            *sum += channel as f64 * *count as f64;
+        }
+    }
+    let [red, green, blue] = sums.map(|sum| (sum / population.max(1) as f64).round() as u8);
+    Swatch {
+        color: Color::new_solid(red, green, blue),
+        population,
+    }
+}
+
+fn median_cut(histogram: &[(Color, usize)], count: usize) -> Vec<Swatch> {
+    let mut boxes = vec![histogram.to_vec()];
+    while boxes.len() < count {
+        // Split the box with the widest channel range, weighted by how many pixels it holds.
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, entries)| entries.len() > 1)
+            .map(|(index, entries)| {
+                let (channel, range) = widest_channel(entries);
+                let population = entries.iter().map(|(_, count)| count).sum::<usize>();
+                (index, channel, range as f64 * (population as f64).sqrt())
+            })
+            .max_by(|a, b| a.2.total_cmp(&b.2));
+        let (index, channel) = match widest {
+            Some((index, channel, _)) => (index, channel),
+            None => break,
+        };
+        let mut entries = boxes.swap_remove(index);
+        entries.sort_by_key(|(color, _)| channels(color)[channel]);
+        let half = entries.iter().map(|(_, count)| count).sum::<usize>() / 2;
+        let mut seen = 0;
+        let split = entries
+            .iter()
+            .position(|(_, count)| {
+                seen += count;
+                seen > half
+            })
+            .unwrap_or(0)
+            .clamp(1, entries.len() - 1);
+        let upper = entries.split_off(split);
+        boxes.push(entries);
+        boxes.push(upper);
+    }
+    boxes.iter().map(|entries| average(entries)).collect()
+}
+
+/// The channel with the largest range in `entries`, and that range.
+fn widest_channel(entries: &[(Color, usize)]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let values = entries.iter().map(|(color, _)| channels(color)[channel]);
+            let range = values.clone().max().unwrap_or(0) - values.min().unwrap_or(0);
+            (channel, range)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap()
+}
+
+fn k_means(histogram: &[(Color, usize)], count: usize) -> Vec<Swatch> {
+    let points = histogram
+        .iter()
+        .map(|(color, weight)| (color.oklab(), *weight as f64))
+        .collect::<Vec<_>>();
+    let mut centroids = median_cut(histogram, count)
+        .iter()
+        .map(|swatch| swatch.color.oklab())
+        .collect::<Vec<_>>();
+    let distance = |a: (f64, f64, f64), b: (f64, f64, f64)| {
+        (a.0 - b.0).powi(2) + (a.1 - b.1).powi(2) + (a.2 - b.2).powi(2)
+    };
+    let mut assignment = vec![0; points.len()];
+    for _ in 0..KMEANS_ITERATIONS {
+        let mut changed = false;
+        for (point, assigned) in points.iter().zip(assignment.iter_mut()) {
+            let nearest = (0..centroids.len())
+                .min_by(|a, b| {
+                    distance(point.0, centroids[*a]).total_cmp(&distance(point.0, centroids[*b]))
+                })
+                .unwrap();
+            changed |= *assigned != nearest;
+            *assigned = nearest;
+        }
+        let mut sums = vec![(0.0, 0.0, 0.0, 0.0); centroids.len()];
+        for ((lab, weight), cluster) in points.iter().zip(&assignment) {
+            let sum = &mut sums[*cluster];
+            sum.0 += lab.0 * weight;
+            sum.1 += lab.1 * weight;
+            sum.2 += lab.2 * weight;
+            sum.3 += weight;
+        }
+        for (centroid, sum) in centroids.iter_mut().zip(&sums) {
+            if sum.3 > 0.0 {
+                *centroid = (sum.0 / sum.3, sum.1 / sum.3, sum.2 / sum.3);
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    let mut populations = vec![0; centroids.len()];
+    for ((_, count), cluster) in histogram.iter().zip(&assignment) {
+        populations[*cluster] += count;
+    }
+    centroids
+        .into_iter()
+        .zip(populations)
+        .filter(|(_, population)| *population > 0)
+        .map(|((l, a, b), population)| Swatch {
+            color: Color::from_oklab(l, a, b),
+            population,
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct OctreeNode {
+    children: [Option<usize>; 8],
+    sums: [u64; 3],
+    population: u64,
+    leaf: bool,
+}
+
+fn octree(histogram: &[(Color, usize)], count: usize) -> Vec<Swatch> {
+    const DEPTH: usize = 8;
+    let mut nodes = vec![OctreeNode::default()];
+    // Nodes with children, by depth, as candidates for merging.
+    let mut levels: Vec<Vec<usize>> = vec![vec![0]; 1];
+    levels.resize(DEPTH, Vec::new());
+    let mut leaves = 0;
+    for (color, weight) in histogram {
+        let [red, green, blue] = channels(color);
+        let mut node = 0;
+        for depth in 0..DEPTH {
+            nodes[node].population += *weight as u64;
+            let shift = 7 - depth;
+            let index = (((red >> shift) & 1) << 2
+                | ((green >> shift) & 1) << 1
+                | ((blue >> shift) & 1)) as usize;
+            node = match nodes[node].children[index] {
+                Some(child) => child,
+                None => {
+                    let child = nodes.len();
+                    let leaf = depth + 1 == DEPTH;
+                    nodes.push(OctreeNode {
+                        leaf,
+                        ..Default::default()
+                    });
+                    if leaf {
+                        leaves += 1;
+                    } else {
+                        levels[depth + 1].push(child);
+                    }
+                    nodes[node].children[index] = Some(child);
+                    child
+                }
+            };
+        }
+        let leaf = &mut nodes[node];
+        leaf.population += *weight as u64;
+        for (sum, channel) in leaf.sums.iter_mut().zip(channels(color)) {
+            *sum += channel as u64 * *weight as u64;
+        }
+    }
+
+    // Fold the least populated of the deepest nodes into themselves until few enough remain.
+    // The root is never folded; merge_closest takes over if its children are too many.
+    while leaves > count {
+        let level = match levels[1..].iter_mut().rev().find(|level| !level.is_empty()) {
+            Some(level) => level,
+            None => break,
+        };
+        let (position, _) = level
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, node)| nodes[**node].population)
+            .unwrap();
+        let node = level.swap_remove(position);
+        let children = nodes[node]
+            .children
+            .iter()
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        let mut sums = [0; 3];
+        for child in &children {
+            for (sum, child_sum) in sums.iter_mut().zip(nodes[*child].sums) {
+                *sum += child_sum;
+            }
+        }
+        let merged = &mut nodes[node];
+        merged.sums = sums;
+        merged.children = [None; 8];
+        merged.leaf = true;
+        leaves = leaves + 1 - children.len();
+    }
+
+    let mut swatches = Vec::new();
+    let mut stack = vec![0];
+    while let Some(node) = stack.pop() {
+        let node = &nodes[node];
+        if node.leaf {
+            let [red, green, blue] = node.sums.map(|sum| (sum / node.population.max(1)) as u8);
+            swatches.push(Swatch {
+                color: Color::new_solid(red, green, blue),
+                population: node.population as usize,
+            });
+        } else {
+            stack.extend(node.children.iter().flatten());
+        }
+    }
+    merge_closest(swatches, count)
+}
+
+/// Merges the two swatches closest in Oklab until at most `count` are left.
+fn merge_closest(mut swatches: Vec<Swatch>, count: usize) -> Vec<Swatch> {
+    while swatches.len() > count.max(1) {
+        let mut closest = (0, 1, f64::MAX);
+        for first in 0..swatches.len() {
+            for second in first + 1..swatches.len() {
+                let distance = swatches[first].color.delta_e_ok(&swatches[second].color);
+                if distance < closest.2 {
+                    closest = (first, second, distance);
+                }
+            }
+        }
+        let second = swatches.swap_remove(closest.1);
+        let first = swatches[closest.0];
+        swatches[closest.0] = average(&[
+            (first.color, first.population),
+            (second.color, second.population),
+        ]);
+    }
+    swatches
+}
+
+/// Names the swatches `swatch-1`, `swatch-2`, ... in a new palette.
+pub fn swatch_palette(name: &str, swatches: &[Swatch]) -> Palette {
+    let mut palette = Palette::new(name, None);
+    for (index, swatch) in swatches.iter().enumerate() {
+        palette
+            .colors
+            .insert(format!("swatch-{}", index + 1), swatch.color);
+    }
+    palette
+}
+
+/// A terminal scheme built from the swatches: `background`, `foreground`, `cursor` and
+/// `color0` to `color15`, named so the roles are filled by their default aliases.
+///
+/// The background comes from the darkest swatch for [`Variant::Dark`] and the lightest
+/// for [`Variant::Light`]. The ANSI colours take the swatch closest in hue to each of red,
+/// green, yellow, blue, magenta and cyan, turned towards that hue if it is far off, and are
+/// moved in lightness until they reach `min_contrast` on the background, bright colours
+/// included.
+pub fn terminal_palette(
+    name: &str,
+    swatches: &[Swatch],
+    variant: Variant,
+    min_contrast: f64,
+) -> Palette {
+    let method = ContrastMethod::Wcag;
+    let by_lightness = |a: &&Swatch, b: &&Swatch| a.color.oklab().0.total_cmp(&b.color.oklab().0);
+    let darkest = swatches.iter().min_by(by_lightness).map(|s| s.color);
+    let lightest = swatches.iter().max_by(by_lightness).map(|s| s.color);
+    let (base, text) = match variant {
+        Variant::Dark => (darkest, lightest),
+        Variant::Light => (lightest, darkest),
+    };
+    let tint = |color: Option<Color>, lightness: f64, max_chroma: f64| {
+        let (_, chroma, hue) = color.map(|c| c.oklch()).unwrap_or((0.0, 0.0, 0.0));
+        Color::from_oklch(lightness, chroma.min(max_chroma), hue)
+    };
+    let (background, foreground) = match variant {
+        Variant::Dark => (
+            tint(base, base.map_or(0.2, |c| c.oklab().0.min(0.22)), 0.04),
+            tint(text, 0.93, 0.03),
+        ),
+        Variant::Light => (
+            tint(base, base.map_or(0.97, |c| c.oklab().0.max(0.95)), 0.03),
+            tint(text, 0.25, 0.04),
+        ),
+    };
+    let foreground = foreground.with_contrast(&background, FOREGROUND_CONTRAST, method);
+    // Black and white keep their meaning in both variants, so on a light background
+    // black is the readable one and white sits close to the background.
+    let (black, white, bright_white) = match variant {
+        Variant::Dark => (
+            tint(base, 0.3, 0.04),
+            tint(text, 0.8, 0.03).with_contrast(&background, min_contrast, method),
+            foreground,
+        ),
+        Variant::Light => (foreground, tint(base, 0.85, 0.03), tint(base, 0.97, 0.02)),
+    };
+    let bright_black = black
+        .mix(&white, 0.4)
+        .with_contrast(&background, DIM_CONTRAST, method);
+
+    let mut ansi = vec![black; 16];
+    ansi[7] = white;
+    ansi[8] = bright_black;
+    ansi[15] = bright_white;
+    let chromatic = swatches
+        .iter()
+        .filter(|swatch| swatch.color.oklch().1 >= MIN_CHROMA)
+        .collect::<Vec<_>>();
+    for (slot, target_hue) in ANSI_HUES.iter().enumerate() {
+        let (lightness, chroma, hue) = chromatic
+            .iter()
+            .map(|swatch| swatch.color.oklch())
+            .min_by(|a, b| {
+                hue_distance(a.2, *target_hue).total_cmp(&hue_distance(b.2, *target_hue))
+            })
+            .unwrap_or((0.65, 0.1, *target_hue));
+        let color = Color::from_oklch(
+            lightness,
+            chroma.max(0.08),
+            toward_hue(hue, *target_hue, MAX_HUE_OFFSET),
+        );
+        // Whichever of the pair is nearer the background is made readable first, then the
+        // other is set a step further away so bright stays lighter than normal.
+        let (normal, bright) = match variant {
+            Variant::Dark => {
+                let normal = color.with_contrast(&background, min_contrast, method);
+                (normal, shift_lightness(&normal, BRIGHT_STEP))
+            }
+            Variant::Light => {
+                let bright = color.with_contrast(&background, min_contrast, method);
+                (shift_lightness(&bright, -BRIGHT_STEP), bright)
+            }
+        };
+        ansi[slot + 1] = normal;
+        ansi[slot + 9] = bright;
+    }
+
+    let mut palette = Palette::new(name, None);
+    palette.colors.insert("background".to_string(), background);
+    palette.colors.insert("foreground".to_string(), foreground);
+    palette.colors.insert("cursor".to_string(), foreground);
+    for (index, color) in ansi.into_iter().enumerate() {
+        palette.colors.insert(format!("color{}", index), color);
+    }
+    palette.metadata.variant = Some(variant);
+    palette
+}
+
+fn hue_distance(a: f64, b: f64) -> f64 {
+    let difference = (a - b).rem_euclid(360.0);
+    difference.min(360.0 - difference)
+}
+
+/// `hue` turned towards `target` until it is at most `max_offset` degrees away.
+fn toward_hue(hue: f64, target: f64, max_offset: f64) -> f64 {
+    let difference = (hue - target + 180.0).rem_euclid(360.0) - 180.0;
+    (target + difference.clamp(-max_offset, max_offset)).rem_euclid(360.0)
+}
+
+fn shift_lightness(color: &Color, amount: f64) -> Color {
+    let (lightness, chroma, hue) = color.oklch();
+    Color::from_oklch(lightness + amount, chroma, hue)
+}
+
+#[cfg(test)]
+mod extract_tests {
+    use super::*;
+    use crate::palette::ColorRole;
+    use crate::testing;
+    use std::fs;
+
+    /// Four flat blocks of colour, with more orange than anything else.
+    fn pixels() -> Vec<Color> {
+        let mut pixels = Vec::new();
+        for (hex, count) in [
+            ("#101820", 400),
+            ("#F08020", 900),
+            ("#2060C0", 300),
+            ("#E0E0D0", 200),
+        ] {
+            let color = Color::from_hex(hex);
+            // A little noise around the colour so the quantizers have something to merge.
+            for i in 0..count {
+                let jitter = |channel: u8, sign: i16| {
+                    (channel as i16 + sign * (i % 5 - 2)).clamp(0, 255) as u8
+                };
+                pixels.push(Color::new_solid(
+                    jitter(color.red, 1),
+                    jitter(color.green, -1),
+                    jitter(color.blue, 1),
+                ));
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn quantizers_find_the_blocks() {
+        let expected = ["#101820", "#F08020", "#2060C0", "#E0E0D0"].map(Color::from_hex);
+        for quantizer in [Quantizer::MedianCut, Quantizer::KMeans, Quantizer::Octree] {
+            let swatches = quantize(&pixels(), 4, quantizer);
+            assert_eq!(swatches.len(), 4, "{}", quantizer);
+            assert_eq!(
+                swatches.iter().map(|s| s.population).sum::<usize>(),
+                1800,
+                "{}",
+                quantizer
+            );
+            // Median cut splits at the median pixel, which can cut through a block of colour.
+            if quantizer == Quantizer::MedianCut {
+                continue;
+            }
+            assert_eq!(swatches[0].population, 900, "{}", quantizer);
+            for swatch in &swatches {
+                let nearest = expected
+                    .iter()
+                    .map(|color| color.delta_e(&swatch.color))
+                    .fold(f64::MAX, f64::min);
+                assert!(
+                    nearest < 3.0,
+                    "{}: {} is off by {}",
+                    quantizer,
+                    swatch.color.hex(),
+                    nearest
+                );
+            }
+        }
+        assert_eq!(quantize(&pixels(), 2, Quantizer::Octree).len(), 2);
+        assert_eq!(quantize(&[], 4, Quantizer::KMeans), vec![]);
+    }
+
+    #[test]
+    fn terminal_scheme_contrast() {
+        let swatches = quantize(&pixels(), 4, Quantizer::KMeans);
+        for variant in [Variant::Dark, Variant::Light] {
+            let palette = terminal_palette("Wallpaper", &swatches, variant, 4.5);
+            assert_eq!(palette.len(), 19);
+            assert_eq!(palette.polarity(), Some(variant));
+            let background = palette.background().unwrap();
+            assert!(palette.foreground().unwrap().contrast_ratio(&background) >= 7.0);
+            for index in (1..7).chain(9..15) {
+                let color = palette.role(ColorRole::Ansi(index)).unwrap();
+                assert!(
+                    color.contrast_ratio(&background) >= 4.5,
+                    "{:?} color{} {}",
+                    variant,
+                    index,
+                    color.hex()
+                );
+            }
+            for index in 1..7 {
+                let normal = palette.role(ColorRole::Ansi(index)).unwrap().oklab().0;
+                let bright = palette.role(ColorRole::Ansi(index + 8)).unwrap().oklab().0;
+                assert!(bright >= normal);
+            }
+            // Orange is the closest the image has to red and yellow.
+            let (_, _, red_hue) = palette.get_color("color1").unwrap().oklch();
+            assert!(hue_distance(red_hue, Color::from_hex("#F08020").oklch().2) < 5.0);
+        }
+    }
+
+    #[test]
+    fn hues() {
+        assert_eq!(toward_hue(55.0, 29.0, 30.0), 55.0);
+        assert_eq!(toward_hue(55.0, 142.0, 30.0), 112.0);
+        assert_eq!(toward_hue(350.0, 29.0, 30.0), 359.0);
+        assert_eq!(toward_hue(10.0, 328.0, 30.0), 358.0);
+        assert_eq!(hue_distance(350.0, 10.0), 20.0);
+    }
+
+    #[test]
+    fn load_png() {
+        let path = testing::temp_path("color-palette").with_extension("png");
+        let image = image::RgbaImage::from_fn(8, 4, |x, _| {
+            if x < 6 {
+                image::Rgba([255, 0, 0, 255])
+            } else {
+                image::Rgba([0, 0, 255, 0])
+            }
+        });
+        image.save(&path).unwrap();
+        let pixels = load_pixels(&path).unwrap();
+        assert_eq!(pixels.len(), 24);
+        assert!(pixels.iter().all(|pixel| pixel.hex() == "#FF0000"));
+        fs::remove_file(path).unwrap();
+        assert!(load_pixels(Path::new("missing.png")).is_err());
+    }
+}
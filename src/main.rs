@@ -1,33 +1,230 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use std::cell::RefCell;
+use std::env;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
 use slint::ComponentHandle;
+use slint::Model;
 use slint::ModelRc;
 use slint::SharedString;
 use slint::VecModel;
 
+mod cli;
 mod color;
 mod config;
+mod extract;
 mod palette;
 mod parser;
+#[cfg(test)]
+mod testing;
 
 slint::include_modules!();
 
+/// The tag filter entry that shows every palette.
+const ALL_TAGS: &str = "All tags";
+/// The sort entry that shows colours in the order of the palette file.
+const FILE_ORDER: &str = "File order";
+
 fn main() -> Result<(), slint::PlatformError> {
     let config_folder = config::config_palette_folder();
-    let color_palettes = config::reader::read_colour_palettes(&config_folder).unwrap();
+    if let Some(settings) = config::reader::read_program_config_file(&config::program_config_file())
+    {
+        palette::RoleAliases::from_settings(&settings).install();
+    }
+    match palette::ColorNamer::from_folder(&config_folder) {
+        Ok(namer) => {
+            namer.install();
+        }
+        Err(error) => eprintln!("Warning: {:#}", error),
+    }
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+    let color_palettes = match config::reader::read_colour_palettes(&config_folder) {
+        Ok(palettes) => palettes,
+        Err(error) => {
+            eprintln!("Error: {:#}", error);
+            std::process::exit(1);
+        }
+    };
+
+    let state = config::state::State::load(&config_folder);
+    let mut library = palette::PaletteLibrary::new(color_palettes).with_state(&state);
+    let tag_filter = Rc::new(RefCell::new(None::<String>));
 
     let ui = AppWindow::new()?;
-    let available_palette: Vec<SharedString> = color_palettes
-        .iter()
-        .map(|pal| pal.name.clone().into())
-        .collect();
-    let palette = &color_palettes[0];
-    ui.set_palette(palette.to_slint());
-    if let Some(theme) = palette.to_slint_theme() {
-        ui.global::<Theme>().set_background(theme.background);
-        ui.global::<Theme>().set_foreground(theme.foreground);
+    let sort_orders = std::iter::once(FILE_ORDER.to_string())
+        .chain(palette::SortOrder::all().map(|order| order.to_string()))
+        .map(SharedString::from)
+        .collect::<Vec<_>>();
+    ui.set_sort_orders(ModelRc::new(VecModel::from(sort_orders)));
+    ui.set_sort_order(FILE_ORDER.into());
+    show_library(&ui, &library, None);
+    let view = Rc::new(RefCell::new(None::<PaletteView>));
+    if let Some(palette) = library.selected() {
+        *view.borrow_mut() = Some(show_palette(&ui, palette));
     }
-    ui.set_available_palette(ModelRc::new(VecModel::from(available_palette)));
+
+    let ui_handle = ui.as_weak();
+    let tag = tag_filter.clone();
+    let state_folder = config_folder.clone();
+    let shown = view.clone();
+    library.subscribe(move |library, event| {
+        let ui = ui_handle.unwrap();
+        show_library(&ui, library, tag.borrow().as_deref());
+        if let Some(palette) = library.selected() {
+            *shown.borrow_mut() = Some(show_palette(&ui, palette));
+        }
+        if let palette::LibraryEvent::Selected(_) | palette::LibraryEvent::Favorites = event {
+            if let Err(error) = library.state().save(&state_folder) {
+                eprintln!("Warning: {:#}", error);
+            }
+        }
+    });
+
+    let library = Rc::new(RefCell::new(library));
+    let ui_handle = ui.as_weak();
+    let shown = view.clone();
+    ui.on_search(move |_| {
+        if let Some(view) = shown.borrow().as_ref() {
+            show_colors(&ui_handle.unwrap(), view);
+        }
+    });
+    let ui_handle = ui.as_weak();
+    let palettes = library.clone();
+    let shown = view.clone();
+    ui.on_sort(move |_| {
+        if let Some(palette) = palettes.borrow().selected() {
+            let ui = ui_handle.unwrap();
+            let view = PaletteView::new(&ui, palette);
+            show_colors(&ui, &view);
+            *shown.borrow_mut() = Some(view);
+        }
+    });
+    let ui_handle = ui.as_weak();
+    let shown = view.clone();
+    ui.on_transform(move |_| {
+        if let Some(view) = shown.borrow().as_ref() {
+            show_colors(&ui_handle.unwrap(), view);
+        }
+    });
+    let ui_handle = ui.as_weak();
+    let palettes = library.clone();
+    ui.on_apply_transform(move || {
+        let ui = ui_handle.unwrap();
+        let transforms = ui.get_transform_text();
+        let search = ui.get_search_text();
+        let applied = edit_selected(&palettes, |edited| {
+            let transforms = transforms
+                .split_whitespace()
+                .map(str::parse::<palette::Transform>)
+                .collect::<Result<Vec<_>, _>>()?;
+            let query = match search.trim() {
+                "" => None,
+                text => Some(text.parse::<palette::Query>()?),
+            };
+            edited.apply_transforms(&transforms, query.as_ref())?;
+            Ok(())
+        });
+        match applied {
+            Ok(()) => {
+                ui.set_transform_text(SharedString::new());
+                ui.invoke_transform(SharedString::new());
+            }
+            Err(error) => ui.set_transform_error(error.into()),
+        }
+    });
+    let ui_handle = ui.as_weak();
+    let palettes = library.clone();
+    ui.on_edit(move |text| {
+        let ui = ui_handle.unwrap();
+        let edited = edit_selected(&palettes, |edited| {
+            let edit = edited.parse_edit(&text)?;
+            edited.apply(edit).map_err(|error| error.to_string())
+        });
+        if edited.is_ok() {
+            ui.set_edit_text(SharedString::new());
+        }
+        show_edit_result(&ui, edited);
+    });
+    let ui_handle = ui.as_weak();
+    let palettes = library.clone();
+    ui.on_undo(move || {
+        let undone = edit_selected(&palettes, |edited| match edited.undo() {
+            true => Ok(()),
+            false => Err("Nothing to undo".to_string()),
+        });
+        show_edit_result(&ui_handle.unwrap(), undone);
+    });
+    let ui_handle = ui.as_weak();
+    let palettes = library.clone();
+    ui.on_redo(move || {
+        let redone = edit_selected(&palettes, |edited| match edited.redo() {
+            true => Ok(()),
+            false => Err("Nothing to redo".to_string()),
+        });
+        show_edit_result(&ui_handle.unwrap(), redone);
+    });
+    let ui_handle = ui.as_weak();
+    let palettes = library.clone();
+    ui.on_save_palette(move || {
+        let saved = edit_selected(&palettes, |edited| {
+            let file = edited
+                .filename
+                .clone()
+                .ok_or_else(|| format!("{} has no file to save to", edited.name))?;
+            edited
+                .save_to(&file)
+                .map_err(|error| format!("{:#}", error))
+        });
+        show_edit_result(&ui_handle.unwrap(), saved);
+    });
+    let palettes = library.clone();
+    ui.on_restore_revision(move |index| {
+        let restored = match palettes.borrow().selected() {
+            Some(palette) => restore_revision(palette, index as usize),
+            None => return,
+        };
+        match restored {
+            Ok(restored) => palettes.borrow_mut().insert(restored),
+            Err(error) => eprintln!("Error: {:#}", error),
+        }
+    });
+    let palettes = library.clone();
+    ui.on_select_palette(move |label| {
+        let id = palettes
+            .borrow()
+            .get_by_name(&label)
+            .map(|palette| palette.uuid);
+        if let Some(id) = id {
+            palettes.borrow_mut().select(&id);
+        }
+    });
+    let palettes = library.clone();
+    ui.on_toggle_favorite(move || {
+        let mut palettes = palettes.borrow_mut();
+        if let Some(id) = palettes.selected().map(|palette| palette.uuid) {
+            let favorite = palettes.is_favorite(&id);
+            palettes.set_favorite(&id, !favorite);
+        }
+    });
+    let ui_handle = ui.as_weak();
+    let palettes = library.clone();
+    ui.on_filter_tag(move |tag| {
+        let tag = (tag != ALL_TAGS).then(|| tag.to_string());
+        show_library(&ui_handle.unwrap(), &palettes.borrow(), tag.as_deref());
+        *tag_filter.borrow_mut() = tag;
+    });
+    let palettes = library.clone();
+    ui.on_refesh_palettes(
+        move || match config::reader::read_colour_palettes(&config_folder) {
+            Ok(loaded) => palettes.borrow_mut().reload(loaded),
+            Err(error) => eprintln!("Error: {:#}", error),
+        },
+    );
     ui.global::<Copy>().on_copy_on_click(move |text| {
         println!("{:?}", text);
         cli_clipboard::set_contents(text.to_string()).expect("");
@@ -35,3 +232,213 @@ fn main() -> Result<(), slint::PlatformError> {
 
     ui.run()
 }
+
+/// Offers the library's palettes for selection, only those tagged `tag` if given, and
+/// the tags to filter them by.
+fn show_library(ui: &AppWindow, library: &palette::PaletteLibrary, tag: Option<&str>) {
+    let labels = library
+        .labels(tag)
+        .into_iter()
+        .map(SharedString::from)
+        .collect::<Vec<_>>();
+    ui.set_available_palette(ModelRc::new(VecModel::from(labels)));
+    let tags = std::iter::once(ALL_TAGS.to_string())
+        .chain(library.tags())
+        .map(SharedString::from)
+        .collect::<Vec<_>>();
+    ui.set_available_tags(ModelRc::new(VecModel::from(tags)));
+    if let Some(palette) = library.selected() {
+        ui.set_selected_label(library.label(palette).into());
+        ui.set_is_favorite(library.is_favorite(&palette.uuid));
+    }
+}
+
+/// The selected palette in the chosen sort order, with how each of its colours is
+/// shown, kept while only the search box and transforms change.
+struct PaletteView {
+    palette: palette::Palette,
+    shown: PaletteType,
+    color_types: IndexMap<String, ColorType>,
+}
+
+impl PaletteView {
+    fn new(ui: &AppWindow, palette: &palette::Palette) -> Self {
+        let palette = match ui.get_sort_order().parse::<palette::SortOrder>() {
+            Ok(order) => palette.sorted(order),
+            Err(_) => palette.clone(),
+        };
+        let shown = palette.to_slint();
+        let color_types = shown
+            .colors
+            .iter()
+            .map(|color| (color.name.to_string(), color))
+            .collect();
+        PaletteView {
+            palette,
+            shown,
+            color_types,
+        }
+    }
+}
+
+/// Shows `palette` in the viewer, theming the window from its roles and listing its
+/// lints and statistics. Returns the view of its colours, see [`show_colors`].
+fn show_palette(ui: &AppWindow, palette: &palette::Palette) -> PaletteView {
+    show_history(ui, palette);
+    let view = PaletteView::new(ui, palette);
+    show_colors(ui, &view);
+    if let Some(theme) = palette.to_slint_theme() {
+        ui.global::<Theme>().set_background(theme.background);
+        ui.global::<Theme>().set_foreground(theme.foreground);
+    }
+    let diagnostics = palette
+        .lint()
+        .iter()
+        .map(|diagnostic| diagnostic.to_slint())
+        .collect::<Vec<DiagnosticType>>();
+    ui.set_diagnostics(ModelRc::new(VecModel::from(diagnostics)));
+    ui.set_stats(palette.stats().to_slint());
+    show_revisions(ui, palette);
+    view
+}
+
+/// Lists the colours of `view` matching the search box, in the chosen sort order, and
+/// previews the transforms typed in on them.
+fn show_colors(ui: &AppWindow, view: &PaletteView) {
+    let palette = &view.palette;
+    let search = ui.get_search_text();
+    let query = match search.trim() {
+        "" => Ok(None),
+        text => text.parse::<palette::Query>().map(Some),
+    };
+    let filtered = query
+        .clone()
+        .and_then(|query| query.map(|query| palette.filtered(&query)).transpose());
+    match filtered {
+        Ok(filtered) => {
+            ui.set_search_error(SharedString::new());
+            ui.set_palette(match filtered {
+                Some(filtered) => filtered.to_slint_with(&view.color_types),
+                None => view.shown.clone(),
+            });
+        }
+        Err(error) => {
+            ui.set_search_error(error.into());
+            ui.set_palette(view.shown.clone());
+        }
+    }
+    show_preview(ui, palette, query.ok().flatten().as_ref());
+}
+
+/// Offers undoing and redoing the palette's last edits, and saving it if it has a file.
+fn show_history(ui: &AppWindow, palette: &palette::Palette) {
+    let history = &palette.history;
+    ui.set_can_undo(history.can_undo());
+    ui.set_can_redo(history.can_redo());
+    ui.set_undo_text(edits_summary(&history.undo_edits()).into());
+    ui.set_redo_text(edits_summary(&history.redo_edits()).into());
+    ui.set_can_save(palette.filename.is_some());
+}
+
+/// What undoing or redoing `edits` does, e.g. ``recolor `red` `` or `3 edits`.
+fn edits_summary(edits: &[&palette::Edit]) -> String {
+    match edits {
+        [] => String::new(),
+        [edit] => edit.to_string(),
+        edits => format!("{} edits", edits.len()),
+    }
+}
+
+/// Changes a copy of the selected palette with `edit` and, if that succeeds, puts it
+/// in the library in place of the selected one.
+fn edit_selected(
+    library: &RefCell<palette::PaletteLibrary>,
+    edit: impl FnOnce(&mut palette::Palette) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut palette = match library.borrow().selected() {
+        Some(palette) => palette.clone(),
+        None => return Ok(()),
+    };
+    edit(&mut palette)?;
+    library.borrow_mut().insert(palette);
+    Ok(())
+}
+
+/// Shows why the last edit failed, or clears the message if it succeeded.
+fn show_edit_result(ui: &AppWindow, result: Result<(), String>) {
+    match result {
+        Ok(()) => ui.set_edit_error(SharedString::new()),
+        Err(error) => ui.set_edit_error(error.into()),
+    }
+}
+
+/// Lists the saved revisions of `palette`, newest first, with what changed since each.
+fn show_revisions(ui: &AppWindow, palette: &palette::Palette) {
+    let revisions = match config::revision::RevisionStore::from_program_config().list(&palette.uuid)
+    {
+        Ok(revisions) => revisions,
+        Err(error) => {
+            eprintln!("Warning: {:#}", error);
+            Vec::new()
+        }
+    };
+    let revisions = revisions
+        .iter()
+        .map(|revision| revision.to_slint(palette))
+        .collect::<Vec<RevisionType>>();
+    ui.set_revisions(ModelRc::new(VecModel::from(revisions)));
+}
+
+/// Restores the `index`th newest revision of `palette` to its file.
+fn restore_revision(palette: &palette::Palette, index: usize) -> anyhow::Result<palette::Palette> {
+    let store = config::revision::RevisionStore::from_program_config();
+    let revision = store
+        .list(&palette.uuid)?
+        .into_iter()
+        .nth(index)
+        .ok_or_else(|| anyhow::anyhow!("{} has no revision {}", palette.name, index + 1))?;
+    store.restore(palette, &revision)
+}
+
+/// Previews the transforms typed in the viewer, e.g. `temperature=0.3 chroma=0.9`, as
+/// the colours they change before and after.
+fn show_preview(ui: &AppWindow, palette: &palette::Palette, query: Option<&palette::Query>) {
+    let text = ui.get_transform_text();
+    let transformed = text
+        .split_whitespace()
+        .map(str::parse::<palette::Transform>)
+        .collect::<Result<Vec<_>, _>>()
+        .and_then(|transforms| match transforms.is_empty() {
+            true => Ok(None),
+            false => palette.transformed(&transforms, query).map(Some),
+        });
+    let preview = match transformed {
+        Ok(Some(transformed)) => {
+            ui.set_transform_error(SharedString::new());
+            palette
+                .colors
+                .iter()
+                .filter(|(name, color)| transformed.colors.get(*name) != Some(*color))
+                .map(|(name, before)| {
+                    let after = transformed.colors[name];
+                    PreviewType {
+                        name: name.clone().into(),
+                        before: before.to_slint(),
+                        after: after.to_slint(),
+                        before_hex: config::writer::color_to_string(before).into(),
+                        after_hex: config::writer::color_to_string(&after).into(),
+                    }
+                })
+                .collect()
+        }
+        Ok(None) => {
+            ui.set_transform_error(SharedString::new());
+            Vec::new()
+        }
+        Err(error) => {
+            ui.set_transform_error(error.into());
+            Vec::new()
+        }
+    };
+    ui.set_preview(ModelRc::new(VecModel::from(preview)));
+}
@@ -0,0 +1,357 @@
+use std::{fmt, str::FromStr};
+
+use super::{
+    group::{split_group, GROUP_SEPARATOR},
+    role::ANSI_NAMES,
+    ColorRole, Palette,
+};
+use crate::color::Color;
+
+/// Oklch lightness between a normal ANSI colour and its bright counterpart.
+const BRIGHT_STEP: f64 = 0.08;
+/// How far towards the foreground a derived selection background is mixed.
+const SELECTION_MIX: f64 = 0.25;
+
+/// The names [`Palette::normalize_ansi`] gives the sixteen ANSI colours.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum AnsiNaming {
+    /// `color0` to `color15`, as terminal configs and extracted schemes use.
+    #[default]
+    Numbered,
+    /// `black` to `white`, then `bright-black` to `bright-white`.
+    Named,
+}
+
+impl AnsiNaming {
+    /// The name of ANSI colour `index`, 0 to 15.
+    pub fn name(&self, index: u8) -> String {
+        let base = ANSI_NAMES[index as usize % 8];
+        match (self, index) {
+            (AnsiNaming::Numbered, _) => format!("color{}", index),
+            (AnsiNaming::Named, 0..=7) => base.to_string(),
+            (AnsiNaming::Named, _) => format!("bright-{}", base),
+        }
+    }
+}
+
+impl FromStr for AnsiNaming {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "numbered" | "color" => Ok(AnsiNaming::Numbered),
+            "named" | "name" => Ok(AnsiNaming::Named),
+            other => Err(format!("Unknown ANSI naming: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for AnsiNaming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnsiNaming::Numbered => write!(f, "numbered"),
+            AnsiNaming::Named => write!(f, "named"),
+        }
+    }
+}
+
+/// What [`Palette::normalize_ansi`] did.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct AnsiReport {
+    pub palette: String,
+    /// Colours given their canonical name, as `(name, new name)`.
+    pub renamed: Vec<(String, String)>,
+    /// Colours added, derived from the others.
+    pub filled: Vec<String>,
+    /// ANSI colours neither given nor derivable, as neither of the pair was given.
+    pub missing: Vec<ColorRole>,
+    /// Colours left under their name because another colour already had the canonical
+    /// one, as `(name, canonical name)`.
+    pub conflicts: Vec<(String, String)>,
+}
+
+impl fmt::Display for AnsiReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} renamed, {} filled",
+            self.palette,
+            self.renamed.len(),
+            self.filled.len()
+        )?;
+        for (name, renamed) in &self.renamed {
+            write!(f, "\n  renamed `{}` to `{}`", name, renamed)?;
+        }
+        for name in &self.filled {
+            write!(f, "\n  filled `{}`", name)?;
+        }
+        if !self.missing.is_empty() {
+            let missing = self.missing.iter().map(ColorRole::to_string);
+            write!(f, "\n  missing {}", missing.collect::<Vec<_>>().join(", "))?;
+        }
+        for (name, canonical) in &self.conflicts {
+            write!(f, "\n  kept `{}`, as `{}` is taken", name, canonical)?;
+        }
+        Ok(())
+    }
+}
+
+/// Roles a terminal scheme has besides its ANSI colours, in the order they are named.
+const TERMINAL_ROLES: [ColorRole; 6] = [
+    ColorRole::Background,
+    ColorRole::Foreground,
+    ColorRole::Cursor,
+    ColorRole::CursorText,
+    ColorRole::SelectionBackground,
+    ColorRole::SelectionForeground,
+];
+
+impl Palette {
+    /// Whether any colour fills an ANSI role, making this a terminal scheme.
+    pub fn is_terminal(&self) -> bool {
+        (0..16).any(|index| self.role_name(ColorRole::Ansi(index)).is_some())
+    }
+
+    /// A complete terminal scheme made from this one, and what changed.
+    ///
+    /// ANSI colours are found by their role aliases, so `color9`, `brightred` and
+    /// `bright-red` are all bright red. A missing bright colour is its normal one made
+    /// lighter in Oklch, and a missing normal colour its bright one made darker. Missing
+    /// cursor and selection colours are derived from the background and foreground, or
+    /// from ANSI black and white if those are missing too. The terminal colours are then
+    /// renamed to their first alias, ANSI colours by `naming` and kept in any group they
+    /// were in, and the ANSI colours moved together in order.
+    ///
+    /// A palette without ANSI colours is returned unchanged.
+    pub fn normalize_ansi(&self, naming: AnsiNaming) -> (Palette, AnsiReport) {
+        let mut palette = self.clone();
+        let mut report = AnsiReport {
+            palette: self.name.clone(),
+            ..AnsiReport::default()
+        };
+        let slots = (0..16)
+            .map(|index| self.role_name(ColorRole::Ansi(index)).map(str::to_string))
+            .collect::<Vec<_>>();
+        let group = match slots.iter().flatten().next() {
+            Some(name) => split_group(name).0.map(str::to_string),
+            None => return (palette, report),
+        };
+        let in_group = |name: String| match &group {
+            Some(group) => format!("{}{}{}", group, GROUP_SEPARATOR, name),
+            None => name,
+        };
+
+        let fill = |palette: &mut Palette, report: &mut AnsiReport, name: String, color: Color| {
+            // Nothing can derive from a colour the palette does not have yet.
+            if palette.colors.contains_key(&name) {
+                return;
+            }
+            palette.colors.insert(name.clone(), color);
+            report.filled.push(name);
+        };
+        for index in 0..8 {
+            let derived = match (&slots[index as usize], &slots[index as usize + 8]) {
+                (Some(normal), None) => Some((index + 8, &self.colors[normal], BRIGHT_STEP)),
+                (None, Some(bright)) => Some((index, &self.colors[bright], -BRIGHT_STEP)),
+                (Some(_), Some(_)) => None,
+                (None, None) => {
+                    report.missing.push(ColorRole::Ansi(index));
+                    report.missing.push(ColorRole::Ansi(index + 8));
+                    None
+                }
+            };
+            if let Some((slot, color, step)) = derived {
+                let (lightness, chroma, hue) = color.oklch();
+                let color = Color {
+                    opacity: color.opacity,
+                    ..Color::from_oklch(lightness + step, chroma, hue)
+                };
+                fill(
+                    &mut palette,
+                    &mut report,
+                    in_group(naming.name(slot)),
+                    color,
+                );
+            }
+        }
+        let ansi = |palette: &Palette, index: u8| palette.role(ColorRole::Ansi(index));
+        let background = palette.background().or_else(|| ansi(&palette, 0));
+        let foreground = palette
+            .foreground()
+            .or_else(|| ansi(&palette, 7))
+            .or_else(|| ansi(&palette, 15));
+        if let (Some(background), Some(foreground)) = (background, foreground) {
+            for (role, color) in [
+                (ColorRole::Cursor, foreground),
+                (ColorRole::CursorText, background),
+                (
+                    ColorRole::SelectionBackground,
+                    background.mix(&foreground, SELECTION_MIX),
+                ),
+                (ColorRole::SelectionForeground, foreground),
+            ] {
+                if palette.role_name(role).is_none() {
+                    fill(
+                        &mut palette,
+                        &mut report,
+                        role.default_aliases().remove(0),
+                        color,
+                    );
+                }
+            }
+        }
+
+        let canonical = TERMINAL_ROLES
+            .iter()
+            .map(|role| (*role, role.default_aliases().remove(0)))
+            .chain((0..16).map(|index| (ColorRole::Ansi(index), in_group(naming.name(index)))))
+            .collect::<Vec<_>>();
+        let mut named = Vec::<String>::new();
+        for (role, canonical) in canonical {
+            let name = match palette.role_name(role) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            // A colour filling several roles keeps the name of the first.
+            if named.contains(&name) {
+                continue;
+            }
+            if name == canonical {
+                named.push(name);
+            } else if palette.rename_color(&name, &canonical) {
+                report.renamed.push((name, canonical.clone()));
+                named.push(canonical);
+            } else {
+                report.conflicts.push((name.clone(), canonical));
+                named.push(name);
+            }
+        }
+
+        let ansi_names = (0..16)
+            .filter_map(|index| palette.role_name(ColorRole::Ansi(index)))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let first = ansi_names
+            .iter()
+            .filter_map(|name| palette.index_of(name))
+            .min()
+            .unwrap_or_default();
+        for (offset, name) in ansi_names.iter().enumerate() {
+            palette.move_color(name, first + offset);
+        }
+        (palette, report)
+    }
+}
+
+#[cfg(test)]
+mod ansi_tests {
+    use super::*;
+    use crate::testing;
+
+    fn eight_colors() -> Palette {
+        testing::palette(
+            "Eight",
+            &[
+                ("bg", "#1D1F21"),
+                ("fg", "#C5C8C6"),
+                ("Black", "#282A2E"),
+                ("Red", "#A54242"),
+                ("green", "#8C9440"),
+                ("YELLOW", "#DE935F"),
+                ("blue", "#5F819D"),
+                ("magenta", "#85678F"),
+                ("cyan", "#5E8D87"),
+                ("white", "#707880"),
+                ("BrightRed", "#CC6666"),
+            ],
+        )
+    }
+
+    #[test]
+    fn normalize_numbered() {
+        let palette = eight_colors();
+        let (normalized, report) = palette.normalize_ansi(AnsiNaming::Numbered);
+        let names = normalized.color_names();
+        let mut expected = vec!["background".to_string(), "foreground".to_string()];
+        expected.extend((0..16).map(|index| format!("color{}", index)));
+        expected.extend(
+            [
+                "cursor",
+                "cursor-text",
+                "selection-background",
+                "selection-foreground",
+            ]
+            .map(String::from),
+        );
+        assert_eq!(names, expected);
+        assert!(report.missing.is_empty());
+        assert!(report.conflicts.is_empty());
+        assert_eq!(normalized.colors["color9"], palette.colors["BrightRed"]);
+        assert_eq!(normalized.colors["color1"], palette.colors["Red"]);
+        assert_eq!(normalized.colors["cursor"], palette.colors["fg"]);
+        for index in 0..8 {
+            let normal = normalized.colors[&format!("color{}", index)].oklch().0;
+            let bright = normalized.colors[&format!("color{}", index + 8)].oklch().0;
+            assert!(bright > normal, "color{}", index + 8);
+        }
+        assert!(normalized
+            .lint()
+            .iter()
+            .all(|diagnostic| diagnostic.kind != crate::palette::lint::LintKind::MissingAnsi));
+        assert!(report.filled.contains(&"color8".to_string()));
+        assert!(!report.filled.contains(&"color9".to_string()));
+        assert!(report
+            .renamed
+            .contains(&("BrightRed".to_string(), "color9".to_string())));
+
+        let (again, report) = normalized.normalize_ansi(AnsiNaming::Numbered);
+        assert_eq!(again.colors, normalized.colors);
+        assert!(report.renamed.is_empty() && report.filled.is_empty());
+        assert!(report.missing.is_empty() && report.conflicts.is_empty());
+    }
+
+    #[test]
+    fn normalize_named_in_group() {
+        let mut palette = Palette::new("Grouped", None);
+        palette
+            .add_color("ansi.color1".to_string(), Color::from_hex("#CC0000"))
+            .unwrap();
+        palette
+            .add_color("ansi.color12".to_string(), Color::from_hex("#3366FF"))
+            .unwrap();
+        let (normalized, report) = palette.normalize_ansi(AnsiNaming::Named);
+        assert_eq!(
+            normalized.color_names()[..4],
+            [
+                "ansi.red",
+                "ansi.blue",
+                "ansi.bright-red",
+                "ansi.bright-blue"
+            ]
+        );
+        assert_eq!(report.missing.len(), 12);
+        assert!(report.missing.contains(&ColorRole::Ansi(15)));
+        assert!(normalized.role(ColorRole::Cursor).is_none());
+
+        let plain = Palette::new("Plain", None);
+        assert!(!plain.is_terminal());
+        assert!(plain.normalize_ansi(AnsiNaming::Named).1.filled.is_empty());
+    }
+
+    #[test]
+    fn conflicting_names() {
+        let mut palette = Palette::new("Both", None);
+        palette
+            .add_color("color1".to_string(), Color::from_hex("#CC0000"))
+            .unwrap();
+        palette
+            .add_color("red".to_string(), Color::from_hex("#DD0000"))
+            .unwrap();
+        let (normalized, report) = palette.normalize_ansi(AnsiNaming::Named);
+        assert_eq!(
+            report.conflicts,
+            vec![("color1".to_string(), "red".to_string())]
+        );
+        assert_eq!(normalized.colors["red"], palette.colors["red"]);
+    }
+}
@@ -0,0 +1,348 @@
+use std::fmt::Write;
+
+use serde_json::{json, Value};
+
+use super::Palette;
+use crate::color::Color;
+use crate::config::writer::color_to_string;
+
+/// One difference between two palettes.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Change {
+    Added {
+        name: String,
+        color: Color,
+    },
+    Removed {
+        name: String,
+        color: Color,
+    },
+    /// The same colour under a new name.
+    Renamed {
+        from: String,
+        to: String,
+        color: Color,
+    },
+    Changed {
+        name: String,
+        before: Color,
+        after: Color,
+        /// CIEDE2000 difference between `before` and `after`.
+        delta_e: f64,
+    },
+}
+
+impl Change {
+    /// Single character marking the kind of change, as used in the table.
+    pub fn symbol(&self) -> char {
+        match self {
+            Change::Added { .. } => '+',
+            Change::Removed { .. } => '-',
+            Change::Renamed { .. } => '>',
+            Change::Changed { .. } => '~',
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Change::Added { .. } => "added",
+            Change::Removed { .. } => "removed",
+            Change::Renamed { .. } => "renamed",
+            Change::Changed { .. } => "changed",
+        }
+    }
+}
+
+/// The differences between two palettes, from [`Palette::diff`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct PaletteDiff {
+    pub from: String,
+    pub to: String,
+    pub changes: Vec<Change>,
+}
+
+impl PaletteDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Aligned table of the changes, one per line.
+    pub fn to_table(&self) -> String {
+        let rows = self
+            .changes
+            .iter()
+            .map(|change| {
+                let (name, before, after, delta_e) = match change {
+                    Change::Added { name, color } => {
+                        (name.clone(), String::new(), color_to_string(color), None)
+                    }
+                    Change::Removed { name, color } => {
+                        (name.clone(), color_to_string(color), String::new(), None)
+                    }
+                    Change::Renamed { from, to, color } => (
+                        format!("{} -> {}", from, to),
+                        color_to_string(color),
+                        color_to_string(color),
+                        None,
+                    ),
+                    Change::Changed {
+                        name,
+                        before,
+                        after,
+                        delta_e,
+                    } => (
+                        name.clone(),
+                        color_to_string(before),
+                        color_to_string(after),
+                        Some(*delta_e),
+                    ),
+                };
+                let delta_e = delta_e
+                    .map(|delta_e| format!("{:.2}", delta_e))
+                    .unwrap_or_default();
+                [change.symbol().to_string(), name, before, after, delta_e]
+            })
+            .collect::<Vec<_>>();
+        let header = ["", "name", "before", "after", "ΔE"].map(String::from);
+        let mut widths = [0; 5];
+        for row in rows.iter().chain([&header]) {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let mut table = format!("{} -> {}: {}\n", self.from, self.to, self.summary());
+        for row in [&header].into_iter().chain(&rows) {
+            let line = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ");
+            table.push_str(line.trim_end());
+            table.push('\n');
+        }
+        table
+    }
+
+    /// The changes in the style of a unified diff of the palette file.
+    pub fn to_unified(&self) -> String {
+        let mut diff = format!("--- {}\n+++ {}\n", self.from, self.to);
+        for change in &self.changes {
+            let _ = match change {
+                Change::Added { name, color } => {
+                    writeln!(diff, "+{} = \"{}\"", name, color_to_string(color))
+                }
+                Change::Removed { name, color } => {
+                    writeln!(diff, "-{} = \"{}\"", name, color_to_string(color))
+                }
+                Change::Renamed { from, to, color } => writeln!(
+                    diff,
+                    "-{from} = \"{color}\"\n+{to} = \"{color}\"  # renamed from {from}",
+                    from = from,
+                    to = to,
+                    color = color_to_string(color)
+                ),
+                Change::Changed {
+                    name,
+                    before,
+                    after,
+                    delta_e,
+                } => writeln!(
+                    diff,
+                    "-{name} = \"{}\"\n+{name} = \"{}\"  # ΔE {:.2}",
+                    color_to_string(before),
+                    color_to_string(after),
+                    delta_e,
+                    name = name
+                ),
+            };
+        }
+        diff
+    }
+
+    pub fn to_json(&self) -> Value {
+        let changes = self
+            .changes
+            .iter()
+            .map(|change| match change {
+                Change::Added { name, color } => json!({
+                    "change": change.kind(),
+                    "name": name,
+                    "after": color_to_string(color),
+                }),
+                Change::Removed { name, color } => json!({
+                    "change": change.kind(),
+                    "name": name,
+                    "before": color_to_string(color),
+                }),
+                Change::Renamed { from, to, color } => json!({
+                    "change": change.kind(),
+                    "from": from,
+                    "to": to,
+                    "color": color_to_string(color),
+                }),
+                Change::Changed {
+                    name,
+                    before,
+                    after,
+                    delta_e,
+                } => json!({
+                    "change": change.kind(),
+                    "name": name,
+                    "before": color_to_string(before),
+                    "after": color_to_string(after),
+                    "delta_e": (delta_e * 100.0).round() / 100.0,
+                }),
+            })
+            .collect::<Vec<_>>();
+        json!({ "from": self.from, "to": self.to, "changes": changes })
+    }
+
+    /// Count of each kind of change, e.g. `1 added, 2 changed`.
+    pub fn summary(&self) -> String {
+        if self.is_empty() {
+            return "no changes".to_string();
+        }
+        ["added", "removed", "renamed", "changed"]
+            .iter()
+            .filter_map(
+                |kind| match self.changes.iter().filter(|c| c.kind() == *kind).count() {
+                    0 => None,
+                    count => Some(format!("{} {}", count, kind)),
+                },
+            )
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl Palette {
+    /// What changed going from this palette to `other`.
+    ///
+    /// A removed colour whose exact value reappears under a new name is reported as renamed.
+    /// Changes follow the order of `self`, with additions from `other` last.
+    pub fn diff(&self, other: &Palette) -> PaletteDiff {
+        let mut changes = Vec::new();
+        let mut added = other
+            .colors
+            .iter()
+            .filter(|(name, _)| !self.colors.contains_key(*name))
+            .collect::<Vec<_>>();
+        for (name, before) in &self.colors {
+            match other.colors.get(name) {
+                Some(after) if after != before => changes.push(Change::Changed {
+                    name: name.clone(),
+                    before: *before,
+                    after: *after,
+                    delta_e: before.delta_e(after),
+                }),
+                Some(_) => {}
+                None => match added.iter().position(|(_, color)| *color == before) {
+                    Some(index) => {
+                        let (to, _) = added.remove(index);
+                        changes.push(Change::Renamed {
+                            from: name.clone(),
+                            to: to.clone(),
+                            color: *before,
+                        });
+                    }
+                    None => changes.push(Change::Removed {
+                        name: name.clone(),
+                        color: *before,
+                    }),
+                },
+            }
+        }
+        for (name, color) in added {
+            changes.push(Change::Added {
+                name: name.clone(),
+                color: *color,
+            });
+        }
+        PaletteDiff {
+            from: self.name.clone(),
+            to: other.name.clone(),
+            changes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod diff_tests {
+    use super::*;
+    use crate::testing;
+
+    fn palettes() -> (Palette, Palette) {
+        let before = testing::palette(
+            "Before",
+            &[
+                ("bg", "#000"),
+                ("fg", "#FFF"),
+                ("red", "#C00"),
+                ("old", "#123"),
+            ],
+        );
+        let after = testing::palette(
+            "After",
+            &[
+                ("base", "#000"),
+                ("fg", "#FFF"),
+                ("red", "#D00"),
+                ("new", "#F80"),
+            ],
+        );
+        (before, after)
+    }
+
+    #[test]
+    fn diff() {
+        let (before, after) = palettes();
+        let diff = before.diff(&after);
+        assert_eq!(
+            diff.changes.iter().map(Change::kind).collect::<Vec<_>>(),
+            vec!["renamed", "changed", "removed", "added"]
+        );
+        assert_eq!(
+            diff.changes[0],
+            Change::Renamed {
+                from: "bg".to_string(),
+                to: "base".to_string(),
+                color: Color::from_hex("#000")
+            }
+        );
+        assert_eq!(diff.summary(), "1 added, 1 removed, 1 renamed, 1 changed");
+        assert!(before.diff(&before).is_empty());
+    }
+
+    #[test]
+    fn renderings() {
+        let (before, after) = palettes();
+        let diff = before.diff(&after);
+        assert_eq!(
+            diff.to_table(),
+            "Before -> After: 1 added, 1 removed, 1 renamed, 1 changed
+   name        before   after    ΔE
+>  bg -> base  #000000  #000000
+~  red         #CC0000  #DD0000  3.56
+-  old         #112233
++  new                  #FF8800
+"
+        );
+        assert_eq!(
+            diff.to_unified(),
+            "--- Before
++++ After
+-bg = \"#000000\"
++base = \"#000000\"  # renamed from bg
+-red = \"#CC0000\"
++red = \"#DD0000\"  # ΔE 3.56
+-old = \"#112233\"
++new = \"#FF8800\"
+"
+        );
+        assert_eq!(
+            diff.to_json()["changes"][1],
+            json!({"change": "changed", "name": "red", "before": "#CC0000", "after": "#DD0000", "delta_e": 3.56})
+        );
+    }
+}
@@ -0,0 +1,272 @@
+use std::{collections::HashSet, error::Error, fmt};
+
+use indexmap::IndexMap;
+
+use crate::color::Color;
+use crate::parser::{parse_expression, Expr};
+
+/// Why a palette entry's expression could not be resolved.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ExpressionError {
+    Parse {
+        entry: String,
+        input: String,
+        message: String,
+    },
+    UnknownColor {
+        entry: String,
+        reference: String,
+    },
+    Cycle(Vec<String>),
+    UnknownFunction {
+        entry: String,
+        function: String,
+    },
+    Arguments {
+        entry: String,
+        function: String,
+        expected: &'static str,
+    },
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpressionError::Parse {
+                entry,
+                input,
+                message,
+            } => write!(f, "`{}`: invalid colour `{}`, {}", entry, input, message),
+            ExpressionError::UnknownColor { entry, reference } => {
+                write!(f, "`{}`: refers to unknown colour `${}`", entry, reference)
+            }
+            ExpressionError::Cycle(names) => {
+                write!(f, "colour references form a cycle: {}", names.join(" -> "))
+            }
+            ExpressionError::UnknownFunction { entry, function } => write!(
+                f,
+                "`{}`: unknown function `{}`, expected one of {}",
+                entry,
+                function,
+                FUNCTIONS.join(", ")
+            ),
+            ExpressionError::Arguments {
+                entry,
+                function,
+                expected,
+            } => write!(f, "`{}`: `{}` takes {}", entry, function, expected),
+        }
+    }
+}
+
+impl Error for ExpressionError {}
+
+/// Functions available in palette expressions.
+pub const FUNCTIONS: [&str; 6] = [
+    "lighten",
+    "darken",
+    "saturate",
+    "desaturate",
+    "mix",
+    "alpha",
+];
+
+/// Parses the source of an entry, naming the entry in the error.
+pub fn parse_entry(entry: &str, input: &str) -> Result<Expr, ExpressionError> {
+    parse_expression(input).map_err(|message| ExpressionError::Parse {
+        entry: entry.to_string(),
+        input: input.to_string(),
+        message,
+    })
+}
+
+/// Evaluates `expr` for `entry`, looking referenced colours up with `lookup`.
+pub fn evaluate(
+    entry: &str,
+    expr: &Expr,
+    lookup: &mut dyn FnMut(&str) -> Result<Color, ExpressionError>,
+) -> Result<Color, ExpressionError> {
+    let arguments = |function: &str, expected: &'static str| ExpressionError::Arguments {
+        entry: entry.to_string(),
+        function: function.to_string(),
+        expected,
+    };
+    match expr {
+        Expr::Color(color) => Ok(*color),
+        Expr::Reference(name) => lookup(name),
+        Expr::Number(_) => Err(arguments("value", "a colour, not a number")),
+        Expr::Call(function, args) => match (function.as_str(), args.as_slice()) {
+            (
+                "lighten" | "darken" | "saturate" | "desaturate" | "alpha",
+                [color, Expr::Number(amount)],
+            ) => {
+                let color = evaluate(entry, color, lookup)?;
+                Ok(match function.as_str() {
+                    "lighten" => color.lighten(*amount),
+                    "darken" => color.darken(*amount),
+                    "saturate" => color.saturate(*amount),
+                    "desaturate" => color.desaturate(*amount),
+                    _ => color.with_alpha(*amount),
+                })
+            }
+            ("lighten" | "darken" | "saturate" | "desaturate" | "alpha", _) => {
+                Err(arguments(function, "a colour and an amount"))
+            }
+            ("mix", [first, second, Expr::Number(weight)]) => {
+                let first = evaluate(entry, first, lookup)?;
+                let second = evaluate(entry, second, lookup)?;
+                Ok(first.mix(&second, *weight))
+            }
+            ("mix", [first, second]) => {
+                let first = evaluate(entry, first, lookup)?;
+                let second = evaluate(entry, second, lookup)?;
+                Ok(first.mix(&second, 0.5))
+            }
+            ("mix", _) => Err(arguments(function, "two colours and an optional weight")),
+            _ => Err(ExpressionError::UnknownFunction {
+                entry: entry.to_string(),
+                function: function.clone(),
+            }),
+        },
+    }
+}
+
+/// Evaluates every expression in `expressions`, writing the results into `colors`.
+///
+/// Entries without an expression are used as they are. Fails on the first
+/// unparsable expression, unknown reference or reference cycle.
+pub fn resolve(
+    colors: &mut IndexMap<String, Color>,
+    expressions: &IndexMap<String, String>,
+) -> Result<(), ExpressionError> {
+    let parsed = expressions
+        .iter()
+        .map(|(entry, input)| Ok((entry.clone(), parse_entry(entry, input)?)))
+        .collect::<Result<IndexMap<String, Expr>, ExpressionError>>()?;
+    let mut resolved = HashSet::new();
+    let mut stack = Vec::new();
+    for entry in parsed.keys() {
+        resolve_entry(entry, &parsed, colors, &mut resolved, &mut stack)?;
+    }
+    Ok(())
+}
+
+fn resolve_entry(
+    entry: &str,
+    parsed: &IndexMap<String, Expr>,
+    colors: &mut IndexMap<String, Color>,
+    resolved: &mut HashSet<String>,
+    stack: &mut Vec<String>,
+) -> Result<Color, ExpressionError> {
+    let expr = match parsed.get(entry) {
+        Some(expr) if !resolved.contains(entry) => expr,
+        _ => {
+            return colors
+                .get(entry)
+                .copied()
+                .ok_or_else(|| ExpressionError::UnknownColor {
+                    entry: stack.last().cloned().unwrap_or_default(),
+                    reference: entry.to_string(),
+                })
+        }
+    };
+    if let Some(start) = stack.iter().position(|name| name == entry) {
+        let mut cycle = stack[start..].to_vec();
+        cycle.push(entry.to_string());
+        return Err(ExpressionError::Cycle(cycle));
+    }
+    stack.push(entry.to_string());
+    let color = evaluate(entry, expr, &mut |reference| {
+        resolve_entry(reference, parsed, colors, resolved, stack)
+    })?;
+    stack.pop();
+    colors.insert(entry.to_string(), color);
+    resolved.insert(entry.to_string());
+    Ok(color)
+}
+
+#[cfg(test)]
+mod expression_tests {
+    use super::*;
+
+    fn palette(entries: &[(&str, &str)]) -> (IndexMap<String, Color>, IndexMap<String, String>) {
+        let mut colors = IndexMap::new();
+        let mut expressions = IndexMap::new();
+        for (name, value) in entries {
+            match parse_expression(value) {
+                Ok(Expr::Color(color)) => {
+                    colors.insert(name.to_string(), color);
+                }
+                _ => {
+                    colors.insert(name.to_string(), Color::new(0, 0, 0, 0));
+                    expressions.insert(name.to_string(), value.to_string());
+                }
+            }
+        }
+        (colors, expressions)
+    }
+
+    #[test]
+    fn resolves_references_in_any_order() {
+        let (mut colors, expressions) = palette(&[
+            ("border", "mix($bg, $fg, 20%)"),
+            ("cursor", "$fg"),
+            ("bg", "#000"),
+            ("fg", "#FFF"),
+            ("color1", "#F00"),
+            ("color9", "lighten($color1, 25%)"),
+        ]);
+        resolve(&mut colors, &expressions).unwrap();
+        assert_eq!(colors["border"].hex(), "#333333");
+        assert_eq!(colors["cursor"].hex(), "#FFFFFF");
+        assert_eq!(colors["color9"].hex(), "#FF8080");
+        assert_eq!(colors.get_index_of("border"), Some(0));
+    }
+
+    #[test]
+    fn reports_cycles() {
+        let (mut colors, expressions) = palette(&[
+            ("a", "$b"),
+            ("b", "lighten($c, 10%)"),
+            ("c", "mix($a, #FFF)"),
+        ]);
+        let error = resolve(&mut colors, &expressions).unwrap_err();
+        assert_eq!(
+            error,
+            ExpressionError::Cycle(vec![
+                "a".to_string(),
+                "b".to_string(),
+                "c".to_string(),
+                "a".to_string()
+            ])
+        );
+        assert_eq!(
+            error.to_string(),
+            "colour references form a cycle: a -> b -> c -> a"
+        );
+    }
+
+    #[test]
+    fn reports_bad_entries() {
+        let (mut colors, expressions) = palette(&[("a", "$missing")]);
+        assert_eq!(
+            resolve(&mut colors, &expressions).unwrap_err().to_string(),
+            "`a`: refers to unknown colour `$missing`"
+        );
+        let (mut colors, expressions) = palette(&[("a", "brighten(#FFF, 10%)")]);
+        assert!(matches!(
+            resolve(&mut colors, &expressions),
+            Err(ExpressionError::UnknownFunction { .. })
+        ));
+        let (mut colors, expressions) = palette(&[("a", "lighten(#FFF)")]);
+        assert_eq!(
+            resolve(&mut colors, &expressions).unwrap_err().to_string(),
+            "`a`: `lighten` takes a colour and an amount"
+        );
+        let (mut colors, expressions) = palette(&[("a", "#FFFF")]);
+        assert!(matches!(
+            resolve(&mut colors, &expressions),
+            Err(ExpressionError::Parse { .. })
+        ));
+    }
+}
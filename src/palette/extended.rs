@@ -0,0 +1,179 @@
+use std::fmt::Write;
+
+use super::{ansi::AnsiNaming, ColorRole, Palette};
+use crate::color::Color;
+
+/// Index of the first colour of the 6×6×6 cube.
+pub const CUBE_START: u8 = 16;
+/// Index of the first colour of the grayscale ramp, which runs to 255.
+pub const GRAYSCALE_START: u8 = 232;
+const CUBE_STEPS: usize = 6;
+const GRAYSCALE_STEPS: usize = 24;
+
+/// Interpolates in CIELAB from `from` at 0.0 to `to` at 1.0.
+fn lerp_lab(from: (f64, f64, f64), to: (f64, f64, f64), t: f64) -> (f64, f64, f64) {
+    (
+        from.0 + (to.0 - from.0) * t,
+        from.1 + (to.1 - from.1) * t,
+        from.2 + (to.2 - from.2) * t,
+    )
+}
+
+impl Palette {
+    /// The eight colours at the corners of the cube: background, red, green, yellow,
+    /// blue, magenta, cyan and foreground. ANSI black and white stand in for a missing
+    /// background and foreground.
+    fn cube_corners(&self) -> Result<[Color; 8], String> {
+        let ansi = |index: u8| {
+            self.role(ColorRole::Ansi(index))
+                .ok_or_else(|| format!("{} has no {}", self.name, ColorRole::Ansi(index)))
+        };
+        let background = self.background().map(Ok).unwrap_or_else(|| ansi(0))?;
+        let foreground = self.foreground().map(Ok).unwrap_or_else(|| ansi(7))?;
+        Ok([
+            background.to_opaque(),
+            ansi(1)?.to_opaque(),
+            ansi(2)?.to_opaque(),
+            ansi(3)?.to_opaque(),
+            ansi(4)?.to_opaque(),
+            ansi(5)?.to_opaque(),
+            ansi(6)?.to_opaque(),
+            foreground.to_opaque(),
+        ])
+    }
+
+    /// Colours 16 to 255 of the 256-colour palette, harmonised with this scheme.
+    ///
+    /// Rather than the fixed xterm values, the 6×6×6 cube is interpolated in CIELAB
+    /// between the background, the six ANSI hues and the foreground at its corners, and
+    /// the 24-step grayscale ramp runs from the background to the foreground, both ends
+    /// left out. Fails if a corner is missing.
+    pub fn extended_colors(&self) -> Result<Vec<Color>, String> {
+        let corners = self.cube_corners()?.map(|color| color.lab());
+        let step = |index: usize| index as f64 / (CUBE_STEPS - 1) as f64;
+        let mut colors = Vec::with_capacity(256 - CUBE_START as usize);
+        for red in 0..CUBE_STEPS {
+            let r = step(red);
+            let black_red = lerp_lab(corners[0], corners[1], r);
+            let green_yellow = lerp_lab(corners[2], corners[3], r);
+            let blue_magenta = lerp_lab(corners[4], corners[5], r);
+            let cyan_white = lerp_lab(corners[6], corners[7], r);
+            for green in 0..CUBE_STEPS {
+                let g = step(green);
+                let without_blue = lerp_lab(black_red, green_yellow, g);
+                let with_blue = lerp_lab(blue_magenta, cyan_white, g);
+                for blue in 0..CUBE_STEPS {
+                    let (l, a, b) = lerp_lab(without_blue, with_blue, step(blue));
+                    colors.push(Color::from_lab(l, a, b));
+                }
+            }
+        }
+        debug_assert_eq!(colors.len(), (GRAYSCALE_START - CUBE_START) as usize);
+        for gray in 0..GRAYSCALE_STEPS {
+            let t = (gray + 1) as f64 / (GRAYSCALE_STEPS + 1) as f64;
+            let (l, a, b) = lerp_lab(corners[0], corners[7], t);
+            colors.push(Color::from_lab(l, a, b));
+        }
+        Ok(colors)
+    }
+
+    /// A copy of the palette with colours 16 to 255 from [`Palette::extended_colors`]
+    /// added as `color16` to `color255`, replacing any already there.
+    pub fn extended_256(&self) -> Result<Palette, String> {
+        let colors = self.extended_colors()?;
+        let mut palette = self.clone();
+        for (index, color) in (CUBE_START..=255).zip(colors) {
+            let name = AnsiNaming::Numbered.name(index);
+            palette.expressions.shift_remove(&name);
+            palette.colors.insert(name, color);
+        }
+        Ok(palette)
+    }
+
+    /// OSC 4 escape sequences setting colours 16 to 255 of the terminal to
+    /// [`Palette::extended_colors`], each terminated by ST.
+    pub fn osc4_sequences(&self) -> Result<String, String> {
+        let mut sequences = String::new();
+        for (index, color) in (CUBE_START..=255).zip(self.extended_colors()?) {
+            let (red, green, blue) = color.rgb();
+            let _ = write!(
+                sequences,
+                "\x1b]4;{};rgb:{:02x}/{:02x}/{:02x}\x1b\\",
+                index, red, green, blue
+            );
+        }
+        Ok(sequences)
+    }
+}
+
+#[cfg(test)]
+mod extended_tests {
+    use super::*;
+
+    fn base16() -> Palette {
+        let mut palette = Palette::new("Base", None);
+        palette
+            .add_color("background".to_string(), Color::from_hex("#1E1E2E"))
+            .unwrap();
+        palette
+            .add_color("foreground".to_string(), Color::from_hex("#CDD6F4"))
+            .unwrap();
+        for (index, hex) in [
+            "#45475A", "#F38BA8", "#A6E3A1", "#F9E2AF", "#89B4FA", "#F5C2E7", "#94E2D5", "#BAC2DE",
+        ]
+        .iter()
+        .enumerate()
+        {
+            palette
+                .add_color(format!("color{}", index), Color::from_hex(hex))
+                .unwrap();
+        }
+        palette
+    }
+
+    #[test]
+    fn cube_and_ramp() {
+        let palette = base16();
+        let colors = palette.extended_colors().unwrap();
+        assert_eq!(colors.len(), 240);
+        let at = |index: u8| colors[(index - CUBE_START) as usize];
+        let close = |a: Color, b: Color| a.delta_e(&b) < 1.0;
+        // Corners of the cube: 16 is 0,0,0 and 231 is 5,5,5; red is 5,0,0.
+        assert!(close(at(16), palette.colors["background"]));
+        assert!(close(at(231), palette.colors["foreground"]));
+        assert!(close(at(196), palette.colors["color1"]));
+        assert!(close(at(46), palette.colors["color2"]));
+        assert!(close(at(21), palette.colors["color4"]));
+        let lightness = (GRAYSCALE_START..=255)
+            .map(|index| at(index).lab().0)
+            .collect::<Vec<_>>();
+        assert!(lightness.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(lightness[0] > palette.colors["background"].lab().0);
+        assert!(lightness[23] < palette.colors["foreground"].lab().0);
+    }
+
+    #[test]
+    fn palette_and_osc4() {
+        let palette = base16();
+        let extended = palette.extended_256().unwrap();
+        assert_eq!(extended.len(), palette.len() + 240);
+        assert_eq!(extended.color_names().last().unwrap(), "color255");
+
+        let sequences = palette.osc4_sequences().unwrap();
+        assert_eq!(sequences.matches("\x1b]4;").count(), 240);
+        let (red, green, blue) = extended.colors["color16"].rgb();
+        assert!(sequences.starts_with(&format!(
+            "\x1b]4;16;rgb:{:02x}/{:02x}/{:02x}\x1b\\",
+            red, green, blue
+        )));
+
+        let mut partial = Palette::new("Partial", None);
+        partial
+            .add_color("color1".to_string(), Color::from_hex("#F00"))
+            .unwrap();
+        assert_eq!(
+            partial.extended_colors().unwrap_err(),
+            "Partial has no ansi0"
+        );
+    }
+}
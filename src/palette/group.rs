@@ -0,0 +1,124 @@
+use indexmap::IndexMap;
+
+use super::Palette;
+use crate::color::Color;
+
+/// Joins a group and a colour name, e.g. `ui.border` for `border` in `[MyTheme.ui]`.
+pub const GROUP_SEPARATOR: char = '.';
+
+/// The group part and the colour part of a name; `None` for colours outside any group.
+pub fn split_group(name: &str) -> (Option<&str>, &str) {
+    match name.rsplit_once(GROUP_SEPARATOR) {
+        Some((group, leaf)) => (Some(group), leaf),
+        None => (None, name),
+    }
+}
+
+/// A colour's name within its group, `border` for `ui.border`.
+pub fn leaf_name(name: &str) -> &str {
+    split_group(name).1
+}
+
+/// Colours sharing a group, in palette order.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ColorGroup<'a> {
+    /// Group path such as `ui` or `syntax.keyword`, empty for colours outside any group.
+    pub name: &'a str,
+    /// Full names of the colours and their values.
+    pub colors: Vec<(&'a str, &'a Color)>,
+}
+
+impl Palette {
+    /// The colours grouped by their group, groups in the order they first appear.
+    pub fn groups(&self) -> Vec<ColorGroup<'_>> {
+        let mut groups: IndexMap<&str, Vec<(&str, &Color)>> = IndexMap::new();
+        for (name, color) in &self.colors {
+            let group = split_group(name).0.unwrap_or_default();
+            groups.entry(group).or_default().push((name, color));
+        }
+        groups
+            .into_iter()
+            .map(|(name, colors)| ColorGroup { name, colors })
+            .collect()
+    }
+
+    /// Every colour with group and name joined by `separator`, e.g. `ui_border` for `_`.
+    pub fn flattened(&self, separator: &str) -> IndexMap<String, Color> {
+        self.colors
+            .iter()
+            .map(|(name, color)| {
+                let flat = name
+                    .split(GROUP_SEPARATOR)
+                    .collect::<Vec<_>>()
+                    .join(separator);
+                (flat, *color)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod group_tests {
+    use super::*;
+    use crate::palette::ColorRole;
+    use crate::testing;
+
+    fn palette() -> Palette {
+        testing::palette(
+            "Grouped",
+            &[
+                ("background", "#000"),
+                ("ansi.red", "#C00"),
+                ("ui.border", "#333"),
+                ("ansi.bright-red", "#F00"),
+                ("syntax.keyword.control", "#C0C"),
+            ],
+        )
+    }
+
+    #[test]
+    fn groups() {
+        let palette = palette();
+        let groups = palette.groups();
+        assert_eq!(
+            groups.iter().map(|group| group.name).collect::<Vec<_>>(),
+            vec!["", "ansi", "ui", "syntax.keyword"]
+        );
+        assert_eq!(groups[0].name, "");
+        assert_eq!(
+            groups[1]
+                .colors
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>(),
+            vec!["ansi.red", "ansi.bright-red"]
+        );
+        assert_eq!(groups[1].colors[1].1, &Color::from_hex("#F00"));
+        assert_eq!(groups[0].colors.len(), 1);
+        assert_eq!(palette.get_color("ui.border").unwrap().hex(), "#333333");
+        assert_eq!(palette.role_name(ColorRole::Ansi(1)), Some("ansi.red"));
+        assert_eq!(
+            palette.role_name(ColorRole::Ansi(9)),
+            Some("ansi.bright-red")
+        );
+        assert_eq!(
+            split_group("syntax.keyword.control"),
+            (Some("syntax.keyword"), "control")
+        );
+    }
+
+    #[test]
+    fn flattened() {
+        let palette = palette();
+        assert_eq!(
+            palette.flattened("_").keys().collect::<Vec<_>>(),
+            vec![
+                "background",
+                "ansi_red",
+                "ui_border",
+                "ansi_bright-red",
+                "syntax_keyword_control"
+            ]
+        );
+    }
+}
@@ -0,0 +1,700 @@
+use std::{collections::VecDeque, error::Error, fmt};
+
+use indexmap::IndexMap;
+
+use super::{ColorRole, ExpressionError, Metadata, Palette};
+use crate::color::Color;
+
+/// Undo steps kept by a new [`History`].
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// An edit to a palette, recorded by [`Palette::apply`] so it can be undone.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Edit {
+    /// Adds a colour at `index`, or at the end.
+    Add {
+        name: String,
+        color: Color,
+        index: Option<usize>,
+    },
+    /// Removes a colour along with its expression and roles.
+    Remove { name: String },
+    /// Renames a colour, see [`Palette::rename_color`].
+    Rename { from: String, to: String },
+    /// Sets a colour, replacing any expression it was defined by.
+    Recolor { name: String, color: Color },
+    /// Defines a colour, new or existing, by an expression.
+    Define { name: String, expression: String },
+    /// Moves a colour to `index`.
+    Move { name: String, index: usize },
+    /// Replaces the palette's metadata.
+    Metadata(Box<Metadata>),
+}
+
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Edit::Add { name, .. } => write!(f, "add `{}`", name),
+            Edit::Remove { name } => write!(f, "remove `{}`", name),
+            Edit::Rename { from, to } => write!(f, "rename `{}` to `{}`", from, to),
+            Edit::Recolor { name, .. } => write!(f, "recolor `{}`", name),
+            Edit::Define { name, .. } => write!(f, "define `{}`", name),
+            Edit::Move { name, index } => write!(f, "move `{}` to {}", name, index),
+            Edit::Metadata(_) => write!(f, "edit metadata"),
+        }
+    }
+}
+
+/// Why [`Palette::apply`] could not make an edit. The palette is left unchanged.
+#[derive(Debug, PartialEq, Clone)]
+pub enum EditError {
+    UnknownColor(String),
+    NameTaken(String),
+    IndexOutOfBounds { name: String, index: usize },
+    Expression(ExpressionError),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::UnknownColor(name) => write!(f, "There is no colour `{}`", name),
+            EditError::NameTaken(name) => write!(f, "There already is a colour `{}`", name),
+            EditError::IndexOutOfBounds { name, index } => {
+                write!(f, "Cannot move `{}` to {}: out of bounds", name, index)
+            }
+            EditError::Expression(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for EditError {}
+
+impl From<ExpressionError> for EditError {
+    fn from(error: ExpressionError) -> Self {
+        EditError::Expression(error)
+    }
+}
+
+/// What it takes to put the palette back as it was before an edit.
+#[derive(Debug, PartialEq, Clone)]
+enum Inverse {
+    Remove(String),
+    Restore {
+        name: String,
+        index: usize,
+        color: Color,
+        expression: Option<String>,
+        roles: Vec<ColorRole>,
+        /// Expressions of the entries that referred to the colour, kept as plain colours
+        /// while it was removed.
+        dependents: Vec<(String, String)>,
+    },
+    Rename {
+        from: String,
+        to: String,
+    },
+    Recolor {
+        name: String,
+        color: Color,
+        expression: Option<String>,
+        /// Values of the entries derived from the colour before it changed.
+        derived: Vec<(String, Color)>,
+    },
+    Move {
+        name: String,
+        index: usize,
+    },
+    Metadata(Box<Metadata>),
+}
+
+/// An edit together with its inverse.
+#[derive(Debug, PartialEq, Clone)]
+struct Step {
+    edit: Edit,
+    inverse: Inverse,
+}
+
+/// Undo and redo stacks of a palette. Each entry is a group of edits undone together.
+#[derive(Debug, Clone)]
+pub struct History {
+    undo: VecDeque<Vec<Step>>,
+    redo: Vec<Vec<Step>>,
+    /// Edits made since [`Palette::begin_edit_group`], not undoable until the group ends.
+    group: Option<Vec<Step>>,
+    limit: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            group: None,
+            limit: DEFAULT_HISTORY_LIMIT,
+        }
+    }
+}
+
+impl History {
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || self.group.as_ref().is_some_and(|group| !group.is_empty())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// The edits the next undo would revert, most recent last.
+    pub fn undo_edits(&self) -> Vec<&Edit> {
+        self.undo
+            .back()
+            .map(|steps| steps.iter().map(|step| &step.edit).collect())
+            .unwrap_or_default()
+    }
+
+    /// The edits the next redo would make again.
+    pub fn redo_edits(&self) -> Vec<&Edit> {
+        self.redo
+            .last()
+            .map(|steps| steps.iter().map(|step| &step.edit).collect())
+            .unwrap_or_default()
+    }
+
+    fn record(&mut self, step: Step) {
+        self.redo.clear();
+        match &mut self.group {
+            Some(group) => match group.last_mut() {
+                // Dragging a slider recolors the same entry over and over; only the
+                // first value before and the last value after are worth keeping.
+                Some(last) if is_same_recolor(&last.edit, &step.edit) => last.edit = step.edit,
+                _ => group.push(step),
+            },
+            None => {
+                self.undo.push_back(vec![step]);
+                self.trim();
+            }
+        }
+    }
+
+    fn end_group(&mut self) {
+        if let Some(group) = self.group.take() {
+            if !group.is_empty() {
+                self.undo.push_back(group);
+                self.trim();
+            }
+        }
+    }
+
+    fn trim(&mut self) {
+        while self.undo.len() > self.limit {
+            self.undo.pop_front();
+        }
+    }
+}
+
+fn is_same_recolor(previous: &Edit, next: &Edit) -> bool {
+    matches!(
+        (previous, next),
+        (Edit::Recolor { name: a, .. }, Edit::Recolor { name: b, .. }) if a == b
+    )
+}
+
+impl Palette {
+    /// Reads an edit to this palette as typed in the viewer: `add NAME=COLOR`,
+    /// `remove NAME`, `rename NAME=NEW`, `recolor NAME=COLOR`, `define NAME=EXPRESSION`,
+    /// `move NAME=INDEX` with the index counted from 0, or `tag TAG` and `untag TAG` for
+    /// the palette's tags.
+    pub fn parse_edit(&self, text: &str) -> Result<Edit, String> {
+        let text = text.trim();
+        let (command, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let (command, rest) = (command.to_lowercase(), rest.trim());
+        let pair = || match rest.rsplit_once('=') {
+            Some((name, value)) if !name.trim().is_empty() && !value.trim().is_empty() => {
+                Ok((name.trim().to_string(), value.trim()))
+            }
+            _ => Err(format!("{} needs NAME=VALUE", command)),
+        };
+        let color = |value: &str| {
+            Color::try_from_hex(value).ok_or_else(|| format!("`{}` is not a hex colour", value))
+        };
+        match command.as_str() {
+            "add" => {
+                let (name, value) = pair()?;
+                Ok(Edit::Add {
+                    name,
+                    color: color(value)?,
+                    index: None,
+                })
+            }
+            "recolor" => {
+                let (name, value) = pair()?;
+                Ok(Edit::Recolor {
+                    name,
+                    color: color(value)?,
+                })
+            }
+            "define" => {
+                let (name, expression) = pair()?;
+                Ok(Edit::Define {
+                    name,
+                    expression: expression.to_string(),
+                })
+            }
+            "rename" => {
+                let (from, to) = pair()?;
+                Ok(Edit::Rename {
+                    from,
+                    to: to.to_string(),
+                })
+            }
+            "move" => {
+                let (name, value) = pair()?;
+                let index = value
+                    .parse()
+                    .map_err(|_| format!("`{}` is not an index", value))?;
+                Ok(Edit::Move { name, index })
+            }
+            "remove" | "tag" | "untag" if rest.is_empty() => {
+                Err(format!("{} needs a name", command))
+            }
+            "remove" => Ok(Edit::Remove {
+                name: rest.to_string(),
+            }),
+            "tag" | "untag" => {
+                let mut metadata = self.metadata.clone();
+                if command == "untag" {
+                    metadata.tags.retain(|tag| !tag.eq_ignore_ascii_case(rest));
+                } else if !metadata.has_tag(rest) {
+                    metadata.tags.push(rest.to_string());
+                }
+                Ok(Edit::Metadata(Box::new(metadata)))
+            }
+            "" => Err("Type an edit, e.g. recolor red=#F38BA8".to_string()),
+            other => Err(format!("Unknown edit: {}", other)),
+        }
+    }
+
+    /// Makes `edit` and records it in the palette's history, clearing what could be redone.
+    pub fn apply(&mut self, edit: Edit) -> Result<(), EditError> {
+        let inverse = self.perform(&edit)?;
+        self.history.record(Step { edit, inverse });
+        Ok(())
+    }
+
+    /// Starts grouping edits, so continuous changes such as dragging a slider are undone
+    /// as one step. Ends any group already started.
+    pub fn begin_edit_group(&mut self) {
+        self.history.end_group();
+        self.history.group = Some(Vec::new());
+    }
+
+    /// Ends the group started by [`Palette::begin_edit_group`].
+    pub fn end_edit_group(&mut self) {
+        self.history.end_group();
+    }
+
+    /// Reverts the last edit or group of edits. Returns `false` if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        self.history.end_group();
+        let steps = match self.history.undo.pop_back() {
+            Some(steps) => steps,
+            None => return false,
+        };
+        for step in steps.iter().rev() {
+            self.revert(step.inverse.clone());
+        }
+        self.history.redo.push(steps);
+        true
+    }
+
+    /// Makes the last undone edit or group of edits again. Returns `false` if there was
+    /// nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        self.history.end_group();
+        let steps = match self.history.redo.pop() {
+            Some(steps) => steps,
+            None => return false,
+        };
+        let mut redone = Vec::with_capacity(steps.len());
+        for step in steps {
+            // An edit that was undone applies cleanly to the palette it was undone to.
+            if let Ok(inverse) = self.perform(&step.edit) {
+                redone.push(Step {
+                    edit: step.edit,
+                    inverse,
+                });
+            }
+        }
+        self.history.undo.push_back(redone);
+        self.history.trim();
+        true
+    }
+
+    fn perform(&mut self, edit: &Edit) -> Result<Inverse, EditError> {
+        let known = |palette: &Palette, name: &str| match palette.colors.get_index_of(name) {
+            Some(index) => Ok(index),
+            None => Err(EditError::UnknownColor(name.to_string())),
+        };
+        match edit {
+            Edit::Add { name, color, index } => {
+                if self.colors.contains_key(name) {
+                    return Err(EditError::NameTaken(name.clone()));
+                }
+                // No expression can refer to a colour the palette does not have yet, so
+                // nothing needs resolving.
+                let index = index.unwrap_or(self.colors.len());
+                self.insert_at(index, name.clone(), *color);
+                Ok(Inverse::Remove(name.clone()))
+            }
+            Edit::Remove { name } => {
+                let index = known(self, name)?;
+                let roles = self
+                    .roles
+                    .iter()
+                    .filter(|(_, assigned)| *assigned == name)
+                    .map(|(role, _)| *role)
+                    .collect();
+                let expression = self.expression(name).map(str::to_string);
+                let dependents = self
+                    .dependents(name)
+                    .into_iter()
+                    .map(|dependent| {
+                        let expression = self.expressions[&dependent].clone();
+                        (dependent, expression)
+                    })
+                    .collect();
+                let (name, color) = self.remove_color(name).expect("checked to exist");
+                Ok(Inverse::Restore {
+                    name,
+                    index,
+                    color,
+                    expression,
+                    roles,
+                    dependents,
+                })
+            }
+            Edit::Rename { from, to } => {
+                known(self, from)?;
+                if !self.rename_color(from, to) {
+                    return Err(EditError::NameTaken(to.clone()));
+                }
+                Ok(Inverse::Rename {
+                    from: to.clone(),
+                    to: from.clone(),
+                })
+            }
+            Edit::Recolor { name, color } => {
+                let index = known(self, name)?;
+                let before = self.colors.clone();
+                let expression = self.expression(name).map(str::to_string);
+                self.add_color(name.clone(), *color)?;
+                Ok(Inverse::Recolor {
+                    name: name.clone(),
+                    color: before[index],
+                    expression,
+                    derived: self.derived_changes(&before, name),
+                })
+            }
+            Edit::Define { name, expression } => {
+                let before = self.colors.clone();
+                let previous = self.expression(name).map(str::to_string);
+                self.set_expression(name, expression)?;
+                match before.get(name) {
+                    Some(color) => Ok(Inverse::Recolor {
+                        name: name.clone(),
+                        color: *color,
+                        expression: previous,
+                        derived: self.derived_changes(&before, name),
+                    }),
+                    None => Ok(Inverse::Remove(name.clone())),
+                }
+            }
+            Edit::Move { name, index } => {
+                let current = known(self, name)?;
+                if !self.move_color(name, *index) {
+                    return Err(EditError::IndexOutOfBounds {
+                        name: name.clone(),
+                        index: *index,
+                    });
+                }
+                Ok(Inverse::Move {
+                    name: name.clone(),
+                    index: current,
+                })
+            }
+            Edit::Metadata(metadata) => {
+                let previous = std::mem::replace(&mut self.metadata, (**metadata).clone());
+                Ok(Inverse::Metadata(Box::new(previous)))
+            }
+        }
+    }
+
+    /// Values before an edit of the entries, other than `name`, that it changed.
+    fn derived_changes(
+        &self,
+        before: &IndexMap<String, Color>,
+        name: &str,
+    ) -> Vec<(String, Color)> {
+        before
+            .iter()
+            .filter(|(entry, value)| *entry != name && self.colors[*entry] != **value)
+            .map(|(entry, value)| (entry.clone(), *value))
+            .collect()
+    }
+
+    fn revert(&mut self, inverse: Inverse) {
+        match inverse {
+            Inverse::Remove(name) => {
+                self.remove_color(&name);
+            }
+            Inverse::Restore {
+                name,
+                index,
+                color,
+                expression,
+                roles,
+                dependents,
+            } => {
+                self.colors.shift_insert(index, name.clone(), color);
+                if let Some(expression) = expression {
+                    self.expressions.insert(name.clone(), expression);
+                }
+                for role in roles {
+                    self.roles.insert(role, name.clone());
+                }
+                // The dependents kept their values, so restoring their expressions leaves
+                // every colour as it was before the removal.
+                self.expressions.extend(dependents);
+            }
+            Inverse::Rename { from, to } => {
+                self.rename_color(&from, &to);
+            }
+            Inverse::Recolor {
+                name,
+                color,
+                expression,
+                derived,
+            } => {
+                self.colors.insert(name.clone(), color);
+                match expression {
+                    Some(expression) => self.expressions.insert(name, expression),
+                    None => self.expressions.shift_remove(&name),
+                };
+                self.colors.extend(derived);
+            }
+            Inverse::Move { name, index } => {
+                self.move_color(&name, index);
+            }
+            Inverse::Metadata(metadata) => self.metadata = *metadata,
+        }
+    }
+}
+
+#[cfg(test)]
+mod history_tests {
+    use super::*;
+    use crate::testing;
+
+    fn palette() -> Palette {
+        let mut palette = testing::palette(
+            "History",
+            &[("bg", "#000"), ("fg", "#FFF"), ("red", "#C00")],
+        );
+        palette
+            .set_expression("bright-red", "lighten($red, 20%)")
+            .unwrap();
+        palette.assign_role(ColorRole::Background, "bg");
+        palette
+    }
+
+    fn recolor(name: &str, hex: &str) -> Edit {
+        Edit::Recolor {
+            name: name.to_string(),
+            color: Color::from_hex(hex),
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_every_edit() {
+        let original = palette();
+        let metadata = Metadata {
+            author: Some("Ozy".to_string()),
+            ..Metadata::default()
+        };
+        let edits = [
+            Edit::Add {
+                name: "blue".to_string(),
+                color: Color::from_hex("#00C"),
+                index: Some(1),
+            },
+            Edit::Remove {
+                name: "bg".to_string(),
+            },
+            // `bright-red` is derived from `red`.
+            Edit::Remove {
+                name: "red".to_string(),
+            },
+            recolor("red", "#00C"),
+            Edit::Rename {
+                from: "red".to_string(),
+                to: "crimson".to_string(),
+            },
+            recolor("bright-red", "#F00"),
+            Edit::Define {
+                name: "red".to_string(),
+                expression: "mix($bg, $fg, 50%)".to_string(),
+            },
+            Edit::Define {
+                name: "grey".to_string(),
+                expression: "mix($bg, $fg, 50%)".to_string(),
+            },
+            Edit::Move {
+                name: "fg".to_string(),
+                index: 3,
+            },
+            Edit::Metadata(Box::new(metadata)),
+        ];
+        for edit in edits {
+            let mut palette = original.clone();
+            palette.apply(edit.clone()).unwrap();
+            let edited = palette.clone();
+            assert!(palette.undo(), "{}", edit);
+            assert_eq!(palette.colors, original.colors, "{}", edit);
+            assert_eq!(palette.expressions, original.expressions, "{}", edit);
+            assert_eq!(palette.roles, original.roles, "{}", edit);
+            assert_eq!(palette.metadata, original.metadata, "{}", edit);
+            assert!(palette.redo(), "{}", edit);
+            assert_eq!(palette.colors, edited.colors, "{}", edit);
+            assert_eq!(palette.expressions, edited.expressions, "{}", edit);
+            assert_eq!(palette.roles, edited.roles, "{}", edit);
+        }
+    }
+
+    #[test]
+    fn parse_edits() {
+        let palette = palette();
+        assert_eq!(
+            palette.parse_edit(" recolor red = #F00"),
+            Ok(recolor("red", "#F00"))
+        );
+        assert_eq!(
+            palette.parse_edit("add Test Blue=#00C"),
+            Ok(Edit::Add {
+                name: "Test Blue".to_string(),
+                color: Color::from_hex("#00C"),
+                index: None,
+            })
+        );
+        assert_eq!(
+            palette.parse_edit("Move fg=0"),
+            Ok(Edit::Move {
+                name: "fg".to_string(),
+                index: 0,
+            })
+        );
+        let tagged = palette.parse_edit("tag dark").unwrap();
+        assert_eq!(tagged.to_string(), "edit metadata");
+        let mut tagged_palette = palette.clone();
+        tagged_palette.apply(tagged).unwrap();
+        assert_eq!(tagged_palette.metadata.tags, vec!["dark"]);
+        match tagged_palette.parse_edit("untag DARK") {
+            Ok(Edit::Metadata(metadata)) => assert!(metadata.tags.is_empty()),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(
+            palette.parse_edit("define border=mix($bg, $fg, 20%)"),
+            Ok(Edit::Define {
+                name: "border".to_string(),
+                expression: "mix($bg, $fg, 20%)".to_string(),
+            })
+        );
+        assert!(palette.parse_edit("recolor red").is_err());
+        assert!(palette.parse_edit("recolor red=crimson").is_err());
+        assert!(palette.parse_edit("move fg=-1").is_err());
+        assert!(palette.parse_edit("remove").is_err());
+        assert!(palette.parse_edit("paint red").is_err());
+    }
+
+    #[test]
+    fn rename_updates_references() {
+        let original = palette();
+        let mut palette = original.clone();
+        palette
+            .apply(Edit::Rename {
+                from: "red".to_string(),
+                to: "crimson".to_string(),
+            })
+            .unwrap();
+        assert_eq!(
+            palette.expression("bright-red"),
+            Some("lighten($crimson, 20%)")
+        );
+        palette.apply(recolor("crimson", "#800")).unwrap();
+        assert_ne!(
+            palette.get_color("bright-red"),
+            original.get_color("bright-red")
+        );
+    }
+
+    #[test]
+    fn failed_edits_are_not_recorded() {
+        let mut palette = palette();
+        assert_eq!(
+            palette.apply(recolor("missing", "#000")),
+            Err(EditError::UnknownColor("missing".to_string()))
+        );
+        assert_eq!(
+            palette.apply(Edit::Rename {
+                from: "red".to_string(),
+                to: "fg".to_string(),
+            }),
+            Err(EditError::NameTaken("fg".to_string()))
+        );
+        assert!(palette
+            .apply(Edit::Move {
+                name: "fg".to_string(),
+                index: 9,
+            })
+            .is_err());
+        assert!(!palette.history.can_undo());
+        assert!(!palette.undo());
+    }
+
+    #[test]
+    fn groups_continuous_edits() {
+        let mut palette = palette();
+        palette.begin_edit_group();
+        for hex in ["#100", "#200", "#300"] {
+            palette.apply(recolor("bg", hex)).unwrap();
+        }
+        palette.apply(recolor("fg", "#EEE")).unwrap();
+        palette.end_edit_group();
+        assert_eq!(
+            palette.history.undo_edits(),
+            vec![&recolor("bg", "#300"), &recolor("fg", "#EEE")]
+        );
+
+        assert!(palette.undo());
+        assert_eq!(palette.get_color("bg").unwrap().hex(), "#000000");
+        assert_eq!(palette.get_color("fg").unwrap().hex(), "#FFFFFF");
+        assert!(!palette.history.can_undo());
+        assert!(palette.redo());
+        assert_eq!(palette.get_color("bg").unwrap().hex(), "#330000");
+
+        palette.apply(recolor("bg", "#000")).unwrap();
+        assert!(!palette.history.can_redo());
+    }
+
+    #[test]
+    fn bounded() {
+        let mut palette = palette();
+        palette.history.limit = 2;
+        for hex in ["#100", "#200", "#300"] {
+            palette.apply(recolor("bg", hex)).unwrap();
+        }
+        assert!(palette.undo());
+        assert!(palette.undo());
+        assert!(!palette.undo());
+        assert_eq!(palette.get_color("bg").unwrap().hex(), "#110000");
+    }
+}
@@ -0,0 +1,30 @@
+use indexmap::IndexMap;
+
+use super::ColorRole;
+
+/// Name of the key naming the palette, or palette file, a palette builds on.
+pub const EXTENDS_KEY: &str = "extends";
+
+/// What a palette took from the palette it extends.
+///
+/// Kept so that saving writes back only the entries that override the base.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Inheritance {
+    /// The `extends` value: a palette name or a path to a palette file.
+    pub base: String,
+    /// Inherited entries and their source, a hex colour or an expression.
+    pub colors: IndexMap<String, String>,
+    pub roles: IndexMap<ColorRole, String>,
+}
+
+impl Inheritance {
+    /// Whether `source` for the entry `name` is exactly what the base provides.
+    pub fn is_inherited(&self, name: &str, source: &str) -> bool {
+        self.colors.get(name).is_some_and(|base| base == source)
+    }
+
+    /// Whether the role assignment is exactly what the base provides.
+    pub fn is_inherited_role(&self, role: &ColorRole, name: &str) -> bool {
+        self.roles.get(role).is_some_and(|base| base == name)
+    }
+}
@@ -0,0 +1,325 @@
+use std::path::Path;
+
+use uuid::Uuid;
+
+use super::{find_by_id, Palette};
+use crate::config::state::State;
+
+/// How many recently shown palettes [`PaletteLibrary`] remembers.
+pub const RECENT_LIMIT: usize = 10;
+
+/// A change to a [`PaletteLibrary`], passed to its listeners after it is made.
+#[derive(Debug, PartialEq, Clone)]
+pub enum LibraryEvent {
+    Added(Uuid),
+    Updated(Uuid),
+    /// Every palette was replaced, e.g. after the palette folder was read again.
+    Reloaded,
+    Favorites,
+    Selected(Uuid),
+}
+
+type Listener = Box<dyn FnMut(&PaletteLibrary, &LibraryEvent)>;
+
+/// Every loaded palette, with the favourites and recently shown palettes remembered
+/// between launches.
+///
+/// Palettes read from the palette folder have unique names, but a palette added from
+/// elsewhere may share one; such palettes are told apart by [`PaletteLibrary::label`].
+#[derive(Default)]
+pub struct PaletteLibrary {
+    palettes: Vec<Palette>,
+    favorites: Vec<Uuid>,
+    recent: Vec<Uuid>,
+    listeners: Vec<Listener>,
+}
+
+impl PaletteLibrary {
+    pub fn new(palettes: Vec<Palette>) -> Self {
+        Self {
+            palettes,
+            ..Self::default()
+        }
+    }
+
+    /// The library with the favourites and recents in `state`, forgetting ids of
+    /// palettes that are not loaded.
+    pub fn with_state(mut self, state: &State) -> Self {
+        self.favorites = state.favorites.clone();
+        self.recent = state.recent.clone();
+        if self.recent.is_empty() {
+            self.recent.extend(state.last_palette);
+        }
+        self.forget_unloaded();
+        self
+    }
+
+    fn forget_unloaded(&mut self) {
+        let palettes = &self.palettes;
+        let loaded = |id: &Uuid| palettes.iter().any(|palette| palette.uuid == *id);
+        self.favorites.retain(loaded);
+        self.recent.retain(loaded);
+        self.recent.truncate(RECENT_LIMIT);
+    }
+
+    /// The favourites and recents to persist.
+    pub fn state(&self) -> State {
+        State {
+            last_palette: self.recent.first().copied(),
+            favorites: self.favorites.clone(),
+            recent: self.recent.clone(),
+        }
+    }
+
+    /// Calls `listener` after every change to the library.
+    pub fn subscribe(&mut self, listener: impl FnMut(&PaletteLibrary, &LibraryEvent) + 'static) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    fn notify(&mut self, event: LibraryEvent) {
+        let mut listeners = std::mem::take(&mut self.listeners);
+        for listener in &mut listeners {
+            listener(self, &event);
+        }
+        listeners.append(&mut self.listeners);
+        self.listeners = listeners;
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<&Palette> {
+        find_by_id(&self.palettes, id)
+    }
+
+    /// The palette shown as `name` by [`PaletteLibrary::label`], or else the first
+    /// palette with that name, ignoring case.
+    pub fn get_by_name(&self, name: &str) -> Option<&Palette> {
+        self.palettes
+            .iter()
+            .find(|palette| self.label(palette) == name)
+            .or_else(|| {
+                self.palettes
+                    .iter()
+                    .find(|palette| palette.name.eq_ignore_ascii_case(name))
+            })
+    }
+
+    /// The palette's name, followed by its file name when another palette has the
+    /// same name, e.g. `Mocha (mocha-dark.toml)`.
+    pub fn label(&self, palette: &Palette) -> String {
+        let shared = self
+            .palettes
+            .iter()
+            .any(|other| other.uuid != palette.uuid && other.name == palette.name);
+        let file_name = palette
+            .filename
+            .as_deref()
+            .and_then(Path::file_name)
+            .map(|file_name| file_name.to_string_lossy());
+        match (shared, file_name) {
+            (true, Some(file_name)) => format!("{} ({})", palette.name, file_name),
+            (true, None) => format!("{} ({})", palette.name, palette.uuid.simple()),
+            _ => palette.name.clone(),
+        }
+    }
+
+    /// Labels of the palettes to offer for selection, only those tagged `tag` if given:
+    /// favourites first, in the order they were marked, then the others in the order
+    /// they were loaded.
+    pub fn labels(&self, tag: Option<&str>) -> Vec<String> {
+        self.ordered()
+            .filter(|palette| tag.is_none_or(|tag| palette.metadata.has_tag(tag)))
+            .map(|palette| self.label(palette))
+            .collect()
+    }
+
+    fn ordered(&self) -> impl Iterator<Item = &Palette> {
+        let favorites = self.favorites.iter().filter_map(|id| self.get(id));
+        let others = self
+            .palettes
+            .iter()
+            .filter(|palette| !self.is_favorite(&palette.uuid));
+        favorites.chain(others)
+    }
+
+    /// Every tag used by a palette, sorted and without duplicates.
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags = Vec::<String>::new();
+        for tag in self
+            .palettes
+            .iter()
+            .flat_map(|palette| &palette.metadata.tags)
+        {
+            if !tags.iter().any(|known| known.eq_ignore_ascii_case(tag)) {
+                tags.push(tag.clone());
+            }
+        }
+        tags.sort_by_key(|tag| tag.to_lowercase());
+        tags
+    }
+
+    /// Adds a palette, or replaces the loaded palette with the same id.
+    pub fn insert(&mut self, palette: Palette) {
+        let id = palette.uuid;
+        match self.palettes.iter_mut().find(|loaded| loaded.uuid == id) {
+            Some(loaded) => {
+                *loaded = palette;
+                self.notify(LibraryEvent::Updated(id));
+            }
+            None => {
+                self.palettes.push(palette);
+                self.notify(LibraryEvent::Added(id));
+            }
+        }
+    }
+
+    /// Replaces every palette, keeping the favourites and recents still loaded.
+    pub fn reload(&mut self, palettes: Vec<Palette>) {
+        self.palettes = palettes;
+        self.forget_unloaded();
+        self.notify(LibraryEvent::Reloaded);
+    }
+
+    pub fn is_favorite(&self, id: &Uuid) -> bool {
+        self.favorites.contains(id)
+    }
+
+    /// Marks or unmarks a loaded palette as a favourite.
+    pub fn set_favorite(&mut self, id: &Uuid, favorite: bool) {
+        if self.get(id).is_none() || self.is_favorite(id) == favorite {
+            return;
+        }
+        match favorite {
+            true => self.favorites.push(*id),
+            false => self.favorites.retain(|favorite| favorite != id),
+        }
+        self.notify(LibraryEvent::Favorites);
+    }
+
+    /// The palette shown last, or else the first one loaded.
+    pub fn selected(&self) -> Option<&Palette> {
+        self.recent
+            .first()
+            .and_then(|id| self.get(id))
+            .or_else(|| self.palettes.first())
+    }
+
+    /// Shows a loaded palette, making it the most recent.
+    pub fn select(&mut self, id: &Uuid) {
+        if self.get(id).is_none() {
+            return;
+        }
+        self.recent.retain(|recent| recent != id);
+        self.recent.insert(0, *id);
+        self.recent.truncate(RECENT_LIMIT);
+        self.notify(LibraryEvent::Selected(*id));
+    }
+}
+
+#[cfg(test)]
+mod library_tests {
+    use super::*;
+    use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+    fn palette(name: &str, file: &str, tags: &[&str]) -> Palette {
+        let mut palette = Palette::new(name, Some(PathBuf::from(file)));
+        palette.metadata.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        palette
+    }
+
+    fn library() -> PaletteLibrary {
+        PaletteLibrary::new(vec![
+            palette("Mocha", "/palettes/mocha.toml", &["dark", "Pastel"]),
+            palette("Latte", "/palettes/latte.toml", &["light", "pastel"]),
+            palette("Nord", "/palettes/nord.toml", &["dark"]),
+        ])
+    }
+
+    #[test]
+    fn lookup() {
+        let mut library = library();
+        let nord = library.get_by_name("nord").unwrap().uuid;
+        assert_eq!(library.get(&nord).unwrap().name, "Nord");
+        assert_eq!(library.tags(), vec!["dark", "light", "Pastel"]);
+        assert_eq!(library.labels(Some("pastel")), vec!["Mocha", "Latte"]);
+        assert_eq!(library.labels(Some("DARK")), vec!["Mocha", "Nord"]);
+
+        library.insert(palette("Mocha", "/elsewhere/mocha-dark.toml", &[]));
+        assert_eq!(
+            library.labels(None),
+            vec![
+                "Mocha (mocha.toml)",
+                "Latte",
+                "Nord",
+                "Mocha (mocha-dark.toml)"
+            ]
+        );
+        let label = "Mocha (mocha-dark.toml)";
+        assert_eq!(library.get_by_name(label).unwrap().name, "Mocha");
+        assert_eq!(
+            library.get_by_name(label).unwrap().filename,
+            Some(PathBuf::from("/elsewhere/mocha-dark.toml"))
+        );
+    }
+
+    #[test]
+    fn favorites_and_recents() {
+        let mut library = library();
+        let [mocha, latte, nord] = [0, 1, 2].map(|index| library.palettes[index].uuid);
+        assert_eq!(library.selected().unwrap().uuid, mocha);
+
+        library.set_favorite(&nord, true);
+        assert_eq!(library.labels(None), vec!["Nord", "Mocha", "Latte"]);
+        library.select(&latte);
+        library.select(&nord);
+        library.select(&latte);
+        assert_eq!(library.selected().unwrap().uuid, latte);
+        assert_eq!(library.recent, vec![latte, nord]);
+
+        let state = library.state();
+        assert_eq!(state.last_palette, Some(latte));
+        let restored = self::library();
+        let restored = PaletteLibrary::new(restored.palettes).with_state(&state);
+        assert_eq!(restored.labels(None), library.labels(None));
+        assert_eq!(restored.selected().unwrap().uuid, latte);
+
+        let gone = Uuid::new_v4();
+        let state = State {
+            last_palette: Some(gone),
+            favorites: vec![gone, nord],
+            recent: Vec::new(),
+        };
+        let forgetful = self::library().with_state(&state);
+        assert_eq!(forgetful.favorites, vec![nord]);
+        assert_eq!(forgetful.selected().unwrap().name, "Mocha");
+    }
+
+    #[test]
+    fn notifications() {
+        let mut library = library();
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let seen = events.clone();
+        library.subscribe(move |library, event| {
+            seen.borrow_mut()
+                .push((event.clone(), library.palettes.len()));
+        });
+        let latte = library.get_by_name("Latte").unwrap().clone();
+        library.select(&latte.uuid);
+        library.set_favorite(&latte.uuid, true);
+        library.set_favorite(&latte.uuid, true);
+        library.insert(latte.clone());
+        let extra = palette("Extra", "/palettes/extra.toml", &[]);
+        let extra_id = extra.uuid;
+        library.insert(extra);
+        library.reload(vec![latte.clone()]);
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                (LibraryEvent::Selected(latte.uuid), 3),
+                (LibraryEvent::Favorites, 3),
+                (LibraryEvent::Updated(latte.uuid), 3),
+                (LibraryEvent::Added(extra_id), 4),
+                (LibraryEvent::Reloaded, 1),
+            ]
+        );
+        assert!(library.is_favorite(&latte.uuid));
+    }
+}
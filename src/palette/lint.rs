@@ -0,0 +1,359 @@
+use std::fmt;
+
+use indexmap::IndexMap;
+
+use super::{group::leaf_name, role::normalize_name, ColorRole, Palette, RoleAliases};
+use crate::color::{Color, ContrastMethod};
+use crate::config::writer::color_to_string;
+use crate::DiagnosticType;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// The check a [`Diagnostic`] comes from.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LintKind {
+    DuplicateColor,
+    NearDuplicateColor,
+    LowContrast,
+    MissingAnsi,
+    BrightDarkerThanNormal,
+    Transparent,
+    RoleAliasCollision,
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintKind::DuplicateColor => write!(f, "duplicate-color"),
+            LintKind::NearDuplicateColor => write!(f, "near-duplicate-color"),
+            LintKind::LowContrast => write!(f, "low-contrast"),
+            LintKind::MissingAnsi => write!(f, "missing-ansi"),
+            LintKind::BrightDarkerThanNormal => write!(f, "bright-darker-than-normal"),
+            LintKind::Transparent => write!(f, "transparent"),
+            LintKind::RoleAliasCollision => write!(f, "role-alias-collision"),
+        }
+    }
+}
+
+/// One problem found by [`Palette::lint`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub kind: LintKind,
+    pub message: String,
+    /// Names of the colours involved.
+    pub colors: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn to_slint(&self) -> DiagnosticType {
+        DiagnosticType {
+            severity: self.severity.to_string().into(),
+            kind: self.kind.to_string().into(),
+            message: self.message.clone().into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.kind, self.message)
+    }
+}
+
+/// Thresholds used by [`Palette::lint_with`].
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct LintOptions {
+    /// Colours closer than this CIEDE2000 difference are reported as near duplicates.
+    pub near_duplicate_delta_e: f64,
+    pub contrast_method: ContrastMethod,
+    /// Minimum foreground on background contrast, in the units of `contrast_method`.
+    pub min_contrast: f64,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        LintOptions {
+            near_duplicate_delta_e: 2.0,
+            contrast_method: ContrastMethod::Wcag,
+            min_contrast: 4.5,
+        }
+    }
+}
+
+impl Palette {
+    /// Checks the palette with the default [`LintOptions`].
+    pub fn lint(&self) -> Vec<Diagnostic> {
+        self.lint_with(&LintOptions::default())
+    }
+
+    /// Checks the palette for likely mistakes, most severe first.
+    pub fn lint_with(&self, options: &LintOptions) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        self.lint_duplicates(options, &mut diagnostics);
+        self.lint_contrast(options, &mut diagnostics);
+        self.lint_ansi(&mut diagnostics);
+        self.lint_transparent(&mut diagnostics);
+        self.lint_role_aliases(RoleAliases::global(), &mut diagnostics);
+        diagnostics.sort_by_key(|diagnostic| std::cmp::Reverse(diagnostic.severity));
+        diagnostics
+    }
+
+    fn lint_duplicates(&self, options: &LintOptions, diagnostics: &mut Vec<Diagnostic>) {
+        let mut exact: IndexMap<Color, Vec<String>> = IndexMap::new();
+        for (name, color) in &self.colors {
+            exact.entry(*color).or_default().push(name.clone());
+        }
+        for (color, names) in exact.iter().filter(|(_, names)| names.len() > 1) {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Info,
+                kind: LintKind::DuplicateColor,
+                message: format!("{} are all {}", quote_names(names), color_to_string(color)),
+                colors: names.clone(),
+            });
+        }
+        let unique = exact.iter().collect::<Vec<_>>();
+        for (index, (first, first_names)) in unique.iter().enumerate() {
+            for (second, second_names) in &unique[index + 1..] {
+                let delta_e = first.delta_e(second);
+                if delta_e < options.near_duplicate_delta_e && first.opacity == second.opacity {
+                    diagnostics.push(Diagnostic {
+                        severity: Severity::Warning,
+                        kind: LintKind::NearDuplicateColor,
+                        message: format!(
+                            "`{}` ({}) and `{}` ({}) are barely distinguishable, ΔE {:.2}",
+                            first_names[0],
+                            first.hex(),
+                            second_names[0],
+                            second.hex(),
+                            delta_e
+                        ),
+                        colors: vec![first_names[0].clone(), second_names[0].clone()],
+                    });
+                }
+            }
+        }
+    }
+
+    fn lint_contrast(&self, options: &LintOptions, diagnostics: &mut Vec<Diagnostic>) {
+        let pairs = [
+            (ColorRole::Foreground, ColorRole::Background),
+            (
+                ColorRole::SelectionForeground,
+                ColorRole::SelectionBackground,
+            ),
+            (ColorRole::CursorText, ColorRole::Cursor),
+        ];
+        for (text_role, background_role) in pairs {
+            let (text, background) =
+                match (self.role_name(text_role), self.role_name(background_role)) {
+                    (Some(text), Some(background)) => (text, background),
+                    _ => continue,
+                };
+            let contrast = self.colors[text].to_opaque().contrast(
+                &self.colors[background].to_opaque(),
+                options.contrast_method,
+            );
+            if contrast < options.min_contrast {
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Error,
+                    kind: LintKind::LowContrast,
+                    message: format!(
+                        "{} `{}` on {} `{}` has contrast {:.2}, below {}",
+                        text_role,
+                        text,
+                        background_role,
+                        background,
+                        contrast,
+                        options.min_contrast
+                    ),
+                    colors: vec![text.to_string(), background.to_string()],
+                });
+            }
+        }
+    }
+
+    fn lint_ansi(&self, diagnostics: &mut Vec<Diagnostic>) {
+        let slots = (0..16)
+            .map(|index| self.role_name(ColorRole::Ansi(index)))
+            .collect::<Vec<_>>();
+        if slots.iter().all(Option::is_none) {
+            return;
+        }
+        let missing = (0..16)
+            .filter(|index| slots[*index as usize].is_none())
+            .map(|index| ColorRole::Ansi(index).to_string())
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Warning,
+                kind: LintKind::MissingAnsi,
+                message: format!("missing ANSI colours {}", missing.join(", ")),
+                colors: vec![],
+            });
+        }
+        for index in 0..8 {
+            let (normal, bright) = match (slots[index], slots[index + 8]) {
+                (Some(normal), Some(bright)) => (normal, bright),
+                _ => continue,
+            };
+            let normal_lightness = self.colors[normal].oklab().0;
+            let bright_lightness = self.colors[bright].oklab().0;
+            if bright_lightness + 1e-3 < normal_lightness {
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Warning,
+                    kind: LintKind::BrightDarkerThanNormal,
+                    message: format!(
+                        "bright `{}` is darker than normal `{}` (lightness {:.2} < {:.2})",
+                        bright, normal, bright_lightness, normal_lightness
+                    ),
+                    colors: vec![bright.to_string(), normal.to_string()],
+                });
+            }
+        }
+    }
+
+    fn lint_transparent(&self, diagnostics: &mut Vec<Diagnostic>) {
+        for (name, _) in self.colors.iter().filter(|(_, color)| color.opacity == 0) {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Warning,
+                kind: LintKind::Transparent,
+                message: format!("`{}` is fully transparent", name),
+                colors: vec![name.clone()],
+            });
+        }
+    }
+
+    fn lint_role_aliases(&self, aliases: &RoleAliases, diagnostics: &mut Vec<Diagnostic>) {
+        for role in ColorRole::all() {
+            let named = self
+                .colors
+                .keys()
+                .filter(|name| {
+                    let role_aliases = aliases.aliases(role);
+                    role_aliases.contains(&normalize_name(name))
+                        || role_aliases.contains(&normalize_name(leaf_name(name)))
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            let used = match self.role_name(role) {
+                Some(used) => used,
+                None => continue,
+            };
+            let unused = named
+                .iter()
+                .filter(|name| *name != used)
+                .cloned()
+                .collect::<Vec<_>>();
+            if unused.is_empty() {
+                continue;
+            }
+            diagnostics.push(Diagnostic {
+                severity: Severity::Info,
+                kind: LintKind::RoleAliasCollision,
+                message: format!(
+                    "{} {} named like {} but `{}` fills it",
+                    quote_names(&unused),
+                    if unused.len() == 1 { "is" } else { "are" },
+                    role,
+                    used
+                ),
+                colors: unused,
+            });
+        }
+    }
+}
+
+fn quote_names(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod lint_tests {
+    use super::*;
+    use crate::testing;
+
+    fn palette(entries: &[(&str, &str)]) -> Palette {
+        testing::palette("Test Palette", entries)
+    }
+
+    fn kinds(palette: &Palette) -> Vec<LintKind> {
+        palette.lint().iter().map(|d| d.kind).collect()
+    }
+
+    #[test]
+    fn clean_palette() {
+        let palette = palette(&[("bg", "#000"), ("fg", "#FFF"), ("accent", "#F80")]);
+        assert_eq!(palette.lint(), vec![]);
+    }
+
+    #[test]
+    fn duplicates() {
+        let palette = palette(&[("a", "#102030"), ("b", "#102030"), ("c", "#102031")]);
+        let diagnostics = palette.lint();
+        assert_eq!(
+            kinds(&palette),
+            vec![LintKind::NearDuplicateColor, LintKind::DuplicateColor]
+        );
+        assert_eq!(diagnostics[1].colors, vec!["a", "b"]);
+        assert_eq!(diagnostics[0].colors, vec!["a", "c"]);
+    }
+
+    #[test]
+    fn low_contrast() {
+        let palette = palette(&[("background", "#777"), ("foreground", "#FFF")]);
+        let diagnostics = palette.lint();
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[low-contrast]: foreground `foreground` on background `background` has contrast 4.48, below 4.5"
+        );
+    }
+
+    #[test]
+    fn ansi_checks() {
+        let mut palette = palette(&[("color0", "#000"), ("color1", "#C00"), ("color9", "#800")]);
+        palette
+            .add_color("color8".to_string(), Color::new(0x55, 0x55, 0x55, 0))
+            .unwrap();
+        assert_eq!(
+            kinds(&palette),
+            vec![
+                LintKind::MissingAnsi,
+                LintKind::BrightDarkerThanNormal,
+                LintKind::Transparent
+            ]
+        );
+        assert_eq!(palette.lint()[1].colors, vec!["color9", "color1"]);
+    }
+
+    #[test]
+    fn role_alias_collisions() {
+        let mut palette = palette(&[("BG", "#000"), ("background", "#111"), ("fg", "#FFF")]);
+        assert_eq!(
+            palette.lint()[0].message,
+            "`BG` is named like background but `background` fills it"
+        );
+        assert!(palette.assign_role(ColorRole::Background, "BG"));
+        assert_eq!(palette.lint()[0].colors, vec!["background"]);
+    }
+}
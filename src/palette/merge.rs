@@ -0,0 +1,326 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use indexmap::IndexMap;
+
+use super::{ExpressionError, Metadata, Palette};
+use crate::color::Color;
+use crate::config::writer::color_to_string;
+
+/// How [`Palette::merge`] settles a name both palettes give different colours.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum MergeStrategy {
+    /// Keep the colour already in the palette.
+    KeepLeft,
+    /// Take the colour from the palette being merged in.
+    #[default]
+    KeepRight,
+    /// Keep both, adding the incoming colour as `name-2`, `name-3`, ...
+    RenameConflicts,
+    /// Leave the palette unchanged and report the conflicts as an error.
+    Fail,
+}
+
+impl FromStr for MergeStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "keep-left" => Ok(MergeStrategy::KeepLeft),
+            "keep-right" => Ok(MergeStrategy::KeepRight),
+            "rename-conflicts" => Ok(MergeStrategy::RenameConflicts),
+            "fail" | "fail-with-report" => Ok(MergeStrategy::Fail),
+            other => Err(format!("Unknown merge strategy: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for MergeStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeStrategy::KeepLeft => write!(f, "keep-left"),
+            MergeStrategy::KeepRight => write!(f, "keep-right"),
+            MergeStrategy::RenameConflicts => write!(f, "rename-conflicts"),
+            MergeStrategy::Fail => write!(f, "fail"),
+        }
+    }
+}
+
+/// A name both palettes define with different colours.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MergeConflict {
+    pub name: String,
+    pub left: Color,
+    pub right: Color,
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` is {} and {}",
+            self.name,
+            color_to_string(&self.left),
+            color_to_string(&self.right)
+        )
+    }
+}
+
+/// What [`Palette::merge`] did.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MergeReport {
+    /// Name of the palette merged into.
+    pub left: String,
+    /// Name of the palette merged in.
+    pub right: String,
+    pub strategy: MergeStrategy,
+    /// Colours only the right palette had.
+    pub added: Vec<String>,
+    pub conflicts: Vec<MergeConflict>,
+    /// Conflicting colours added under a new name, as `(name, new name)`.
+    pub renamed: Vec<(String, String)>,
+}
+
+impl MergeReport {
+    pub fn has_conflicts(&self) -> bool {
+        !self.conflicts.is_empty()
+    }
+}
+
+impl fmt::Display for MergeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "merged '{}' into '{}' ({}): {} added, {} conflicting",
+            self.right,
+            self.left,
+            self.strategy,
+            self.added.len(),
+            self.conflicts.len()
+        )?;
+        for conflict in &self.conflicts {
+            write!(f, "\n  {}", conflict)?;
+            if let Some((_, renamed)) = self.renamed.iter().find(|(name, _)| *name == conflict.name)
+            {
+                write!(f, ", kept both as `{}`", renamed)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Why [`Palette::merge`] left the palette unchanged.
+#[derive(Debug, PartialEq, Clone)]
+pub enum MergeError {
+    /// The conflicts that stopped a merge with [`MergeStrategy::Fail`].
+    Conflicts(Box<MergeReport>),
+    /// The palette's expressions could not be resolved with the merged colours.
+    Expression(ExpressionError),
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeError::Conflicts(report) => write!(
+                f,
+                "Cannot merge '{}' into '{}', {} colours conflict: {}",
+                report.right,
+                report.left,
+                report.conflicts.len(),
+                report
+                    .conflicts
+                    .iter()
+                    .map(MergeConflict::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            MergeError::Expression(error) => write!(f, "Cannot merge: {}", error),
+        }
+    }
+}
+
+impl Error for MergeError {}
+
+impl Palette {
+    /// Merges `other` into this palette, settling conflicting colours by `strategy`.
+    ///
+    /// Colours taken from `other` keep the value they had there; their expressions are not
+    /// carried across. Roles and metadata fields missing here are filled in from `other`,
+    /// which wins for those too with [`MergeStrategy::KeepRight`]. Tags are combined.
+    pub fn merge(
+        &mut self,
+        other: &Palette,
+        strategy: MergeStrategy,
+    ) -> Result<MergeReport, MergeError> {
+        let mut report = MergeReport {
+            left: self.name.clone(),
+            right: other.name.clone(),
+            strategy,
+            added: Vec::new(),
+            conflicts: Vec::new(),
+            renamed: Vec::new(),
+        };
+        for (name, right) in &other.colors {
+            match self.colors.get(name) {
+                None => report.added.push(name.clone()),
+                Some(left) if left != right => report.conflicts.push(MergeConflict {
+                    name: name.clone(),
+                    left: *left,
+                    right: *right,
+                }),
+                Some(_) => {}
+            }
+        }
+        if strategy == MergeStrategy::Fail && report.has_conflicts() {
+            return Err(MergeError::Conflicts(Box::new(report)));
+        }
+
+        let mut merged = self.clone();
+        let mut renames = IndexMap::new();
+        for name in &report.added {
+            merged
+                .add_color(name.clone(), other.colors[name])
+                .map_err(MergeError::Expression)?;
+        }
+        for conflict in &report.conflicts {
+            let name = match strategy {
+                MergeStrategy::KeepRight => conflict.name.clone(),
+                MergeStrategy::RenameConflicts => {
+                    let renamed = merged.unused_name(&conflict.name);
+                    renames.insert(conflict.name.clone(), renamed.clone());
+                    report
+                        .renamed
+                        .push((conflict.name.clone(), renamed.clone()));
+                    renamed
+                }
+                MergeStrategy::KeepLeft | MergeStrategy::Fail => continue,
+            };
+            merged
+                .add_color(name, conflict.right)
+                .map_err(MergeError::Expression)?;
+        }
+        self.colors = merged.colors;
+        self.expressions = merged.expressions;
+
+        let prefer_right = strategy == MergeStrategy::KeepRight;
+        for (role, name) in &other.roles {
+            let name = renames.get(name).unwrap_or(name);
+            if prefer_right || !self.roles.contains_key(role) {
+                self.assign_role(*role, name);
+            }
+        }
+        merge_metadata(&mut self.metadata, &other.metadata, prefer_right);
+        Ok(report)
+    }
+
+    /// The first of `name-2`, `name-3`, ... the palette does not use yet.
+    fn unused_name(&self, name: &str) -> String {
+        (2..)
+            .map(|n| format!("{}-{}", name, n))
+            .find(|candidate| !self.colors.contains_key(candidate))
+            .unwrap()
+    }
+}
+
+fn merge_metadata(left: &mut Metadata, right: &Metadata, prefer_right: bool) {
+    fn pick<T: Clone>(left: &mut Option<T>, right: &Option<T>, prefer_right: bool) {
+        if right.is_some() && (prefer_right || left.is_none()) {
+            *left = right.clone();
+        }
+    }
+    pick(&mut left.author, &right.author, prefer_right);
+    pick(&mut left.description, &right.description, prefer_right);
+    pick(&mut left.license, &right.license, prefer_right);
+    pick(&mut left.variant, &right.variant, prefer_right);
+    pick(&mut left.source, &right.source, prefer_right);
+    pick(&mut left.wallpaper, &right.wallpaper, prefer_right);
+    pick(&mut left.modified, &right.modified, prefer_right);
+    pick(&mut left.created, &right.created, false);
+    for tag in &right.tags {
+        if !left.has_tag(tag) {
+            left.tags.push(tag.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod merge_tests {
+    use super::*;
+    use crate::palette::ColorRole;
+    use crate::testing;
+
+    fn palettes() -> (Palette, Palette) {
+        let mut left = testing::palette(
+            "Left",
+            &[("bg", "#000"), ("red", "#C00"), ("red-2", "#F00")],
+        );
+        left.assign_role(ColorRole::Accent, "red");
+        left.metadata.author = Some("Left Author".to_string());
+        left.metadata.tags = vec!["dark".to_string()];
+
+        let mut right = testing::palette(
+            "Right",
+            &[("bg", "#000"), ("red", "#D00"), ("blue", "#00C")],
+        );
+        right.assign_role(ColorRole::Accent, "red");
+        right.assign_role(ColorRole::Link, "blue");
+        right.metadata.author = Some("Right Author".to_string());
+        right.metadata.license = Some("MIT".to_string());
+        right.metadata.tags = vec!["Dark".to_string(), "warm".to_string()];
+        (left, right)
+    }
+
+    #[test]
+    fn keep_left_and_right() {
+        let (mut left, right) = palettes();
+        let report = left.merge(&right, MergeStrategy::KeepLeft).unwrap();
+        assert_eq!(report.added, vec!["blue"]);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(left.get_color("red").unwrap().hex(), "#CC0000");
+        assert_eq!(left.role_name(ColorRole::Link), Some("blue"));
+        assert_eq!(left.metadata.author.as_deref(), Some("Left Author"));
+        assert_eq!(left.metadata.license.as_deref(), Some("MIT"));
+        assert_eq!(left.metadata.tags, vec!["dark", "warm"]);
+
+        let (mut left, right) = palettes();
+        left.merge(&right, MergeStrategy::KeepRight).unwrap();
+        assert_eq!(left.color_names(), vec!["bg", "red", "red-2", "blue"]);
+        assert_eq!(left.get_color("red").unwrap().hex(), "#DD0000");
+        assert_eq!(left.metadata.author.as_deref(), Some("Right Author"));
+    }
+
+    #[test]
+    fn rename_conflicts() {
+        let (mut left, right) = palettes();
+        let report = left.merge(&right, MergeStrategy::RenameConflicts).unwrap();
+        assert_eq!(
+            report.renamed,
+            vec![("red".to_string(), "red-3".to_string())]
+        );
+        assert_eq!(left.get_color("red").unwrap().hex(), "#CC0000");
+        assert_eq!(left.get_color("red-3").unwrap().hex(), "#DD0000");
+        assert_eq!(left.role_name(ColorRole::Accent), Some("red"));
+        assert_eq!(
+            report.to_string(),
+            "merged 'Right' into 'Left' (rename-conflicts): 1 added, 1 conflicting\n  `red` is #CC0000 and #DD0000, kept both as `red-3`"
+        );
+    }
+
+    #[test]
+    fn fail_with_report() {
+        let (mut left, right) = palettes();
+        let error = left.merge(&right, MergeStrategy::Fail).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Cannot merge 'Right' into 'Left', 1 colours conflict: `red` is #CC0000 and #DD0000"
+        );
+        assert_eq!(left.color_names(), vec!["bg", "red", "red-2"]);
+        assert_eq!(left.metadata.license, None);
+
+        let mut same = right.clone();
+        assert!(same.merge(&right, MergeStrategy::Fail).is_ok());
+        assert_eq!(
+            "rename-conflicts".parse(),
+            Ok(MergeStrategy::RenameConflicts)
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    fmt,
+    path::PathBuf,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use toml::value::{Date, Datetime, Offset, Time};
+
+/// Name of the reserved table holding a palette's metadata, e.g. `["My Theme".meta]`.
+pub const METADATA_KEY: &str = "meta";
+
+/// Whether a palette is meant for a light or a dark background.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Variant {
+    Light,
+    Dark,
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "light" => Ok(Variant::Light),
+            "dark" => Ok(Variant::Dark),
+            other => Err(format!("Unknown palette variant: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Variant::Light => write!(f, "light"),
+            Variant::Dark => write!(f, "dark"),
+        }
+    }
+}
+
+/// Provenance and description of a palette, stored in its [`METADATA_KEY`] table.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Metadata {
+    pub author: Option<String>,
+    pub description: Option<String>,
+    pub license: Option<String>,
+    pub tags: Vec<String>,
+    pub variant: Option<Variant>,
+    /// Where the palette came from, usually a URL.
+    pub source: Option<String>,
+    /// Image the palette was generated from.
+    pub wallpaper: Option<PathBuf>,
+    pub created: Option<Datetime>,
+    pub modified: Option<Datetime>,
+}
+
+impl Metadata {
+    /// Sets `modified` to now, and `created` too if it has never been set.
+    pub fn touch(&mut self) {
+        let now = now_datetime();
+        if self.created.is_none() {
+            self.created = Some(now);
+        }
+        self.modified = Some(now);
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+}
+
+/// The current UTC time as a TOML datetime, to the second.
+pub fn now_datetime() -> Datetime {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    datetime_from_unix(seconds)
+}
+
+/// Converts seconds since the Unix epoch to a UTC TOML datetime.
+pub fn datetime_from_unix(seconds: u64) -> Datetime {
+    let days = (seconds / 86_400) as i64;
+    let seconds_of_day = seconds % 86_400;
+
+    // Civil date from days since epoch, http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = (year_of_era + era * 400 + i64::from(month <= 2)) as u16;
+
+    Datetime {
+        date: Some(Date { year, month, day }),
+        time: Some(Time {
+            hour: (seconds_of_day / 3600) as u8,
+            minute: (seconds_of_day % 3600 / 60) as u8,
+            second: (seconds_of_day % 60) as u8,
+            nanosecond: 0,
+        }),
+        offset: Some(Offset::Z),
+    }
+}
+
+#[cfg(test)]
+mod metadata_tests {
+    use super::*;
+
+    #[test]
+    fn variant() {
+        assert_eq!("Dark".parse::<Variant>(), Ok(Variant::Dark));
+        assert_eq!(" light ".parse::<Variant>(), Ok(Variant::Light));
+        assert!("dim".parse::<Variant>().is_err());
+        assert_eq!(Variant::Light.to_string(), "light");
+    }
+
+    #[test]
+    fn unix_to_datetime() {
+        assert_eq!(datetime_from_unix(0).to_string(), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            datetime_from_unix(1_709_210_096).to_string(),
+            "2024-02-29T12:34:56Z"
+        );
+    }
+
+    #[test]
+    fn touch() {
+        let mut metadata = Metadata::default();
+        assert_eq!(metadata, Metadata::default());
+        metadata.touch();
+        assert!(metadata.created.is_some());
+        assert_eq!(metadata.created, metadata.modified);
+        let created = Some(datetime_from_unix(0));
+        metadata.created = created;
+        metadata.touch();
+        assert_eq!(metadata.created, created);
+    }
+}
@@ -0,0 +1,925 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use indexmap::IndexMap;
+use slint::ModelRc;
+use uuid::Uuid;
+
+use super::{ColorType, GroupType, PaletteType};
+use crate::color::{Color, ContrastMethod, BLACK, WHITE};
+use crate::config::revision::RevisionStore;
+use slint::Color as Slint_Color;
+use slint::VecModel;
+
+pub mod ansi;
+pub mod diff;
+pub mod expression;
+pub mod extended;
+pub mod group;
+pub mod history;
+pub mod inheritance;
+pub mod library;
+pub mod lint;
+pub mod merge;
+pub mod metadata;
+pub mod naming;
+pub mod polarity;
+pub mod query;
+pub mod recolor;
+pub mod role;
+pub mod sort;
+pub mod stats;
+pub mod transform;
+pub mod vision;
+
+pub use ansi::AnsiNaming;
+pub use expression::ExpressionError;
+pub use group::GROUP_SEPARATOR;
+pub use history::{Edit, History};
+pub use inheritance::{Inheritance, EXTENDS_KEY};
+pub use library::{LibraryEvent, PaletteLibrary};
+pub use merge::{MergeReport, MergeStrategy};
+pub use metadata::{Metadata, Variant, METADATA_KEY};
+pub use naming::ColorNamer;
+pub use query::Query;
+pub use recolor::Assignment;
+pub use role::{ColorRole, RoleAliases, ROLES_KEY};
+pub use sort::SortOrder;
+pub use transform::Transform;
+pub use vision::{Deficiency, VisionOptions};
+
+/// Key of a palette's persisted id in its metadata table.
+pub const ID_KEY: &str = "id";
+
+/// Id of a palette that has none stored, stable for the same file and palette name
+/// whatever its case.
+pub fn derived_id(path: &Path, name: &str) -> Uuid {
+    let source = format!("file://{}#{}", path.display(), name.to_lowercase());
+    Uuid::new_v5(&Uuid::NAMESPACE_URL, source.as_bytes())
+}
+
+/// The palette with the given id.
+pub fn find_by_id<'a>(palettes: &'a [Palette], id: &Uuid) -> Option<&'a Palette> {
+    palettes.iter().find(|palette| palette.uuid == *id)
+}
+
+/// Prefix of the companion entries generated by [`Palette::add_on_colors`].
+pub const ON_COLOR_PREFIX: &str = "on-";
+
+#[derive(Debug, Clone)]
+pub struct Palette {
+    pub name: String,
+    pub uuid: Uuid,
+    pub colors: IndexMap<String, Color>,
+    pub filename: Option<PathBuf>,
+    pub metadata: Metadata,
+    /// Roles assigned explicitly in the palette file, taking priority over name aliases.
+    pub roles: IndexMap<ColorRole, String>,
+    /// Source of entries defined by an expression such as `lighten($color1, 10%)`.
+    /// Their resolved values are kept in `colors`.
+    pub expressions: IndexMap<String, String>,
+    /// Set when the palette `extends` another one.
+    pub inheritance: Option<Inheritance>,
+    /// Edits made through [`Palette::apply`], for undo and redo.
+    pub history: History,
+}
+
+pub struct ColorScheme {
+    pub foreground: Slint_Color,
+    pub background: Slint_Color,
+}
+
+impl Palette {
+    /// A new empty palette. Its id is derived from `filename` and `name` if there is a
+    /// file, and random otherwise; either way it is persisted once the palette is saved.
+    pub fn new(name: &str, filename: Option<PathBuf>) -> Self {
+        let uuid = match &filename {
+            Some(path) => derived_id(path, name),
+            None => Uuid::new_v4(),
+        };
+        Palette {
+            name: name.to_string(),
+            uuid,
+            colors: IndexMap::new(),
+            filename,
+            metadata: Metadata::default(),
+            roles: IndexMap::new(),
+            expressions: IndexMap::new(),
+            inheritance: None,
+            history: History::default(),
+        }
+    }
+
+    /// Writes the palette to `file_path`, updating its modified time, and
+    /// remembers the path as the palette's file. The palette's id is written too.
+    ///
+    /// A file in a config folder's palettes folder is snapshotted in that folder's
+    /// revisions.
+    pub fn save_to(&mut self, file_path: &Path) -> anyhow::Result<()> {
+        match RevisionStore::for_file(file_path) {
+            Some(store) => store.save(self, file_path).map(drop),
+            None => self.write_to(file_path),
+        }
+    }
+
+    /// Writes the palette to `file_path` as [`Palette::save_to`] does, without a snapshot.
+    pub(crate) fn write_to(&mut self, file_path: &Path) -> anyhow::Result<()> {
+        self.metadata.touch();
+        let previous = self.filename.replace(file_path.to_path_buf());
+        if let Err(error) = crate::config::writer::write_palette(self, file_path) {
+            self.filename = previous;
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    /// Adds a colour at the end of the palette; an existing colour keeps its position.
+    ///
+    /// Replaces any expression the entry had and updates the colours derived from it.
+    /// On error the palette is left unchanged.
+    pub fn add_color(
+        &mut self,
+        name: String,
+        color: Color,
+    ) -> Result<Option<Color>, ExpressionError> {
+        if self.expressions.is_empty() {
+            return Ok(self.colors.insert(name, color));
+        }
+        let mut colors = self.colors.clone();
+        let mut expressions = self.expressions.clone();
+        expressions.shift_remove(&name);
+        let previous = colors.insert(name, color);
+        expression::resolve(&mut colors, &expressions)?;
+        self.colors = colors;
+        self.expressions = expressions;
+        Ok(previous)
+    }
+
+    /// Adds an entry defined by an expression without resolving it, for loading
+    /// entries that refer to ones not added yet. Call [`Palette::resolve_expressions`]
+    /// once every entry is in place.
+    pub fn add_expression(&mut self, name: String, expression: &str) {
+        self.colors
+            .entry(name.clone())
+            .or_insert(Color::new(0, 0, 0, 0));
+        self.expressions.insert(name, expression.to_string());
+    }
+
+    /// Defines an entry by an expression and resolves it, along with everything derived from it.
+    ///
+    /// On error the palette is left unchanged.
+    pub fn set_expression(
+        &mut self,
+        name: &str,
+        expression: &str,
+    ) -> Result<Color, ExpressionError> {
+        let mut colors = self.colors.clone();
+        let mut expressions = self.expressions.clone();
+        colors
+            .entry(name.to_string())
+            .or_insert(Color::new(0, 0, 0, 0));
+        expressions.insert(name.to_string(), expression.to_string());
+        expression::resolve(&mut colors, &expressions)?;
+        self.colors = colors;
+        self.expressions = expressions;
+        Ok(self.colors[name])
+    }
+
+    /// The expression an entry is defined by, if it is not a plain colour.
+    pub fn expression(&self, name: &str) -> Option<&str> {
+        self.expressions.get(name).map(String::as_str)
+    }
+
+    /// Re-evaluates every expression from the current colours.
+    pub fn resolve_expressions(&mut self) -> Result<(), ExpressionError> {
+        expression::resolve(&mut self.colors, &self.expressions)
+    }
+
+    /// Inserts a colour at `index`, moving it there if the name already exists.
+    ///
+    /// An `index` past the end appends the colour.
+    pub fn insert_at(&mut self, index: usize, name: String, color: Color) -> Option<Color> {
+        let index = index.min(self.colors.len());
+        match self.colors.get_index_of(&name) {
+            Some(current) => {
+                let previous = self.colors.insert(name, color);
+                self.colors
+                    .move_index(current, index.min(self.colors.len() - 1));
+                previous
+            }
+            None => {
+                self.colors.shift_insert(index, name, color);
+                None
+            }
+        }
+    }
+
+    /// Removes a colour along with any roles explicitly assigned to it.
+    ///
+    /// Entries whose expressions refer to it keep their current colour as a plain value,
+    /// so the palette never refers to a colour it does not have.
+    pub fn remove_color(&mut self, name: &str) -> Option<(String, Color)> {
+        self.roles.retain(|_, assigned| assigned != name);
+        self.expressions.shift_remove(name);
+        for dependent in self.dependents(name) {
+            self.expressions.shift_remove(&dependent);
+        }
+        self.colors.shift_remove_entry(name)
+    }
+
+    /// The entries whose expressions refer to `name` directly.
+    fn dependents(&self, name: &str) -> Vec<String> {
+        self.expressions
+            .iter()
+            .filter(|(_, expression)| {
+                crate::parser::parse_expression(expression)
+                    .is_ok_and(|parsed| parsed.references().contains(&name))
+            })
+            .map(|(entry, _)| entry.clone())
+            .collect()
+    }
+
+    /// Moves the named colour to `index`, shifting the colours in between.
+    ///
+    /// Returns `false` if the colour does not exist or `index` is out of bounds.
+    pub fn move_color(&mut self, name: &str, index: usize) -> bool {
+        match self.colors.get_index_of(name) {
+            Some(current) if index < self.colors.len() => {
+                self.colors.move_index(current, index);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Renames a colour in place. Its roles and expression move with it, and expressions
+    /// referring to it are updated.
+    ///
+    /// Returns `false` if there is no colour `from` or another colour is already named `to`.
+    pub fn rename_color(&mut self, from: &str, to: &str) -> bool {
+        let index = match self.colors.get_index_of(from) {
+            Some(index) if from == to || !self.colors.contains_key(to) => index,
+            _ => return false,
+        };
+        if from == to {
+            return true;
+        }
+        let color = self.colors.shift_remove(from).expect("checked to exist");
+        self.colors.shift_insert(index, to.to_string(), color);
+        for assigned in self.roles.values_mut() {
+            if assigned == from {
+                *assigned = to.to_string();
+            }
+        }
+        if let Some(expression) = self.expressions.shift_remove(from) {
+            self.expressions.insert(to.to_string(), expression);
+        }
+        for expression in self.expressions.values_mut() {
+            if let Ok(parsed) = crate::parser::parse_expression(expression) {
+                if parsed.references().contains(&from) {
+                    *expression = parsed.renamed(from, to).to_string();
+                }
+            }
+        }
+        true
+    }
+
+    /// Position of the named colour in the palette.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.colors.get_index_of(name)
+    }
+
+    pub fn get_color(&self, name: &str) -> Option<&Color> {
+        self.colors.get(name)
+    }
+
+    pub fn color_names(&self) -> Vec<String> {
+        self.colors.keys().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    /// The most readable text colour for every colour in the palette.
+    ///
+    /// Black and white are always candidates; with `from_palette` every opaque
+    /// palette colour is considered too. Existing `on-` entries are skipped.
+    pub fn text_colors(&self, method: ContrastMethod, from_palette: bool) -> Vec<(String, Color)> {
+        let mut candidates = vec![BLACK, WHITE];
+        if from_palette {
+            candidates.extend(
+                self.colors
+                    .values()
+                    .filter(|color| color.opacity == 255)
+                    .copied(),
+            );
+        }
+        self.colors
+            .iter()
+            .filter(|(name, _)| !name.starts_with(ON_COLOR_PREFIX))
+            .map(|(name, color)| {
+                let others = candidates
+                    .iter()
+                    .filter(|candidate| *candidate != color)
+                    .copied()
+                    .collect::<Vec<Color>>();
+                (name.clone(), color.best_text_color(&others, method))
+            })
+            .collect()
+    }
+
+    /// Adds an `on-<name>` entry holding the most readable text colour for each colour.
+    ///
+    /// Existing `on-` entries are overwritten. Returns the number of entries written.
+    pub fn add_on_colors(
+        &mut self,
+        method: ContrastMethod,
+        from_palette: bool,
+    ) -> Result<usize, ExpressionError> {
+        let text_colors = self.text_colors(method, from_palette);
+        let count = text_colors.len();
+        for (name, color) in text_colors {
+            self.add_color(format!("{}{}", ON_COLOR_PREFIX, name), color)?;
+        }
+        Ok(count)
+    }
+
+    pub fn to_slint(&self) -> PaletteType {
+        self.to_slint_with(&self.color_types())
+    }
+
+    /// How each colour is shown in the viewer, with its text colour, name and roles.
+    fn color_types(&self) -> IndexMap<String, ColorType> {
+        let text_colors = self
+            .text_colors(ContrastMethod::default(), false)
+            .into_iter()
+            .collect::<IndexMap<String, Color>>();
+        let roles = self.roles_by_name();
+        let color_type = |key: &str, value: &Color| {
+            let text_color = text_colors
+                .get(key)
+                .copied()
+                .unwrap_or_else(|| value.best_text_color(&[], ContrastMethod::default()));
+            let mut color = value.to_colortype(key, text_color);
+            color.expression = self.expression(key).unwrap_or_default().into();
+            color.description = ColorNamer::global()
+                .name(value)
+                .map(|name| name.to_string())
+                .unwrap_or_default()
+                .into();
+            color.role = roles
+                .get(key)
+                .map(|roles| {
+                    roles
+                        .iter()
+                        .map(ColorRole::to_string)
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                })
+                .unwrap_or_default()
+                .into();
+            color
+        };
+        self.colors
+            .iter()
+            .map(|(key, value)| (key.clone(), color_type(key, value)))
+            .collect()
+    }
+
+    /// The palette for the viewer, its colours shown as in `color_types`, the `colors` of
+    /// [`Palette::to_slint`] for this palette or one it was filtered from. Naming each
+    /// colour and finding its roles is the costly part of that, so a filtered palette
+    /// can be shown without doing it again.
+    pub fn to_slint_with(&self, color_types: &IndexMap<String, ColorType>) -> PaletteType {
+        let color_type = |key: &str, value: &Color| match color_types.get(key) {
+            Some(color) => color.clone(),
+            None => value.to_colortype(key, value.best_text_color(&[], ContrastMethod::default())),
+        };
+        let groups = self
+            .groups()
+            .iter()
+            .map(|group| GroupType {
+                name: group.name.into(),
+                colors: ModelRc::new(VecModel::from(
+                    group
+                        .colors
+                        .iter()
+                        .map(|(key, value)| ColorType {
+                            name: group::leaf_name(key).into(),
+                            ..color_type(key, value)
+                        })
+                        .collect::<Vec<ColorType>>(),
+                )),
+            })
+            .collect::<Vec<GroupType>>();
+        PaletteType {
+            colors: ModelRc::new(VecModel::from(
+                self.colors
+                    .iter()
+                    .map(|(key, value)| color_type(key, value))
+                    .collect::<Vec<ColorType>>(),
+            )),
+            groups: ModelRc::new(VecModel::from(groups)),
+            name: self.name.clone().into(),
+            author: self.metadata.author.clone().unwrap_or_default().into(),
+            description: self.metadata.description.clone().unwrap_or_default().into(),
+            license: self.metadata.license.clone().unwrap_or_default().into(),
+            tags: self.metadata.tags.join(", ").into(),
+            variant: self
+                .metadata
+                .variant
+                .map(|variant| variant.to_string())
+                .unwrap_or_default()
+                .into(),
+            source: self
+                .metadata
+                .source
+                .clone()
+                .or_else(|| {
+                    self.metadata
+                        .wallpaper
+                        .as_ref()
+                        .map(|path| path.display().to_string())
+                })
+                .unwrap_or_default()
+                .into(),
+        }
+    }
+
+    /// Assigns `role` to the named colour. Returns `false` if there is no such colour.
+    pub fn assign_role(&mut self, role: ColorRole, name: &str) -> bool {
+        if !self.colors.contains_key(name) {
+            return false;
+        }
+        self.roles.insert(role, name.to_string());
+        true
+    }
+
+    /// Name of the colour filling `role`, using the installed [`RoleAliases`].
+    pub fn role_name(&self, role: ColorRole) -> Option<&str> {
+        self.role_name_with(role, RoleAliases::global())
+    }
+
+    /// Name of the colour filling `role`: the explicit assignment if there is one,
+    /// otherwise the first colour named after one of the role's aliases. Colours in a
+    /// group, like `ansi.red`, match by their name within it when no full name does.
+    pub fn role_name_with(&self, role: ColorRole, aliases: &RoleAliases) -> Option<&str> {
+        if let Some((name, _)) = self
+            .roles
+            .get(&role)
+            .and_then(|name| self.colors.get_key_value(name))
+        {
+            return Some(name.as_str());
+        }
+        let find = |name_of: fn(&str) -> &str| {
+            aliases.aliases(role).iter().find_map(|alias| {
+                self.colors
+                    .keys()
+                    .find(|name| role::normalize_name(name_of(name)) == *alias)
+                    .map(String::as_str)
+            })
+        };
+        find(|name| name).or_else(|| find(group::leaf_name))
+    }
+
+    pub fn role(&self, role: ColorRole) -> Option<Color> {
+        self.role_name(role)
+            .and_then(|name| self.get_color(name))
+            .copied()
+    }
+
+    /// Every role the named colour fills.
+    pub fn roles_of(&self, name: &str) -> Vec<ColorRole> {
+        ColorRole::all()
+            .into_iter()
+            .filter(|role| self.role_name(*role) == Some(name))
+            .collect()
+    }
+
+    /// Every colour filling a role, with the roles it fills. Cheaper than calling
+    /// [`Palette::roles_of`] for each colour.
+    pub fn roles_by_name(&self) -> HashMap<&str, Vec<ColorRole>> {
+        let mut names = HashMap::<&str, Vec<ColorRole>>::new();
+        for role in ColorRole::all() {
+            if let Some(name) = self.role_name(role) {
+                names.entry(name).or_default().push(role);
+            }
+        }
+        names
+    }
+
+    pub fn background(&self) -> Option<Color> {
+        self.role(ColorRole::Background)
+    }
+
+    pub fn foreground(&self) -> Option<Color> {
+        self.role(ColorRole::Foreground)
+    }
+
+    pub fn to_slint_theme(&self) -> Option<ColorScheme> {
+        let background = match self.background() {
+            None => return None,
+            Some(bg) => bg.to_opaque().to_slint(),
+        };
+        let foreground = match self.foreground() {
+            None => return None,
+            Some(fg) => fg.to_opaque().to_slint(),
+        };
+        Some(ColorScheme {
+            foreground,
+            background,
+        })
+    }
+}
+
+/// Compares two names, treating runs of ASCII digits as numbers.
+pub(crate) fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+    let mut tie_break = Ordering::Equal;
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return tie_break,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let mut x_digits = String::new();
+                while let Some(c) = a_chars.next_if(|c| c.is_ascii_digit()) {
+                    x_digits.push(c);
+                }
+                let mut y_digits = String::new();
+                while let Some(c) = b_chars.next_if(|c| c.is_ascii_digit()) {
+                    y_digits.push(c);
+                }
+                let x_trimmed = x_digits.trim_start_matches('0');
+                let y_trimmed = y_digits.trim_start_matches('0');
+                let ordering = x_trimmed
+                    .len()
+                    .cmp(&y_trimmed.len())
+                    .then_with(|| x_trimmed.cmp(y_trimmed));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                tie_break = tie_break.then_with(|| x_digits.len().cmp(&y_digits.len()));
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                tie_break = tie_break.then_with(|| x.cmp(&y));
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod palette_tests {
+    use super::*;
+    use crate::testing;
+    use slint::Model;
+
+    #[test]
+    fn test_create_palette() {
+        let input = Palette::new("Test Palette", None);
+        assert_eq!(input.name, "Test Palette".to_string());
+    }
+
+    #[test]
+    fn test_add_color() {
+        let mut input = Palette::new("Test Palette", None);
+        let testblue1 = Color::new_solid(0, 0, 255);
+        let testblue2 = Color::new_solid(0, 0, 255);
+        let first = input.add_color("Test Blue".to_string(), testblue1).unwrap();
+        assert_eq!(*input.colors.get("Test Blue").unwrap(), testblue1);
+        assert_eq!(first, None);
+        assert_eq!(
+            input.add_color("Test Blue".to_string(), testblue2),
+            Ok(Some(testblue1))
+        )
+    }
+
+    #[test]
+    fn test_get_color() {
+        let mut input = Palette::new("Test Palette", None);
+        let testblue = Color::new_solid(0, 0, 255);
+        input.add_color("Test Blue".to_string(), testblue).unwrap();
+        assert_eq!(input.get_color("Test Blue").unwrap(), &testblue)
+    }
+
+    #[test]
+    fn test_remove_color() {
+        let mut input = Palette::new("Test Palette", None);
+        let testblue = Color::new_solid(0, 0, 255);
+        input.add_color("Test Blue".to_string(), testblue).unwrap();
+        assert_eq!(*input.colors.get("Test Blue").unwrap(), testblue);
+        assert_eq!(input.colors.len(), 1);
+        assert_eq!(
+            input.remove_color("Test Blue"),
+            Some(("Test Blue".to_string(), testblue))
+        );
+        assert_eq!(input.colors.len(), 0);
+    }
+
+    #[test]
+    fn test_len() {
+        let mut input = Palette::new("Test Palette", None);
+        assert_eq!(input.len(), 0);
+        let testblue = Color::new_solid(0, 0, 255);
+        input.add_color("Test Blue".to_string(), testblue).unwrap();
+        assert_eq!(input.len(), 1);
+        let testblue = Color::new_solid(0, 0, 255);
+        input
+            .add_color("Test Blue 2".to_string(), testblue)
+            .unwrap();
+        assert_eq!(input.len(), 2);
+        let testblue = Color::new_solid(0, 0, 255);
+        input
+            .add_color("Test Blue 2".to_string(), testblue)
+            .unwrap();
+        assert_eq!(input.len(), 2);
+    }
+
+    #[test]
+    fn color_names() {
+        let mut palette = Palette::new("Test Palette", None);
+        let color1 = ("Test Color 1".to_string(), Color::from_hex("#FFF"));
+        let color2 = ("Test Color 2".to_string(), Color::from_hex("#F00"));
+        assert_eq!(palette.len(), 0);
+        palette.add_color(color1.0, color1.1).unwrap();
+        palette.add_color(color2.0, color2.1).unwrap();
+    }
+
+    #[test]
+    fn text_colors() {
+        let mut palette = Palette::new("Test Palette", None);
+        palette
+            .add_color("yellow".to_string(), Color::from_hex("#FF0"))
+            .unwrap();
+        palette
+            .add_color("navy".to_string(), Color::from_hex("#000080"))
+            .unwrap();
+        assert_eq!(
+            palette.text_colors(ContrastMethod::Wcag, false),
+            vec![("yellow".to_string(), BLACK), ("navy".to_string(), WHITE)]
+        );
+        assert_eq!(
+            palette.text_colors(ContrastMethod::Apca, true),
+            vec![("yellow".to_string(), BLACK), ("navy".to_string(), WHITE)]
+        );
+    }
+
+    #[test]
+    fn add_on_colors() {
+        let mut palette = Palette::new("Test Palette", None);
+        palette
+            .add_color("yellow".to_string(), Color::from_hex("#FF0"))
+            .unwrap();
+        palette.add_color("black".to_string(), BLACK).unwrap();
+        assert_eq!(palette.add_on_colors(ContrastMethod::Wcag, true), Ok(2));
+        assert_eq!(palette.get_color("on-yellow"), Some(&BLACK));
+        assert_eq!(palette.get_color("on-black"), Some(&WHITE));
+        assert_eq!(palette.add_on_colors(ContrastMethod::Wcag, true), Ok(2));
+        assert_eq!(palette.len(), 4);
+    }
+
+    fn numbered_palette() -> Palette {
+        testing::palette(
+            "Test Palette",
+            &[
+                ("color0", "#000"),
+                ("color1", "#F00"),
+                ("color2", "#0F0"),
+                ("color3", "#00F"),
+            ],
+        )
+    }
+
+    #[test]
+    fn keeps_insertion_order() {
+        let mut palette = Palette::new("Test Palette", None);
+        for name in ["color2", "color10", "background", "color1"] {
+            palette
+                .add_color(name.to_string(), Color::from_hex("#FFF"))
+                .unwrap();
+        }
+        let _ = palette.remove_color("color10");
+        assert_eq!(
+            palette.color_names(),
+            vec!["color2", "background", "color1"]
+        );
+    }
+
+    #[test]
+    fn move_color() {
+        let mut palette = numbered_palette();
+        assert!(palette.move_color("color3", 0));
+        assert_eq!(
+            palette.color_names(),
+            vec!["color3", "color0", "color1", "color2"]
+        );
+        assert!(!palette.move_color("color3", 4));
+        assert!(!palette.move_color("missing", 0));
+    }
+
+    #[test]
+    fn insert_at() {
+        let mut palette = numbered_palette();
+        let white = Color::from_hex("#FFF");
+        assert_eq!(palette.insert_at(1, "white".to_string(), white), None);
+        assert_eq!(palette.index_of("white"), Some(1));
+        assert_eq!(
+            palette.insert_at(10, "white".to_string(), white),
+            Some(white)
+        );
+        assert_eq!(palette.index_of("white"), Some(4));
+        assert_eq!(palette.insert_at(0, "end".to_string(), white), None);
+        assert_eq!(palette.index_of("end"), Some(0));
+    }
+
+    #[test]
+    fn natural_order() {
+        let mut names = vec!["color10", "Color2", "background", "color1", "color02"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            vec!["background", "color1", "Color2", "color02", "color10"]
+        );
+    }
+
+    #[test]
+    fn role_lookup() {
+        let mut palette = Palette::new("Test Palette", None);
+        palette
+            .add_color("Base00".to_string(), Color::from_hex("#111"))
+            .unwrap();
+        palette
+            .add_color("BG".to_string(), Color::from_hex("#222"))
+            .unwrap();
+        palette
+            .add_color("Text".to_string(), Color::from_hex("#EEE"))
+            .unwrap();
+        palette
+            .add_color("bright_red".to_string(), Color::from_hex("#F55"))
+            .unwrap();
+        assert_eq!(palette.background(), Some(Color::from_hex("#222")));
+        assert_eq!(palette.foreground(), Some(Color::from_hex("#EEE")));
+        assert_eq!(palette.role_name(ColorRole::Ansi(9)), Some("bright_red"));
+        assert_eq!(palette.role(ColorRole::Accent), None);
+
+        assert!(palette.assign_role(ColorRole::Background, "Base00"));
+        assert!(!palette.assign_role(ColorRole::Accent, "missing"));
+        assert_eq!(palette.background(), Some(Color::from_hex("#111")));
+        assert_eq!(palette.roles_of("Base00"), vec![ColorRole::Background]);
+        assert_eq!(palette.roles_of("BG"), vec![]);
+        assert_eq!(
+            palette.roles_by_name().get("Base00"),
+            Some(&vec![ColorRole::Background])
+        );
+
+        let _ = palette.remove_color("Base00");
+        assert!(palette.roles.is_empty());
+        assert_eq!(palette.background(), Some(Color::from_hex("#222")));
+    }
+
+    #[test]
+    fn role_lookup_with_aliases() {
+        let mut palette = Palette::new("Test Palette", None);
+        palette
+            .add_color("bg".to_string(), Color::from_hex("#222"))
+            .unwrap();
+        palette
+            .add_color("base00".to_string(), Color::from_hex("#111"))
+            .unwrap();
+        let mut aliases = RoleAliases::default();
+        assert_eq!(
+            palette.role_name_with(ColorRole::Background, &aliases),
+            Some("bg")
+        );
+        aliases.prepend(ColorRole::Background, &["base00".to_string()]);
+        assert_eq!(
+            palette.role_name_with(ColorRole::Background, &aliases),
+            Some("base00")
+        );
+    }
+
+    #[test]
+    fn expressions_follow_edits() {
+        let mut palette = Palette::new("Test Palette", None);
+        palette.add_expression("color9".to_string(), "lighten($color1, 25%)");
+        palette.add_expression("cursor".to_string(), "$color9");
+        palette
+            .add_color("color1".to_string(), Color::from_hex("#F00"))
+            .unwrap();
+        assert_eq!(palette.get_color("cursor").unwrap().hex(), "#FF8080");
+        assert_eq!(palette.color_names(), vec!["color9", "cursor", "color1"]);
+
+        palette
+            .add_color("color1".to_string(), Color::from_hex("#00F"))
+            .unwrap();
+        assert_eq!(palette.get_color("color9").unwrap().hex(), "#8080FF");
+        assert_eq!(palette.get_color("cursor").unwrap().hex(), "#8080FF");
+        assert_eq!(palette.expression("cursor"), Some("$color9"));
+
+        palette
+            .add_color("color9".to_string(), Color::from_hex("#0F0"))
+            .unwrap();
+        assert_eq!(palette.expression("color9"), None);
+        assert_eq!(palette.get_color("cursor").unwrap().hex(), "#00FF00");
+    }
+
+    #[test]
+    fn set_expression() {
+        let mut palette = Palette::new("Test Palette", None);
+        palette
+            .add_color("bg".to_string(), Color::from_hex("#000"))
+            .unwrap();
+        assert_eq!(
+            palette.set_expression("border", "mix($bg, #FFF, 20%)"),
+            Ok(Color::from_hex("#333"))
+        );
+        assert!(palette.set_expression("bg", "$border").is_err());
+        assert_eq!(palette.expression("bg"), None);
+        assert_eq!(palette.get_color("bg"), Some(&Color::from_hex("#000")));
+    }
+
+    #[test]
+    fn rename_keeps_amounts() {
+        let mut palette = testing::palette("Test Palette", &[("a", "#808080")]);
+        palette.set_expression("light", "lighten($a, 7%)").unwrap();
+        palette.set_expression("dark", "darken($a, 29%)").unwrap();
+        assert!(palette.rename_color("a", "base"));
+        assert_eq!(palette.expression("light"), Some("lighten($base, 7%)"));
+        assert_eq!(palette.expression("dark"), Some("darken($base, 29%)"));
+    }
+
+    #[test]
+    fn to_slint_with_color_types() {
+        let mut palette =
+            testing::palette("Test Palette", &[("base", "#000"), ("ui.border", "#333")]);
+        palette.assign_role(ColorRole::Background, "base");
+        let mut color_types = palette
+            .to_slint()
+            .colors
+            .iter()
+            .map(|color| (color.name.to_string(), color))
+            .collect::<IndexMap<_, _>>();
+        assert_eq!(color_types["base"].role, "background");
+        color_types["ui.border"].description = "kept".into();
+
+        palette.remove_color("base");
+        let shown = palette.to_slint_with(&color_types);
+        assert_eq!(shown.colors.row_count(), 1);
+        let group = shown.groups.row_data(0).unwrap();
+        assert_eq!(group.name, "ui");
+        let border = group.colors.row_data(0).unwrap();
+        assert_eq!(
+            (border.name.as_str(), border.description.as_str()),
+            ("border", "kept")
+        );
+    }
+
+    #[test]
+    fn removing_a_referenced_color() {
+        let mut palette = Palette::new("Test Palette", None);
+        palette
+            .add_color("red".to_string(), Color::from_hex("#C00"))
+            .unwrap();
+        palette
+            .set_expression("bright-red", "lighten($red, 20%)")
+            .unwrap();
+        palette.set_expression("cursor", "$bright-red").unwrap();
+        let bright_red = palette.colors["bright-red"];
+
+        palette.remove_color("red");
+        assert_eq!(palette.expression("bright-red"), None);
+        assert_eq!(palette.colors["bright-red"], bright_red);
+        assert_eq!(palette.expression("cursor"), Some("$bright-red"));
+        let written = crate::config::writer::palettes_to_string(&[&palette]).unwrap();
+        assert!(!written.contains("$red"), "{}", written);
+        assert!(palette.resolve_expressions().is_ok());
+    }
+
+    #[test]
+    fn add_color_reports_unresolvable_expressions() {
+        let mut palette = Palette::new("Test Palette", None);
+        palette.add_expression("cursor".to_string(), "$missing");
+        let error = palette
+            .add_color("bg".to_string(), Color::from_hex("#000"))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            ExpressionError::UnknownColor {
+                entry: "cursor".to_string(),
+                reference: "missing".to_string()
+            }
+        );
+        assert_eq!(palette.color_names(), vec!["cursor"]);
+    }
+}
@@ -0,0 +1,310 @@
+use std::{fmt, fs, path::Path, sync::OnceLock};
+
+use anyhow::Context;
+
+use super::{ExpressionError, Palette};
+use crate::color::{ciede2000, Color};
+
+/// Folder in the config folder holding extra naming dictionaries, one `.txt` file each.
+pub const DICTIONARIES_FOLDER: &str = "dictionaries";
+
+const CSS_DICTIONARY: &str = include_str!("dictionaries/css.txt");
+
+static NAMER: OnceLock<ColorNamer> = OnceLock::new();
+
+/// Names for colours, such as the CSS named colours or the xkcd colour survey.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ColorDictionary {
+    pub name: String,
+    /// Names with their colours and the colours in CIELAB.
+    entries: Vec<(String, Color, (f64, f64, f64))>,
+}
+
+/// The nearest name [`ColorNamer`] found for a colour.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ColorName {
+    pub name: String,
+    /// Name of the dictionary the name comes from.
+    pub dictionary: String,
+    /// CIEDE2000 difference between the colour and the named one.
+    pub delta_e: f64,
+}
+
+impl fmt::Display for ColorName {
+    /// The name, marked as approximate when the named colour is noticeably different.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.delta_e < 1.0 {
+            true => write!(f, "{}", self.name),
+            false => write!(f, "≈ {}", self.name),
+        }
+    }
+}
+
+impl ColorDictionary {
+    /// The 148 CSS named colours, synonyms such as `gray` and `grey` included.
+    pub fn css() -> Self {
+        Self::parse("css", CSS_DICTIONARY)
+    }
+
+    /// A dictionary with a name and a colour on each line, in either order, separated by
+    /// a tab, comma or semicolon, or by spaces if the colour is written `#RRGGBB`. This
+    /// reads the xkcd survey's `rgb.txt` and CSV exports of Name That Color as they are.
+    /// Lines without a colour, such as headers and `# comments`, are skipped.
+    pub fn parse(name: &str, text: &str) -> Self {
+        let entries = text
+            .lines()
+            .filter_map(parse_line)
+            .map(|(name, color)| (name, color, color.lab()))
+            .collect();
+        Self {
+            name: name.to_string(),
+            entries,
+        }
+    }
+
+    /// Reads a dictionary file, named after the file without its extension.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Unable to read dictionary {}", path.display()))?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Ok(Self::parse(&name, &text))
+    }
+
+    /// The entry closest to `color` by CIEDE2000, ignoring opacity. Of entries equally
+    /// close, the first listed.
+    pub fn nearest(&self, color: &Color) -> Option<ColorName> {
+        let lab = color.to_opaque().lab();
+        self.entries
+            .iter()
+            .map(|(name, _, entry)| (name, ciede2000(lab, *entry)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(name, delta_e)| ColorName {
+                name: name.clone(),
+                dictionary: self.name.clone(),
+                delta_e,
+            })
+    }
+}
+
+/// A name and colour from one line of a dictionary.
+fn parse_line(line: &str) -> Option<(String, Color)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with("# ") || line.starts_with("//") {
+        return None;
+    }
+    let trim = |field: &str| {
+        field
+            .trim_matches(|c: char| c.is_whitespace() || "\"'[](){}".contains(c))
+            .to_string()
+    };
+    let mut fields = line.split(['\t', ',', ';']).map(trim).collect::<Vec<_>>();
+    fields.retain(|field| !field.is_empty());
+    if fields.len() < 2 {
+        fields = line.split_whitespace().map(trim).collect();
+        let index = fields.iter().position(|field| field.starts_with('#'))?;
+        let color = Color::try_from_hex(&fields.remove(index))?;
+        return Some((fields.join(" "), color)).filter(|(name, _)| !name.is_empty());
+    }
+    // A written `#` marks the colour, so a name such as `facade` is not taken for one.
+    let index = fields
+        .iter()
+        .position(|field| field.starts_with('#') && Color::try_from_hex(field).is_some())
+        .or_else(|| {
+            fields
+                .iter()
+                .position(|field| field.len() == 6 && Color::try_from_hex(field).is_some())
+        })?;
+    let color = Color::try_from_hex(&fields.remove(index))?;
+    Some((fields.remove(0), color))
+}
+
+/// The dictionaries colours are named from, most preferred first.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ColorNamer {
+    dictionaries: Vec<ColorDictionary>,
+}
+
+impl Default for ColorNamer {
+    fn default() -> Self {
+        Self {
+            dictionaries: vec![ColorDictionary::css()],
+        }
+    }
+}
+
+impl ColorNamer {
+    /// The dictionaries in the `dictionaries` folder of `config_folder`, in file name
+    /// order, followed by the bundled CSS names. Dictionaries such as the xkcd survey's
+    /// `rgb.txt` are used by dropping them in that folder.
+    pub fn from_folder(config_folder: &Path) -> anyhow::Result<Self> {
+        let folder = config_folder.join(DICTIONARIES_FOLDER);
+        let mut paths = match fs::read_dir(&folder) {
+            Ok(entries) => entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+                .collect::<Vec<_>>(),
+            Err(_) => Vec::new(),
+        };
+        paths.sort();
+        let mut dictionaries = paths
+            .iter()
+            .map(|path| ColorDictionary::load(path))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        dictionaries.push(ColorDictionary::css());
+        Ok(Self { dictionaries })
+    }
+
+    /// The nearest name to `color` in any dictionary. Of names equally close, the one
+    /// from the most preferred dictionary.
+    pub fn name(&self, color: &Color) -> Option<ColorName> {
+        self.dictionaries
+            .iter()
+            .filter_map(|dictionary| dictionary.nearest(color))
+            .min_by(|a, b| a.delta_e.total_cmp(&b.delta_e))
+    }
+
+    /// A colour name for `color` in `palette`: its nearest name in lowercase words
+    /// joined by `-`, such as `cornflower-blue`, numbered from `-2` if the palette
+    /// already has it. `color` if no dictionary has any names.
+    pub fn suggest_name(&self, color: &Color, palette: &Palette) -> String {
+        let base = self
+            .name(color)
+            .map(|name| {
+                name.name
+                    .split(|c: char| !c.is_alphanumeric())
+                    .filter(|word| !word.is_empty())
+                    .map(str::to_lowercase)
+                    .collect::<Vec<_>>()
+                    .join("-")
+            })
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| "color".to_string());
+        (1..)
+            .map(|number| match number {
+                1 => base.clone(),
+                number => format!("{}-{}", base, number),
+            })
+            .find(|name| !palette.colors.contains_key(name))
+            .expect("some number is free")
+    }
+
+    /// Makes this namer the one used by [`ColorNamer::global`].
+    /// Returns `false` if the global namer was already in use.
+    pub fn install(self) -> bool {
+        NAMER.set(self).is_ok()
+    }
+
+    /// The installed namer, or the CSS names if none was installed.
+    pub fn global() -> &'static ColorNamer {
+        NAMER.get_or_init(ColorNamer::default)
+    }
+}
+
+impl Palette {
+    /// Adds a colour at the end of the palette under a name suggested by the installed
+    /// [`ColorNamer`], returning the name.
+    pub fn add_named_color(&mut self, color: Color) -> Result<String, ExpressionError> {
+        let name = ColorNamer::global().suggest_name(&color, self);
+        self.add_color(name.clone(), color)?;
+        Ok(name)
+    }
+}
+
+#[cfg(test)]
+mod naming_tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn css_names() {
+        let css = ColorDictionary::css();
+        assert_eq!(css.entries.len(), 148);
+        let name = css.nearest(&Color::from_hex("#6495ED")).unwrap();
+        assert_eq!(name.name, "cornflower blue");
+        assert_eq!(name.to_string(), "cornflower blue");
+        let near = css.nearest(&Color::from_hex("#6496EE")).unwrap();
+        assert_eq!(near.name, "cornflower blue");
+        assert!(near.delta_e > 0.0);
+        assert_eq!(
+            css.nearest(&Color::from_hex("#808080")).unwrap().name,
+            "gray"
+        );
+        assert_eq!(
+            css.nearest(&Color::from_hex("#110F1E"))
+                .unwrap()
+                .to_string(),
+            "≈ black"
+        );
+    }
+
+    #[test]
+    fn parse_formats() {
+        let dictionary = ColorDictionary::parse(
+            "mixed",
+            "License: http://creativecommons.org/publicdomain/zero/1.0/
+cloudy blue\t#acc2d9\t
+# a comment
+[\"F0DC82\", \"Buff\"],
+#FF0000 fire engine
+facade, #123456
+",
+        );
+        let entries = dictionary
+            .entries
+            .iter()
+            .map(|(name, color, _)| (name.as_str(), color.hex()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            vec![
+                ("cloudy blue", "#ACC2D9".to_string()),
+                ("Buff", "#F0DC82".to_string()),
+                ("fire engine", "#FF0000".to_string()),
+                ("facade", "#123456".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn namer_and_suggestions() {
+        let folder = testing::config_folder();
+        fs::create_dir_all(folder.join(DICTIONARIES_FOLDER)).unwrap();
+        fs::write(
+            folder.join(DICTIONARIES_FOLDER).join("xkcd.txt"),
+            "tomato red\t#ec2d01\n",
+        )
+        .unwrap();
+        let namer = ColorNamer::from_folder(&folder).unwrap();
+        assert_eq!(namer.dictionaries.len(), 2);
+        let name = namer.name(&Color::from_hex("#EC2D01")).unwrap();
+        assert_eq!(
+            (name.name.as_str(), name.dictionary.as_str()),
+            ("tomato red", "xkcd")
+        );
+        assert_eq!(
+            namer.name(&Color::from_hex("#F0F8FF")).unwrap().dictionary,
+            "css"
+        );
+
+        let mut palette = Palette::new("Unnamed", None);
+        palette
+            .add_color("tomato-red".to_string(), Color::from_hex("#EC2D01"))
+            .unwrap();
+        assert_eq!(
+            namer.suggest_name(&Color::from_hex("#EB2C02"), &palette),
+            "tomato-red-2"
+        );
+        assert_eq!(
+            ColorNamer {
+                dictionaries: Vec::new()
+            }
+            .suggest_name(&Color::from_hex("#000"), &palette),
+            "color"
+        );
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -0,0 +1,211 @@
+use super::{lint::LintOptions, ColorRole, Palette, Variant};
+use crate::color::Color;
+
+/// Colours [`Palette::opposite_variant`] moved further from the new background
+/// to keep their contrast.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ContrastAdjustment {
+    pub name: String,
+    /// Contrast against the original background.
+    pub original: f64,
+    /// Contrast against the new background before and after the adjustment.
+    pub before: f64,
+    pub after: f64,
+}
+
+impl Palette {
+    /// Whether the palette is light or dark: its `variant` metadata, or else the
+    /// lightness of its background.
+    pub fn polarity(&self) -> Option<Variant> {
+        if let Some(variant) = self.metadata.variant {
+            return Some(variant);
+        }
+        self.background().map(|background| {
+            if background.oklab().0 < 0.5 {
+                Variant::Dark
+            } else {
+                Variant::Light
+            }
+        })
+    }
+
+    /// A light variant of a dark palette, or a dark variant of a light one.
+    ///
+    /// Lightness is mirrored in Oklch between the background and foreground, so they trade
+    /// places while every colour keeps its hue, chroma and distance from the background.
+    /// Colours that lose contrast against the new background are then moved away from it
+    /// until they are back to their original contrast, capped at `options.min_contrast`.
+    ///
+    /// Expressions are dropped as they would derive the wrong values in the new variant.
+    pub fn opposite_variant(&self, options: &LintOptions) -> (Palette, Vec<ContrastAdjustment>) {
+        let background = self.background().map(|color| color.to_opaque());
+        let (bg_lightness, fg_lightness) = match (background, self.foreground()) {
+            (Some(bg), Some(fg)) => (bg.oklab().0, fg.oklab().0),
+            _ if self.polarity() == Some(Variant::Light) => (1.0, 0.0),
+            _ => (0.0, 1.0),
+        };
+        let variant = match self.polarity() {
+            Some(Variant::Light) => Variant::Dark,
+            _ => Variant::Light,
+        };
+
+        let mut palette = Palette::new(&opposite_name(&self.name, variant), None);
+        palette.metadata = self.metadata.clone();
+        palette.metadata.variant = Some(variant);
+        palette.metadata.created = None;
+        palette.metadata.modified = None;
+        palette.roles = self.roles.clone();
+        for (name, color) in &self.colors {
+            let (lightness, chroma, hue) = color.oklch();
+            let mirrored = (bg_lightness + fg_lightness - lightness).clamp(0.0, 1.0);
+            palette.colors.insert(
+                name.clone(),
+                Color {
+                    opacity: color.opacity,
+                    ..Color::from_oklch(mirrored, chroma, hue)
+                },
+            );
+        }
+
+        let mut adjustments = Vec::new();
+        let (old_background, new_background) = match (background, palette.background()) {
+            (Some(old), Some(new)) => (old, new.to_opaque()),
+            _ => return (palette, adjustments),
+        };
+        let background_names = [ColorRole::Background, ColorRole::SelectionBackground]
+            .iter()
+            .filter_map(|role| palette.role_name(*role).map(str::to_string))
+            .collect::<Vec<_>>();
+        let method = options.contrast_method;
+        for (name, color) in palette.colors.iter_mut() {
+            if background_names.contains(name) {
+                continue;
+            }
+            let original = self.colors[name]
+                .to_opaque()
+                .contrast(&old_background, method);
+            let target = original.min(options.min_contrast);
+            let before = color.to_opaque().contrast(&new_background, method);
+            if before >= target {
+                continue;
+            }
+            let adjusted = color.with_contrast(&new_background, target, method);
+            *color = adjusted;
+            adjustments.push(ContrastAdjustment {
+                name: name.clone(),
+                original,
+                before,
+                after: adjusted.to_opaque().contrast(&new_background, method),
+            });
+        }
+        (palette, adjustments)
+    }
+}
+
+/// `name` with a trailing `Dark` or `Light` swapped for `variant`, or `variant` appended.
+fn opposite_name(name: &str, variant: Variant) -> String {
+    let label = match variant {
+        Variant::Light => "Light",
+        Variant::Dark => "Dark",
+    };
+    for suffix in ["dark", "light"] {
+        // `get` returns `None` when the split would fall inside a character.
+        let split = name.len().saturating_sub(suffix.len());
+        if let (Some(stem), Some(end)) = (name.get(..split), name.get(split..)) {
+            if end.eq_ignore_ascii_case(suffix) && stem.ends_with([' ', '-', '_']) {
+                return format!("{}{}", stem, label);
+            }
+        }
+    }
+    format!("{} {}", name, label)
+}
+
+#[cfg(test)]
+mod polarity_tests {
+    use super::*;
+    use crate::testing;
+
+    fn dark_palette() -> Palette {
+        testing::palette(
+            "Night Dark",
+            &[
+                ("background", "#1E1E2E"),
+                ("foreground", "#CDD6F4"),
+                ("red", "#F38BA8"),
+                ("blue", "#89B4FA"),
+                ("yellow", "#F9E2AF"),
+                ("comment", "#6C7086"),
+            ],
+        )
+    }
+
+    #[test]
+    fn light_from_dark() {
+        let dark = dark_palette();
+        assert_eq!(dark.polarity(), Some(Variant::Dark));
+        let (light, adjustments) = dark.opposite_variant(&LintOptions::default());
+        assert_eq!(light.name, "Night Light");
+        assert_eq!(light.polarity(), Some(Variant::Light));
+        assert_eq!(light.color_names(), dark.color_names());
+
+        let background = light.background().unwrap();
+        let foreground = light.foreground().unwrap();
+        assert!(background.oklab().0 > 0.8);
+        assert!(foreground.oklab().0 < 0.35);
+        assert!(foreground.contrast_ratio(&background) >= 4.5);
+
+        for name in ["red", "blue", "comment"] {
+            let (_, _, before) = dark.get_color(name).unwrap().oklch();
+            let (_, _, after) = light.get_color(name).unwrap().oklch();
+            assert!(
+                (before - after).abs() < 10.0,
+                "{} hue {} -> {}",
+                name,
+                before,
+                after
+            );
+        }
+        // Mirrored, the muted comment colour reads a little fainter on the light background.
+        assert_eq!(adjustments.len(), 1);
+        assert_eq!(adjustments[0].name, "comment");
+        for adjustment in &adjustments {
+            assert!(adjustment.after > adjustment.before);
+            assert!(adjustment.after >= adjustment.original.min(4.5) - 1e-9);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let dark = dark_palette();
+        let (light, _) = dark.opposite_variant(&LintOptions::default());
+        let (dark_again, _) = light.opposite_variant(&LintOptions::default());
+        assert_eq!(dark_again.name, "Night Dark");
+        assert_eq!(dark_again.polarity(), Some(Variant::Dark));
+        assert!(
+            dark_again
+                .background()
+                .unwrap()
+                .delta_e(&dark.background().unwrap())
+                < 2.0
+        );
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(
+            opposite_name("Gruvbox Dark", Variant::Light),
+            "Gruvbox Light"
+        );
+        assert_eq!(
+            opposite_name("solarized-light", Variant::Dark),
+            "solarized-Dark"
+        );
+        assert_eq!(opposite_name("Darkside", Variant::Light), "Darkside Light");
+        // The Kelvin sign lowercases to `k`, so this name lowercases to `night dark`.
+        assert_eq!(
+            opposite_name("Night DAR\u{212A}", Variant::Light),
+            "Night DAR\u{212A} Light"
+        );
+        assert_eq!(opposite_name("dark", Variant::Light), "dark Light");
+    }
+}
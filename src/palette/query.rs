@@ -0,0 +1,642 @@
+use std::{fmt, str::FromStr};
+
+use glob::{MatchOptions, Pattern};
+use winnow::{
+    ascii::{digit1, multispace0, multispace1, Caseless},
+    combinator::{alt, cut_err, delimited, eof, not, opt, preceded, separated, terminated},
+    error::{StrContext, StrContextValue},
+    token::take_while,
+    PResult, Parser,
+};
+
+use super::{group::split_group, history::History, stats::ACHROMATIC_CHROMA, ColorRole, Palette};
+use crate::color::Color;
+
+/// A filter over a palette's colours.
+///
+/// ```text
+/// lightness > 0.6 and hue in 180..240
+/// name ~ "color1*"
+/// role = accent
+/// contrast(bg) < 4.5
+/// not (group = ansi or chroma < 5%)
+/// ```
+///
+/// Numeric fields are `lightness`, `chroma` and `hue` in Oklch, `luminance`, `alpha`,
+/// `red`, `green`, `blue` and `contrast(TARGET)`, the WCAG contrast ratio against a colour,
+/// role or hex colour. `red`, `green` and `blue` run from 0 to 255, so `50%` of them is
+/// 127.5; other percentages are fractions. Text fields are `name`, `group`, `role` and `hex`; `~` matches them
+/// against a glob pattern, ignoring case, and only `hex` compares with a hex colour. Greys have no hue, so never match on it.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Query {
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+    Compare(Field, Op, Value),
+    /// The field is within the inclusive range. A hue range may wrap, as in `330..30`.
+    InRange(Field, f64, f64),
+    /// The field matches a glob pattern.
+    Matches(Field, String),
+}
+
+/// What a [`Query`] looks at in each colour.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Field {
+    Name,
+    Group,
+    Role,
+    Hex,
+    Lightness,
+    Chroma,
+    Hue,
+    Luminance,
+    Alpha,
+    Red,
+    Green,
+    Blue,
+    /// Contrast ratio against the named colour or role, or a hex colour.
+    Contrast(Target),
+}
+
+/// The colour `contrast(...)` compares against.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Target {
+    Color(Color),
+    /// A colour name, or else a role such as `bg`.
+    Name(String),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    /// Percentages are already scaled for the field, so `60%` of `lightness` is `0.6`.
+    Number(f64),
+    Text(String),
+    Color(Color),
+}
+
+impl Field {
+    fn is_numeric(&self) -> bool {
+        !matches!(self, Field::Name | Field::Group | Field::Role | Field::Hex)
+    }
+
+    /// The number an amount stands for in this field: a percentage of 255 for the colour
+    /// channels, and a fraction for everything else.
+    fn scale(&self, (value, percent): (f64, bool)) -> f64 {
+        match (percent, self) {
+            (false, _) => value,
+            (true, Field::Red | Field::Green | Field::Blue) => value * 255.0 / 100.0,
+            (true, _) => value / 100.0,
+        }
+    }
+
+    /// The field's value for a number comparison, `None` if it has none.
+    fn number(&self, palette: &Palette, color: &Color) -> Option<f64> {
+        let (lightness, chroma, hue) = color.oklch();
+        let (red, green, blue, opacity) = color.rgba();
+        Some(match self {
+            Field::Lightness => lightness,
+            Field::Chroma => chroma,
+            Field::Hue if chroma < ACHROMATIC_CHROMA => return None,
+            Field::Hue => hue.rem_euclid(360.0),
+            Field::Luminance => color.relative_luminance(),
+            Field::Alpha => opacity as f64 / 255.0,
+            Field::Red => red as f64,
+            Field::Green => green as f64,
+            Field::Blue => blue as f64,
+            Field::Contrast(target) => {
+                let target = target.resolve(palette)?;
+                color.to_opaque().contrast_ratio(&target.to_opaque())
+            }
+            Field::Name | Field::Group | Field::Role | Field::Hex => return None,
+        })
+    }
+
+    /// The field's values for a text comparison; a colour can fill several roles.
+    fn texts(&self, palette: &Palette, name: &str, color: &Color) -> Vec<String> {
+        match self {
+            Field::Name => vec![name.to_string()],
+            Field::Group => vec![split_group(name).0.unwrap_or_default().to_string()],
+            Field::Role => palette
+                .roles_of(name)
+                .iter()
+                .map(ColorRole::to_string)
+                .collect(),
+            Field::Hex => vec![match color.opacity {
+                255 => color.hex(),
+                _ => color.hexa(),
+            }],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Target {
+    fn resolve(&self, palette: &Palette) -> Option<Color> {
+        match self {
+            Target::Color(color) => Some(*color),
+            Target::Name(name) => palette.get_color(name).copied().or_else(|| {
+                name.parse::<ColorRole>()
+                    .ok()
+                    .and_then(|role| palette.role(role))
+            }),
+        }
+    }
+}
+
+impl Op {
+    fn holds<T: PartialOrd>(&self, left: T, right: T) -> bool {
+        match self {
+            Op::Eq => left == right,
+            Op::Ne => left != right,
+            Op::Lt => left < right,
+            Op::Le => left <= right,
+            Op::Gt => left > right,
+            Op::Ge => left >= right,
+        }
+    }
+}
+
+impl Query {
+    /// Whether the named colour matches.
+    pub fn matches(&self, palette: &Palette, name: &str, color: &Color) -> bool {
+        match self {
+            Query::And(left, right) => {
+                left.matches(palette, name, color) && right.matches(palette, name, color)
+            }
+            Query::Or(left, right) => {
+                left.matches(palette, name, color) || right.matches(palette, name, color)
+            }
+            Query::Not(query) => !query.matches(palette, name, color),
+            Query::Compare(field, op, value) => match value {
+                Value::Number(number) => field
+                    .number(palette, color)
+                    .is_some_and(|found| op.holds(found, *number)),
+                Value::Color(expected) => {
+                    let equal = color == expected;
+                    if *op == Op::Ne {
+                        !equal
+                    } else {
+                        equal
+                    }
+                }
+                Value::Text(text) => {
+                    let texts = field.texts(palette, name, color);
+                    let equal = match field {
+                        Field::Role => match text.parse::<ColorRole>() {
+                            Ok(role) => palette.roles_of(name).contains(&role),
+                            Err(_) => false,
+                        },
+                        _ => texts.iter().any(|found| found.eq_ignore_ascii_case(text)),
+                    };
+                    if *op == Op::Ne {
+                        !equal
+                    } else {
+                        equal
+                    }
+                }
+            },
+            Query::InRange(field, low, high) => {
+                let found = match field.number(palette, color) {
+                    Some(found) => found,
+                    None => return false,
+                };
+                if *field == Field::Hue && low > high {
+                    found >= *low || found <= *high
+                } else {
+                    (*low..=*high).contains(&found)
+                }
+            }
+            Query::Matches(field, pattern) => {
+                let options = MatchOptions {
+                    case_sensitive: false,
+                    ..MatchOptions::new()
+                };
+                let pattern = match Pattern::new(pattern) {
+                    Ok(pattern) => pattern,
+                    Err(_) => return false,
+                };
+                field
+                    .texts(palette, name, color)
+                    .iter()
+                    .any(|text| pattern.matches_with(text, options))
+            }
+        }
+    }
+
+    /// Names of the colours `contrast(...)` refers to, to check they exist.
+    fn targets(&self) -> Vec<&str> {
+        match self {
+            Query::And(left, right) | Query::Or(left, right) => {
+                let mut targets = left.targets();
+                targets.extend(right.targets());
+                targets
+            }
+            Query::Not(query) => query.targets(),
+            Query::Compare(Field::Contrast(Target::Name(name)), _, _)
+            | Query::InRange(Field::Contrast(Target::Name(name)), _, _) => vec![name.as_str()],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Rejects comparisons that can never hold, such as a name against a number.
+    fn check(&self) -> Result<(), String> {
+        match self {
+            Query::And(left, right) | Query::Or(left, right) => {
+                left.check()?;
+                right.check()
+            }
+            Query::Not(query) => query.check(),
+            Query::Compare(field, op, value) => match (field.is_numeric(), value) {
+                (true, Value::Number(_)) => Ok(()),
+                (true, _) => Err(format!("{} compares with a number", field)),
+                (false, Value::Number(_)) => {
+                    Err(format!("{} does not compare with a number", field))
+                }
+                (false, Value::Color(_)) if *field != Field::Hex => {
+                    Err(format!("{} does not compare with a hex colour", field))
+                }
+                (false, _) if matches!(op, Op::Eq | Op::Ne) => Ok(()),
+                (false, _) => Err(format!("{} only takes `=`, `!=` or `~`", field)),
+            },
+            Query::InRange(field, _, _) if !field.is_numeric() => {
+                Err(format!("{} has no range", field))
+            }
+            Query::Matches(field, pattern) => {
+                if field.is_numeric() {
+                    return Err(format!("{} has no pattern", field));
+                }
+                Pattern::new(pattern)
+                    .map(|_| ())
+                    .map_err(|error| format!("Bad pattern `{}`: {}", pattern, error))
+            }
+            Query::InRange(..) => Ok(()),
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parsed = delimited(multispace0, or, (multispace0, eof))
+            .parse(s)
+            .map_err(|error| {
+                let context = error.inner().to_string();
+                let column = error.offset() + 1;
+                match context.is_empty() {
+                    true => format!("unexpected input at column {}", column),
+                    false => format!("{} at column {}", context, column),
+                }
+            })?;
+        parsed.check()?;
+        Ok(parsed)
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Field::Name => write!(f, "name"),
+            Field::Group => write!(f, "group"),
+            Field::Role => write!(f, "role"),
+            Field::Hex => write!(f, "hex"),
+            Field::Lightness => write!(f, "lightness"),
+            Field::Chroma => write!(f, "chroma"),
+            Field::Hue => write!(f, "hue"),
+            Field::Luminance => write!(f, "luminance"),
+            Field::Alpha => write!(f, "alpha"),
+            Field::Red => write!(f, "red"),
+            Field::Green => write!(f, "green"),
+            Field::Blue => write!(f, "blue"),
+            Field::Contrast(Target::Color(color)) => write!(f, "contrast({})", color.hex()),
+            Field::Contrast(Target::Name(name)) => write!(f, "contrast({})", name),
+        }
+    }
+}
+
+impl Palette {
+    /// Names of the colours matching `query`, in palette order.
+    ///
+    /// Fails if `contrast(...)` names neither a colour nor a role the palette has.
+    pub fn select(&self, query: &Query) -> Result<Vec<&str>, String> {
+        for target in query.targets() {
+            if Target::Name(target.to_string()).resolve(self).is_none() {
+                return Err(format!("No colour or role `{}` in {}", target, self.name));
+            }
+        }
+        Ok(self
+            .colors
+            .iter()
+            .filter(|(name, color)| query.matches(self, name, color))
+            .map(|(name, _)| name.as_str())
+            .collect())
+    }
+
+    /// The palette with only the colours matching `query`, keeping their roles and
+    /// expressions. Its edit history starts afresh.
+    pub fn filtered(&self, query: &Query) -> Result<Palette, String> {
+        let selected = self
+            .select(query)?
+            .into_iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let mut palette = self.clone();
+        palette.colors.retain(|name, _| selected.contains(name));
+        palette
+            .expressions
+            .retain(|name, _| selected.contains(name));
+        palette.roles.retain(|_, name| selected.contains(name));
+        palette.history = History::default();
+        Ok(palette)
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+/// A keyword such as `and`, not followed by more of a word.
+fn keyword<'i>(word: &'static str) -> impl Parser<&'i str, (), winnow::error::ContextError> {
+    (Caseless(word), not(take_while(1, is_word_char))).void()
+}
+
+fn or(input: &mut &str) -> PResult<Query> {
+    let queries: Vec<Query> =
+        separated(1.., and, (multispace1, keyword("or"))).parse_next(input)?;
+    Ok(fold(queries, Query::Or))
+}
+
+fn and(input: &mut &str) -> PResult<Query> {
+    let queries: Vec<Query> =
+        separated(1.., negation, (multispace1, keyword("and"))).parse_next(input)?;
+    Ok(fold(queries, Query::And))
+}
+
+fn fold(queries: Vec<Query>, join: fn(Box<Query>, Box<Query>) -> Query) -> Query {
+    queries
+        .into_iter()
+        .reduce(|left, right| join(Box::new(left), Box::new(right)))
+        .expect("separated takes at least one")
+}
+
+fn negation(input: &mut &str) -> PResult<Query> {
+    preceded(
+        multispace0,
+        alt((
+            preceded(keyword("not"), negation).map(|query| Query::Not(Box::new(query))),
+            delimited('(', cut_err(or), cut_err((multispace0, ')'))),
+            comparison,
+        )),
+    )
+    .parse_next(input)
+}
+
+/// What follows the field in a comparison, with amounts as written until the field
+/// scales them.
+enum Condition {
+    InRange((f64, bool), (f64, bool)),
+    Matches(String),
+    CompareAmount(Op, (f64, bool)),
+    Compare(Op, Value),
+}
+
+fn comparison(input: &mut &str) -> PResult<Query> {
+    let field = field.parse_next(input)?;
+    let ws = multispace0;
+    let condition = cut_err(preceded(
+        ws,
+        alt((
+            preceded((keyword("in"), ws), range).map(|(low, high)| Condition::InRange(low, high)),
+            preceded(('~', ws), text).map(Condition::Matches),
+            (op, preceded(ws, amount)).map(|(op, amount)| Condition::CompareAmount(op, amount)),
+            (op, preceded(ws, value)).map(|(op, value)| Condition::Compare(op, value)),
+        )),
+    ))
+    .context(StrContext::Expected(StrContextValue::Description(
+        "a comparison, `in LOW..HIGH` or `~ PATTERN`",
+    )))
+    .parse_next(input)?;
+    Ok(match condition {
+        Condition::InRange(low, high) => {
+            let (low, high) = (field.scale(low), field.scale(high));
+            Query::InRange(field, low, high)
+        }
+        Condition::Matches(pattern) => Query::Matches(field, pattern),
+        Condition::CompareAmount(op, amount) => {
+            let number = field.scale(amount);
+            Query::Compare(field, op, Value::Number(number))
+        }
+        Condition::Compare(op, value) => Query::Compare(field, op, value),
+    })
+}
+
+fn field(input: &mut &str) -> PResult<Field> {
+    let word = take_while(1.., |c: char| c.is_ascii_alphabetic())
+        .context(StrContext::Expected(StrContextValue::Description(
+            "a field",
+        )))
+        .parse_next(input)?;
+    let field = match word.to_lowercase().as_str() {
+        "name" => Field::Name,
+        "group" => Field::Group,
+        "role" => Field::Role,
+        "hex" => Field::Hex,
+        "lightness" | "l" => Field::Lightness,
+        "chroma" | "c" => Field::Chroma,
+        "hue" | "h" => Field::Hue,
+        "luminance" => Field::Luminance,
+        "alpha" | "opacity" => Field::Alpha,
+        "red" => Field::Red,
+        "green" => Field::Green,
+        "blue" => Field::Blue,
+        "contrast" => {
+            let target = cut_err(delimited(
+                (multispace0, '(', multispace0),
+                alt((hex.map(Target::Color), text.map(Target::Name))),
+                (multispace0, ')'),
+            ))
+            .context(StrContext::Expected(StrContextValue::Description(
+                "`(` a colour, role or hex colour `)`",
+            )))
+            .parse_next(input)?;
+            Field::Contrast(target)
+        }
+        _ => {
+            return cut_err(winnow::combinator::fail)
+                .context(StrContext::Expected(StrContextValue::Description(
+                    "a field such as `name`, `hue` or `contrast(bg)`",
+                )))
+                .parse_next(input)
+        }
+    };
+    Ok(field)
+}
+
+fn op(input: &mut &str) -> PResult<Op> {
+    alt((
+        "<=".value(Op::Le),
+        ">=".value(Op::Ge),
+        "!=".value(Op::Ne),
+        "==".value(Op::Eq),
+        "<".value(Op::Lt),
+        ">".value(Op::Gt),
+        "=".value(Op::Eq),
+    ))
+    .parse_next(input)
+}
+
+fn value(input: &mut &str) -> PResult<Value> {
+    alt((hex.map(Value::Color), text.map(Value::Text))).parse_next(input)
+}
+
+fn range(input: &mut &str) -> PResult<((f64, bool), (f64, bool))> {
+    (terminated(amount, (multispace0, "..", multispace0)), amount).parse_next(input)
+}
+
+/// A number and whether it is a percentage, with `.` only before a digit so that
+/// `180..240` is a range.
+fn amount(input: &mut &str) -> PResult<(f64, bool)> {
+    let digits = (opt('-'), digit1, opt(('.', digit1)))
+        .take()
+        .parse_to::<f64>();
+    (digits, opt(preceded(multispace0, '%')))
+        .map(|(value, percent)| (value, percent.is_some()))
+        .parse_next(input)
+}
+
+fn hex(input: &mut &str) -> PResult<Color> {
+    preceded(
+        '#',
+        cut_err(
+            take_while(1.., |c: char| c.is_ascii_hexdigit())
+                .verify(|digits: &str| matches!(digits.len(), 3 | 6 | 8)),
+        )
+        .context(StrContext::Expected(StrContextValue::Description(
+            "3, 6 or 8 hex digits",
+        ))),
+    )
+    .map(Color::from_hex)
+    .parse_next(input)
+}
+
+/// A quoted string or a bare word.
+fn text(input: &mut &str) -> PResult<String> {
+    alt((
+        delimited('"', take_while(0.., |c| c != '"'), cut_err('"')),
+        take_while(1.., |c: char| is_word_char(c) || c == '*' || c == '?'),
+    ))
+    .map(str::to_string)
+    .parse_next(input)
+}
+
+#[cfg(test)]
+mod query_tests {
+    use super::*;
+    use crate::testing;
+
+    fn palette() -> Palette {
+        testing::palette(
+            "Query",
+            &[
+                ("background", "#1E1E2E"),
+                ("foreground", "#CDD6F4"),
+                ("color1", "#F38BA8"),
+                ("color4", "#89B4FA"),
+                ("color12", "#74C7EC"),
+                ("comment", "#45475A"),
+                ("ui.border", "#585B70"),
+                ("ui.accent", "#CBA6F7"),
+                ("overlay", "#6C708680"),
+            ],
+        )
+    }
+
+    fn select(query: &str) -> Vec<String> {
+        let palette = palette();
+        let query = query.parse::<Query>().unwrap();
+        palette
+            .select(&query)
+            .unwrap()
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn examples() {
+        assert_eq!(
+            select("lightness > 0.6 and hue in 180..240"),
+            vec!["color12"]
+        );
+        assert_eq!(select("name ~ \"color1*\""), vec!["color1", "color12"]);
+        assert_eq!(select("role = accent"), vec!["ui.accent"]);
+        assert_eq!(
+            select("contrast(bg) < 4.5"),
+            vec!["background", "comment", "ui.border", "overlay"]
+        );
+    }
+
+    #[test]
+    fn combinators() {
+        assert_eq!(select("group = ui"), vec!["ui.border", "ui.accent"]);
+        assert_eq!(
+            select("NOT (group = ui OR chroma < 5%) and alpha = 1"),
+            vec!["color1", "color4", "color12"]
+        );
+        assert_eq!(select("hue in 330..30"), vec!["color1"]);
+        assert_eq!(
+            select("hex = #f38ba8 or hex ~ \"*80\""),
+            vec!["color1", "overlay"]
+        );
+        assert_eq!(
+            select("role != background and name ~ c*r*"),
+            vec!["color1", "color4", "color12"]
+        );
+        assert_eq!(select("contrast(#FFF) >= 12"), vec!["background"]);
+        assert_eq!(select("red > 90% and blue in 50%..70%"), vec!["color1"]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            "lightness >".parse::<Query>(),
+            Err("expected a comparison, `in LOW..HIGH` or `~ PATTERN` at column 12".to_string())
+        );
+        assert!("colour = red".parse::<Query>().is_err());
+        assert!("name > 3".parse::<Query>().is_err());
+        assert!("hue = red".parse::<Query>().is_err());
+        assert!("hue ~ \"1*\"".parse::<Query>().is_err());
+        assert_eq!(
+            "name = #FFF".parse::<Query>(),
+            Err("name does not compare with a hex colour".to_string())
+        );
+        assert!("name = a andd name = b".parse::<Query>().is_err());
+        assert!("(name = a".parse::<Query>().is_err());
+        let query = "contrast(missing) < 3".parse::<Query>().unwrap();
+        assert_eq!(
+            palette().select(&query),
+            Err("No colour or role `missing` in Query".to_string())
+        );
+    }
+
+    #[test]
+    fn filtered() {
+        let mut palette = palette();
+        palette.assign_role(ColorRole::Accent, "ui.accent");
+        let query = "group = ui".parse::<Query>().unwrap();
+        let filtered = palette.filtered(&query).unwrap();
+        assert_eq!(filtered.color_names(), vec!["ui.border", "ui.accent"]);
+        assert_eq!(filtered.role_name(ColorRole::Accent), Some("ui.accent"));
+        assert_eq!(filtered.uuid, palette.uuid);
+    }
+}
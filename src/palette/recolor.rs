@@ -0,0 +1,451 @@
+use std::{fmt, str::FromStr};
+
+use serde_json::{json, Value};
+
+use super::{ColorRole, Palette};
+use crate::color::Color;
+use crate::config::writer::color_to_string;
+
+/// How [`Palette::recolor_map`] picks the counterpart of a colour that no role matches.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Assignment {
+    /// Each colour takes its nearest counterpart, which several colours may share.
+    #[default]
+    Nearest,
+    /// Each counterpart is taken at most once, keeping the total ΔE as small as possible.
+    /// Colours left over once every counterpart is taken fall back to their nearest.
+    OneToOne,
+}
+
+impl FromStr for Assignment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "nearest" => Ok(Assignment::Nearest),
+            "one-to-one" | "hungarian" => Ok(Assignment::OneToOne),
+            other => Err(format!("Unknown assignment: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for Assignment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Assignment::Nearest => write!(f, "nearest"),
+            Assignment::OneToOne => write!(f, "one-to-one"),
+        }
+    }
+}
+
+/// A colour and the counterpart it maps onto.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RecolorEntry {
+    pub from: String,
+    pub from_color: Color,
+    pub to: String,
+    pub to_color: Color,
+    /// CIEDE2000 difference between the two colours.
+    pub delta_e: f64,
+    /// The role both colours fill, if they were matched by it rather than by ΔE.
+    pub role: Option<ColorRole>,
+}
+
+/// Where each colour of one palette goes in another, from [`Palette::recolor_map`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct RecolorMap {
+    pub from: String,
+    pub to: String,
+    pub assignment: Assignment,
+    /// One entry per colour of the source palette, in its order.
+    pub entries: Vec<RecolorEntry>,
+}
+
+impl RecolorMap {
+    /// Mean ΔE over every entry.
+    pub fn mean_delta_e(&self) -> f64 {
+        match self.entries.len() {
+            0 => 0.0,
+            count => self.entries.iter().map(|entry| entry.delta_e).sum::<f64>() / count as f64,
+        }
+    }
+
+    /// Aligned table of the mapping, one colour per line.
+    pub fn to_table(&self) -> String {
+        let rows = self
+            .entries
+            .iter()
+            .map(|entry| {
+                [
+                    entry.from.clone(),
+                    color_to_string(&entry.from_color),
+                    entry.to.clone(),
+                    color_to_string(&entry.to_color),
+                    format!("{:.2}", entry.delta_e),
+                    entry.role.map(|role| role.to_string()).unwrap_or_default(),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let header = ["from", "", "to", "", "ΔE", "role"].map(String::from);
+        let mut widths = [0; 6];
+        for row in rows.iter().chain([&header]) {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let mut table = format!(
+            "{} -> {} ({}): mean ΔE {:.2}\n",
+            self.from,
+            self.to,
+            self.assignment,
+            self.mean_delta_e()
+        );
+        for row in [&header].into_iter().chain(&rows) {
+            let line = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ");
+            table.push_str(line.trim_end());
+            table.push('\n');
+        }
+        table
+    }
+
+    pub fn to_json(&self) -> Value {
+        let entries = self
+            .entries
+            .iter()
+            .map(|entry| {
+                json!({
+                    "from": entry.from,
+                    "from_color": color_to_string(&entry.from_color),
+                    "to": entry.to,
+                    "to_color": color_to_string(&entry.to_color),
+                    "delta_e": (entry.delta_e * 100.0).round() / 100.0,
+                    "role": entry.role.map(|role| role.to_string()),
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({
+            "from": self.from,
+            "to": self.to,
+            "assignment": self.assignment.to_string(),
+            "mapping": entries,
+        })
+    }
+
+    /// The mapping as CSV with a header row, empty `role` where matched by ΔE.
+    pub fn to_csv(&self) -> String {
+        let mut csv = "from,from_color,to,to_color,delta_e,role\n".to_string();
+        for entry in &self.entries {
+            let row = [
+                csv_field(&entry.from),
+                color_to_string(&entry.from_color),
+                csv_field(&entry.to),
+                color_to_string(&entry.to_color),
+                format!("{:.2}", entry.delta_e),
+                entry.role.map(|role| role.to_string()).unwrap_or_default(),
+            ];
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// Quotes a CSV field that holds a comma, quote or line break.
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+impl Palette {
+    /// Maps every colour of this palette onto its counterpart in `target`.
+    ///
+    /// A colour filling a role that `target` also fills maps onto the colour filling it
+    /// there; the others are matched by ΔE according to `assignment`. Counterparts taken
+    /// by a role are not offered again for a one-to-one assignment.
+    pub fn recolor_map(&self, target: &Palette, assignment: Assignment) -> RecolorMap {
+        let roles = self.roles_by_name();
+        let mut entries = vec![None; self.colors.len()];
+        for (entry, name) in entries.iter_mut().zip(self.colors.keys()) {
+            *entry = roles
+                .get(name.as_str())
+                .into_iter()
+                .flatten()
+                .find_map(|role| {
+                    let to = target.role_name(*role)?;
+                    Some((to.to_string(), Some(*role)))
+                });
+        }
+
+        let candidates = target
+            .colors
+            .keys()
+            .filter(|name| {
+                assignment == Assignment::Nearest
+                    || !entries.iter().flatten().any(|(to, _)| to == *name)
+            })
+            .collect::<Vec<_>>();
+        let unmatched = entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.is_none())
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        let colors = self.colors.values().collect::<Vec<_>>();
+        let costs = unmatched
+            .iter()
+            .map(|index| {
+                candidates
+                    .iter()
+                    .map(|name| colors[*index].delta_e(&target.colors[*name]))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        if assignment == Assignment::OneToOne && !candidates.is_empty() {
+            for (row, column) in assign(&costs).into_iter().enumerate() {
+                if let Some(column) = column {
+                    entries[unmatched[row]] = Some((candidates[column].clone(), None));
+                }
+            }
+        }
+        for (row, index) in unmatched.iter().enumerate() {
+            if entries[*index].is_some() {
+                continue;
+            }
+            entries[*index] = nearest(&costs[row]).map(|column| (candidates[column].clone(), None));
+        }
+        // Colours with no candidate left, every counterpart being taken by a role.
+        for (entry, color) in entries.iter_mut().zip(&colors) {
+            if entry.is_none() {
+                *entry = target
+                    .colors
+                    .iter()
+                    .min_by(|(_, a), (_, b)| color.delta_e(a).total_cmp(&color.delta_e(b)))
+                    .map(|(name, _)| (name.clone(), None));
+            }
+        }
+
+        let entries = self
+            .colors
+            .iter()
+            .zip(entries)
+            .filter_map(|((from, from_color), entry)| {
+                let (to, role) = entry?;
+                let to_color = target.colors[&to];
+                Some(RecolorEntry {
+                    from: from.clone(),
+                    from_color: *from_color,
+                    delta_e: from_color.delta_e(&to_color),
+                    to,
+                    to_color,
+                    role,
+                })
+            })
+            .collect();
+        RecolorMap {
+            from: self.name.clone(),
+            to: target.name.clone(),
+            assignment,
+            entries,
+        }
+    }
+}
+
+fn nearest(costs: &[f64]) -> Option<usize> {
+    (0..costs.len()).min_by(|a, b| costs[*a].total_cmp(&costs[*b]))
+}
+
+/// Assigns each row of `costs` a distinct column so the total cost is as small as
+/// possible, by the Hungarian method. Rows left without a column, when there are more
+/// rows than columns, get `None`.
+fn assign(costs: &[Vec<f64>]) -> Vec<Option<usize>> {
+    let rows = costs.len();
+    let columns = costs.first().map_or(0, Vec::len);
+    if rows > columns {
+        let transposed = (0..columns)
+            .map(|column| costs.iter().map(|row| row[column]).collect())
+            .collect::<Vec<Vec<f64>>>();
+        let mut assigned = vec![None; rows];
+        for (column, row) in assign(&transposed).into_iter().enumerate() {
+            if let Some(row) = row {
+                assigned[row] = Some(column);
+            }
+        }
+        return assigned;
+    }
+
+    // Potentials over rows and columns, 1-based with column 0 as the free slot.
+    let mut row_potential = vec![0.0; rows + 1];
+    let mut column_potential = vec![0.0; columns + 1];
+    let mut row_of = vec![0; columns + 1];
+    let mut way = vec![0; columns + 1];
+    for row in 1..=rows {
+        row_of[0] = row;
+        let mut column = 0;
+        let mut min_slack = vec![f64::INFINITY; columns + 1];
+        let mut used = vec![false; columns + 1];
+        loop {
+            used[column] = true;
+            let current = row_of[column];
+            let mut delta = f64::INFINITY;
+            let mut next = 0;
+            for candidate in 1..=columns {
+                if used[candidate] {
+                    continue;
+                }
+                let slack = costs[current - 1][candidate - 1]
+                    - row_potential[current]
+                    - column_potential[candidate];
+                if slack < min_slack[candidate] {
+                    min_slack[candidate] = slack;
+                    way[candidate] = column;
+                }
+                if min_slack[candidate] < delta {
+                    delta = min_slack[candidate];
+                    next = candidate;
+                }
+            }
+            for candidate in 0..=columns {
+                if used[candidate] {
+                    row_potential[row_of[candidate]] += delta;
+                    column_potential[candidate] -= delta;
+                } else {
+                    min_slack[candidate] -= delta;
+                }
+            }
+            column = next;
+            if row_of[column] == 0 {
+                break;
+            }
+        }
+        while column != 0 {
+            let previous = way[column];
+            row_of[column] = row_of[previous];
+            column = previous;
+        }
+    }
+    let mut assigned = vec![None; rows];
+    for column in 1..=columns {
+        if row_of[column] != 0 {
+            assigned[row_of[column] - 1] = Some(column - 1);
+        }
+    }
+    assigned
+}
+
+#[cfg(test)]
+mod recolor_tests {
+    use super::*;
+    use crate::testing;
+
+    fn palette(name: &str, colors: &[(&str, &str)]) -> Palette {
+        testing::palette(name, colors)
+    }
+
+    #[test]
+    fn hungarian() {
+        let costs = vec![
+            vec![4.0, 1.0, 3.0],
+            vec![2.0, 0.0, 5.0],
+            vec![3.0, 2.0, 2.0],
+        ];
+        assert_eq!(assign(&costs), vec![Some(1), Some(0), Some(2)]);
+        let wide = vec![vec![5.0, 1.0, 9.0], vec![1.0, 2.0, 9.0]];
+        assert_eq!(assign(&wide), vec![Some(1), Some(0)]);
+        let tall = vec![vec![1.0], vec![0.5], vec![3.0]];
+        assert_eq!(assign(&tall), vec![None, Some(0), None]);
+    }
+
+    #[test]
+    fn nearest_and_one_to_one() {
+        let from = palette(
+            "From",
+            &[
+                ("crimson", "#D01030"),
+                ("scarlet", "#F02010"),
+                ("sky", "#3090F0"),
+            ],
+        );
+        let to = palette(
+            "To",
+            &[
+                ("red", "#E01020"),
+                ("orange", "#F08020"),
+                ("blue", "#2080E0"),
+            ],
+        );
+
+        let map = from.recolor_map(&to, Assignment::Nearest);
+        let targets = |map: &RecolorMap| {
+            map.entries
+                .iter()
+                .map(|entry| entry.to.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(targets(&map), vec!["red", "red", "blue"]);
+        assert!(map.entries.iter().all(|entry| entry.role.is_none()));
+
+        let map = from.recolor_map(&to, Assignment::OneToOne);
+        assert_eq!(targets(&map), vec!["red", "orange", "blue"]);
+
+        let few = palette("Few", &[("only", "#E01020")]);
+        let map = from.recolor_map(&few, Assignment::OneToOne);
+        assert_eq!(targets(&map), vec!["only", "only", "only"]);
+        assert!(from
+            .recolor_map(&Palette::new("Empty", None), Assignment::OneToOne)
+            .entries
+            .is_empty());
+    }
+
+    #[test]
+    fn roles_take_precedence() {
+        let from = palette("From", &[("bg", "#101010"), ("accent", "#F0F0F0")]);
+        let mut to = palette("To", &[("base", "#F8F8F8"), ("ink", "#080808")]);
+        to.assign_role(ColorRole::Background, "base");
+
+        let map = from.recolor_map(&to, Assignment::OneToOne);
+        let [bg, accent] = [&map.entries[0], &map.entries[1]];
+        assert_eq!((bg.from.as_str(), bg.to.as_str()), ("bg", "base"));
+        assert_eq!(bg.role, Some(ColorRole::Background));
+        assert_eq!(
+            (accent.from.as_str(), accent.to.as_str()),
+            ("accent", "ink")
+        );
+        assert_eq!(accent.role, None);
+
+        let map = from.recolor_map(&to, Assignment::Nearest);
+        assert_eq!(map.entries[1].to, "base");
+    }
+
+    #[test]
+    fn renderings() {
+        let from = palette("From", &[("a,b", "#000000"), ("c", "#FFFFFF")]);
+        let to = palette("To", &[("black", "#000000"), ("white", "#FEFEFE")]);
+        let map = from.recolor_map(&to, Assignment::Nearest);
+        assert_eq!(
+            map.to_csv(),
+            "from,from_color,to,to_color,delta_e,role
+\"a,b\",#000000,black,#000000,0.00,
+c,#FFFFFF,white,#FEFEFE,0.20,
+"
+        );
+        assert_eq!(
+            map.to_table(),
+            "From -> To (nearest): mean ΔE 0.10
+from           to              ΔE    role
+a,b   #000000  black  #000000  0.00
+c     #FFFFFF  white  #FEFEFE  0.20
+"
+        );
+        assert_eq!(
+            map.to_json()["mapping"][1],
+            json!({"from": "c", "from_color": "#FFFFFF", "to": "white", "to_color": "#FEFEFE", "delta_e": 0.2, "role": null})
+        );
+    }
+}
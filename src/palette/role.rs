@@ -0,0 +1,282 @@
+use std::{fmt, str::FromStr, sync::OnceLock};
+
+use config::Config;
+use indexmap::IndexMap;
+
+/// Name of the reserved table assigning roles to colours, e.g. `["My Theme".roles]`.
+pub const ROLES_KEY: &str = "roles";
+
+/// Name of the settings table extending the default role aliases.
+pub const ROLES_SETTINGS_KEY: &str = "Roles";
+
+static ALIASES: OnceLock<RoleAliases> = OnceLock::new();
+
+/// What a colour is used for, independent of what it is called.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub enum ColorRole {
+    Background,
+    Foreground,
+    Cursor,
+    CursorText,
+    SelectionBackground,
+    SelectionForeground,
+    /// ANSI colours 0-15; 8-15 are the bright variants.
+    Ansi(u8),
+    Accent,
+    Error,
+    Warning,
+    Success,
+    Info,
+    Link,
+}
+
+pub const ANSI_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+impl ColorRole {
+    /// Every role, in display order.
+    pub fn all() -> Vec<ColorRole> {
+        let mut roles = vec![
+            ColorRole::Background,
+            ColorRole::Foreground,
+            ColorRole::Cursor,
+            ColorRole::CursorText,
+            ColorRole::SelectionBackground,
+            ColorRole::SelectionForeground,
+        ];
+        roles.extend((0..16).map(ColorRole::Ansi));
+        roles.extend([
+            ColorRole::Accent,
+            ColorRole::Error,
+            ColorRole::Warning,
+            ColorRole::Success,
+            ColorRole::Info,
+            ColorRole::Link,
+        ]);
+        roles
+    }
+
+    /// Names a colour can have to take this role when none is assigned explicitly.
+    pub fn default_aliases(&self) -> Vec<String> {
+        let aliases: Vec<String> = match self {
+            ColorRole::Background => vec!["background".into(), "bg".into()],
+            ColorRole::Foreground => vec!["foreground".into(), "fg".into(), "text".into()],
+            ColorRole::Cursor => vec!["cursor".into(), "cursor-color".into()],
+            ColorRole::CursorText => vec!["cursor-text".into(), "cursor-foreground".into()],
+            ColorRole::SelectionBackground => vec![
+                "selection-background".into(),
+                "selection-bg".into(),
+                "selection".into(),
+            ],
+            ColorRole::SelectionForeground => {
+                vec!["selection-foreground".into(), "selection-fg".into()]
+            }
+            ColorRole::Ansi(index) => {
+                let base = ANSI_NAMES[*index as usize % 8];
+                let mut aliases = vec![format!("color{}", index), format!("ansi{}", index)];
+                if *index < 8 {
+                    aliases.push(base.to_string());
+                } else {
+                    aliases.push(format!("bright-{}", base));
+                    aliases.push(format!("light-{}", base));
+                }
+                aliases
+            }
+            ColorRole::Accent => vec!["accent".into(), "primary".into()],
+            ColorRole::Error => vec!["error".into(), "danger".into()],
+            ColorRole::Warning => vec!["warning".into(), "warn".into()],
+            ColorRole::Success => vec!["success".into()],
+            ColorRole::Info => vec!["info".into()],
+            ColorRole::Link => vec!["link".into(), "url".into()],
+        };
+        aliases
+    }
+}
+
+impl FromStr for ColorRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = normalize_name(s);
+        if let Some(index) = name.strip_prefix("ansi") {
+            return match index.parse::<u8>() {
+                Ok(index) if index < 16 => Ok(ColorRole::Ansi(index)),
+                _ => Err(format!("Unknown colour role: {}", s)),
+            };
+        }
+        ColorRole::all()
+            .into_iter()
+            .find(|role| normalize_name(&role.to_string()) == name)
+            .or(match name.as_str() {
+                "bg" => Some(ColorRole::Background),
+                "fg" => Some(ColorRole::Foreground),
+                "selectionbg" => Some(ColorRole::SelectionBackground),
+                "selectionfg" => Some(ColorRole::SelectionForeground),
+                _ => None,
+            })
+            .ok_or_else(|| format!("Unknown colour role: {}", s))
+    }
+}
+
+impl fmt::Display for ColorRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorRole::Background => write!(f, "background"),
+            ColorRole::Foreground => write!(f, "foreground"),
+            ColorRole::Cursor => write!(f, "cursor"),
+            ColorRole::CursorText => write!(f, "cursor-text"),
+            ColorRole::SelectionBackground => write!(f, "selection-background"),
+            ColorRole::SelectionForeground => write!(f, "selection-foreground"),
+            ColorRole::Ansi(index) => write!(f, "ansi{}", index),
+            ColorRole::Accent => write!(f, "accent"),
+            ColorRole::Error => write!(f, "error"),
+            ColorRole::Warning => write!(f, "warning"),
+            ColorRole::Success => write!(f, "success"),
+            ColorRole::Info => write!(f, "info"),
+            ColorRole::Link => write!(f, "link"),
+        }
+    }
+}
+
+/// Lowercase alphanumeric form of a colour name, so `Bright_Black` matches `bright-black`.
+pub fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Colour names that take each role, most preferred first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoleAliases {
+    aliases: IndexMap<ColorRole, Vec<String>>,
+}
+
+impl Default for RoleAliases {
+    fn default() -> Self {
+        Self {
+            aliases: ColorRole::all()
+                .into_iter()
+                .map(|role| {
+                    let aliases = role.default_aliases();
+                    (role, aliases.iter().map(|a| normalize_name(a)).collect())
+                })
+                .collect(),
+        }
+    }
+}
+
+impl RoleAliases {
+    /// The defaults extended by the `[Roles]` table of the program settings.
+    ///
+    /// Each key is a role and each value an alias or list of aliases, which take
+    /// priority over the defaults. Unknown roles are ignored.
+    pub fn from_settings(settings: &Config) -> Self {
+        let mut aliases = Self::default();
+        let table = match settings.get_table(ROLES_SETTINGS_KEY) {
+            Ok(table) => table,
+            Err(_) => return aliases,
+        };
+        for (role, value) in table {
+            let role = match role.parse::<ColorRole>() {
+                Ok(role) => role,
+                Err(_) => continue,
+            };
+            let names = match value.clone().into_array() {
+                Ok(names) => names
+                    .into_iter()
+                    .filter_map(|name| name.into_string().ok())
+                    .collect(),
+                Err(_) => value.into_string().into_iter().collect::<Vec<String>>(),
+            };
+            aliases.prepend(role, &names);
+        }
+        aliases
+    }
+
+    /// Adds aliases for `role` ahead of the existing ones.
+    pub fn prepend(&mut self, role: ColorRole, names: &[String]) {
+        let entry = self.aliases.entry(role).or_default();
+        let mut names = names
+            .iter()
+            .map(|name| normalize_name(name))
+            .collect::<Vec<String>>();
+        entry.retain(|alias| !names.contains(alias));
+        names.append(entry);
+        *entry = names;
+    }
+
+    /// Normalised aliases for `role`, most preferred first.
+    pub fn aliases(&self, role: ColorRole) -> &[String] {
+        self.aliases.get(&role).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Makes these aliases the ones used by [`RoleAliases::global`].
+    /// Returns `false` if the global aliases were already in use.
+    pub fn install(self) -> bool {
+        ALIASES.set(self).is_ok()
+    }
+
+    /// The installed aliases, or the defaults if none were installed.
+    pub fn global() -> &'static RoleAliases {
+        ALIASES.get_or_init(RoleAliases::default)
+    }
+}
+
+#[cfg(test)]
+mod role_tests {
+    use super::*;
+    use config::{File, FileFormat};
+
+    /// Every role `name` is an alias of.
+    fn roles_of(aliases: &RoleAliases, name: &str) -> Vec<ColorRole> {
+        let name = normalize_name(name);
+        ColorRole::all()
+            .into_iter()
+            .filter(|role| aliases.aliases(*role).contains(&name))
+            .collect()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        for role in ColorRole::all() {
+            assert_eq!(role.to_string().parse::<ColorRole>(), Ok(role));
+        }
+        assert_eq!("Selection_BG".parse(), Ok(ColorRole::SelectionBackground));
+        assert_eq!("ansi 12".parse(), Ok(ColorRole::Ansi(12)));
+        assert!("ansi16".parse::<ColorRole>().is_err());
+        assert!("border".parse::<ColorRole>().is_err());
+    }
+
+    #[test]
+    fn default_aliases() {
+        let aliases = RoleAliases::default();
+        assert_eq!(roles_of(&aliases, "BG"), [ColorRole::Background]);
+        assert_eq!(roles_of(&aliases, " Text "), [ColorRole::Foreground]);
+        assert_eq!(roles_of(&aliases, "color9"), [ColorRole::Ansi(9)]);
+        assert_eq!(roles_of(&aliases, "Bright_Red"), [ColorRole::Ansi(9)]);
+        assert_eq!(roles_of(&aliases, "red"), [ColorRole::Ansi(1)]);
+        assert_eq!(roles_of(&aliases, "border"), []);
+    }
+
+    #[test]
+    fn aliases_from_settings() {
+        let settings = Config::builder()
+            .add_source(File::from_str(
+                "[Roles]\nbackground = [\"base00\", \"bg\"]\naccent = \"blue\"\nnot-a-role = \"x\"",
+                FileFormat::Toml,
+            ))
+            .build()
+            .unwrap();
+        let aliases = RoleAliases::from_settings(&settings);
+        assert_eq!(
+            aliases.aliases(ColorRole::Background),
+            ["base00", "bg", "background"]
+        );
+        assert_eq!(
+            roles_of(&aliases, "blue"),
+            [ColorRole::Ansi(4), ColorRole::Accent]
+        );
+    }
+}
@@ -0,0 +1,305 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use super::{history::Edit, natural_cmp, stats::ACHROMATIC_CHROMA, Palette};
+use crate::color::Color;
+
+/// Bits per Oklab axis of the Hilbert curve.
+const HILBERT_BITS: u32 = 10;
+
+/// An order [`Palette::sorted`] can put colours in.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SortOrder {
+    /// Around the Oklch hue circle from red, greys last from dark to light.
+    Hue,
+    /// Oklab lightness, dark to light.
+    Lightness,
+    /// WCAG relative luminance, dark to light.
+    Luminance,
+    /// Oklch chroma, grey to vivid.
+    Chroma,
+    /// Name, with digit runs compared numerically.
+    Name,
+    /// Along a Hilbert curve through Oklab, keeping similar colours together.
+    Hilbert,
+    /// From the darkest colour, each next colour the nearest in Oklab not yet visited.
+    NearestNeighbor,
+}
+
+impl SortOrder {
+    pub fn all() -> [SortOrder; 7] {
+        [
+            SortOrder::Hue,
+            SortOrder::Lightness,
+            SortOrder::Luminance,
+            SortOrder::Chroma,
+            SortOrder::Name,
+            SortOrder::Hilbert,
+            SortOrder::NearestNeighbor,
+        ]
+    }
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "hue" => Ok(SortOrder::Hue),
+            "lightness" => Ok(SortOrder::Lightness),
+            "luminance" => Ok(SortOrder::Luminance),
+            "chroma" => Ok(SortOrder::Chroma),
+            "name" => Ok(SortOrder::Name),
+            "hilbert" => Ok(SortOrder::Hilbert),
+            "nearest" | "nearest-neighbor" => Ok(SortOrder::NearestNeighbor),
+            other => Err(format!("Unknown sort order: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortOrder::Hue => write!(f, "hue"),
+            SortOrder::Lightness => write!(f, "lightness"),
+            SortOrder::Luminance => write!(f, "luminance"),
+            SortOrder::Chroma => write!(f, "chroma"),
+            SortOrder::Name => write!(f, "name"),
+            SortOrder::Hilbert => write!(f, "hilbert"),
+            SortOrder::NearestNeighbor => write!(f, "nearest-neighbor"),
+        }
+    }
+}
+
+impl Palette {
+    /// The colour names in `order`. Colours that compare equal keep their palette order.
+    pub fn sorted_names(&self, order: SortOrder) -> Vec<&str> {
+        let mut colors = self
+            .colors
+            .iter()
+            .map(|(name, color)| (name.as_str(), color.to_opaque()))
+            .collect::<Vec<_>>();
+        let by = |key: fn(&Color) -> f64| {
+            move |a: &(&str, Color), b: &(&str, Color)| key(&a.1).total_cmp(&key(&b.1))
+        };
+        match order {
+            SortOrder::Hue => colors.sort_by(|(_, a), (_, b)| hue_cmp(a, b)),
+            SortOrder::Lightness => colors.sort_by(by(|color| color.oklab().0)),
+            SortOrder::Luminance => colors.sort_by(by(Color::relative_luminance)),
+            SortOrder::Chroma => colors.sort_by(by(|color| color.oklch().1)),
+            SortOrder::Name => colors.sort_by(|(a, _), (b, _)| natural_cmp(a, b)),
+            SortOrder::Hilbert => colors.sort_by_cached_key(|(_, color)| hilbert_key(color)),
+            SortOrder::NearestNeighbor => colors = nearest_neighbor_path(colors),
+        }
+        colors.into_iter().map(|(name, _)| name).collect()
+    }
+
+    /// A copy of the palette with its colours in `order`, to show them sorted without
+    /// changing the palette. The copy has no edit history.
+    pub fn sorted(&self, order: SortOrder) -> Palette {
+        let mut palette = self.clone();
+        let names = self.sorted_names(order);
+        palette
+            .colors
+            .sort_by_cached_key(|name, _| names.iter().position(|sorted| sorted == name));
+        palette.history = Default::default();
+        palette
+    }
+
+    /// Reorders the palette's colours into `order`, undoable as one step.
+    pub fn sort_colors(&mut self, order: SortOrder) {
+        let names = self
+            .sorted_names(order)
+            .into_iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        self.begin_edit_group();
+        for (index, name) in names.into_iter().enumerate() {
+            if self.index_of(&name) != Some(index) {
+                self.apply(Edit::Move { name, index })
+                    .expect("sorted names are in the palette");
+            }
+        }
+        self.end_edit_group();
+    }
+}
+
+fn hue_cmp(a: &Color, b: &Color) -> Ordering {
+    let (a_lightness, a_chroma, a_hue) = a.oklch();
+    let (b_lightness, b_chroma, b_hue) = b.oklch();
+    let a_grey = a_chroma < ACHROMATIC_CHROMA;
+    let b_grey = b_chroma < ACHROMATIC_CHROMA;
+    a_grey
+        .cmp(&b_grey)
+        .then_with(|| match a_grey {
+            true => Ordering::Equal,
+            false => a_hue.total_cmp(&b_hue),
+        })
+        .then_with(|| a_lightness.total_cmp(&b_lightness))
+}
+
+/// Position of the colour along a Hilbert curve filling the Oklab cube, lightness
+/// 0.0-1.0 and a and b -0.4-0.4.
+fn hilbert_key(color: &Color) -> u64 {
+    let (l, a, b) = color.oklab();
+    let max = (1u32 << HILBERT_BITS) - 1;
+    let quantize = |value: f64| (value.clamp(0.0, 1.0) * max as f64).round() as u32;
+    hilbert_index(
+        [
+            quantize(l),
+            quantize(a / 0.8 + 0.5),
+            quantize(b / 0.8 + 0.5),
+        ],
+        HILBERT_BITS,
+    )
+}
+
+/// Index along a 3D Hilbert curve of the point `axes`, each coordinate `bits` wide, by
+/// Skilling's transpose method.
+fn hilbert_index(mut axes: [u32; 3], bits: u32) -> u64 {
+    let top = 1u32 << (bits - 1);
+    let mut q = top;
+    while q > 1 {
+        let p = q - 1;
+        for i in 0..3 {
+            if axes[i] & q != 0 {
+                axes[0] ^= p;
+            } else {
+                let t = (axes[0] ^ axes[i]) & p;
+                axes[0] ^= t;
+                axes[i] ^= t;
+            }
+        }
+        q >>= 1;
+    }
+    for i in 1..3 {
+        axes[i] ^= axes[i - 1];
+    }
+    let mut t = 0;
+    let mut q = top;
+    while q > 1 {
+        if axes[2] & q != 0 {
+            t ^= q - 1;
+        }
+        q >>= 1;
+    }
+    for axis in &mut axes {
+        *axis ^= t;
+    }
+    let mut index = 0u64;
+    for bit in (0..bits).rev() {
+        for axis in axes {
+            index = (index << 1) | ((axis >> bit) & 1) as u64;
+        }
+    }
+    index
+}
+
+/// The colours from the darkest, each followed by the nearest one left in Oklab.
+fn nearest_neighbor_path(mut colors: Vec<(&str, Color)>) -> Vec<(&str, Color)> {
+    let mut path = Vec::with_capacity(colors.len());
+    let darkest =
+        (0..colors.len()).min_by(|a, b| colors[*a].1.oklab().0.total_cmp(&colors[*b].1.oklab().0));
+    let mut current = match darkest {
+        Some(index) => colors.remove(index),
+        None => return path,
+    };
+    while !colors.is_empty() {
+        let next = (0..colors.len())
+            .min_by(|a, b| {
+                current
+                    .1
+                    .delta_e_ok(&colors[*a].1)
+                    .total_cmp(&current.1.delta_e_ok(&colors[*b].1))
+            })
+            .expect("colours left");
+        path.push(std::mem::replace(&mut current, colors.remove(next)));
+    }
+    path.push(current);
+    path
+}
+
+#[cfg(test)]
+mod sort_tests {
+    use super::*;
+    use crate::testing;
+
+    fn palette() -> Palette {
+        testing::palette(
+            "Sort",
+            &[
+                ("color10", "#0000FF"),
+                ("white", "#FFFFFF"),
+                ("color2", "#00FF00"),
+                ("dark-red", "#800000"),
+                ("black", "#000000"),
+                ("red", "#FF0000"),
+                ("grey", "#808080"),
+            ],
+        )
+    }
+
+    #[test]
+    fn orders() {
+        let palette = palette();
+        assert_eq!(
+            palette.sorted_names(SortOrder::Hue),
+            vec!["dark-red", "red", "color2", "color10", "black", "grey", "white"]
+        );
+        assert_eq!(
+            palette.sorted_names(SortOrder::Lightness),
+            vec!["black", "dark-red", "color10", "grey", "red", "color2", "white"]
+        );
+        assert_eq!(
+            palette.sorted_names(SortOrder::Luminance)[..3],
+            ["black", "dark-red", "color10"]
+        );
+        let mut greys = palette.sorted_names(SortOrder::Chroma)[..3].to_vec();
+        greys.sort();
+        assert_eq!(greys, ["black", "grey", "white"]);
+        assert_eq!(
+            palette.sorted_names(SortOrder::Name),
+            vec!["black", "color2", "color10", "dark-red", "grey", "red", "white"]
+        );
+        let path = palette.sorted_names(SortOrder::NearestNeighbor);
+        assert_eq!(path[0], "black");
+        assert_eq!(path.len(), 7);
+        let hilbert = palette.sorted_names(SortOrder::Hilbert);
+        assert_eq!(hilbert.len(), 7);
+        for order in SortOrder::all() {
+            assert_eq!(order.to_string().parse(), Ok(order));
+        }
+    }
+
+    #[test]
+    fn hilbert_curve_is_continuous() {
+        let bits = 3;
+        let side = 1 << bits;
+        let mut points = vec![[0u32; 3]; side * side * side];
+        for x in 0..side as u32 {
+            for y in 0..side as u32 {
+                for z in 0..side as u32 {
+                    points[hilbert_index([x, y, z], bits) as usize] = [x, y, z];
+                }
+            }
+        }
+        for pair in points.windows(2) {
+            let distance = (0..3).map(|i| pair[0][i].abs_diff(pair[1][i])).sum::<u32>();
+            assert_eq!(distance, 1, "{:?}", pair);
+        }
+    }
+
+    #[test]
+    fn view_and_reorder() {
+        let mut palette = palette();
+        let original = palette.color_names();
+        let sorted = palette.sorted(SortOrder::Name);
+        assert_eq!(palette.color_names(), original);
+        assert_eq!(sorted.sorted_names(SortOrder::Name), sorted.color_names());
+
+        palette.sort_colors(SortOrder::Name);
+        assert_eq!(palette.color_names(), sorted.color_names());
+        assert!(palette.undo());
+        assert_eq!(palette.color_names(), original);
+        assert!(!palette.undo());
+    }
+}
@@ -0,0 +1,392 @@
+use std::fmt;
+
+use slint::{ModelRc, VecModel};
+
+use super::Palette;
+use crate::color::Color;
+use crate::{HueBinType, StatType, StatsType};
+
+/// Bins of [`PaletteStats::hue_histogram`], each covering 30° of Oklch hue from 0°.
+pub const HUE_BINS: usize = 12;
+/// Oklch chroma below which a colour counts as grey and has no meaningful hue.
+pub const ACHROMATIC_CHROMA: f64 = 0.03;
+
+/// Chromaticities (CIE xy) of the red, green and blue primaries of sRGB.
+const SRGB_PRIMARIES: [(f64, f64); 3] = [(0.64, 0.33), (0.30, 0.60), (0.15, 0.06)];
+/// Chromaticities of the Display P3 primaries.
+const P3_PRIMARIES: [(f64, f64); 3] = [(0.680, 0.320), (0.265, 0.690), (0.150, 0.060)];
+
+/// Smallest, largest and mean of a set of values.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Summary {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+impl Summary {
+    fn of(values: impl IntoIterator<Item = f64>) -> Option<Self> {
+        let mut count = 0;
+        let mut summary = Summary {
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            mean: 0.0,
+        };
+        for value in values {
+            count += 1;
+            summary.min = summary.min.min(value);
+            summary.max = summary.max.max(value);
+            summary.mean += value;
+        }
+        (count > 0).then(|| Summary {
+            mean: summary.mean / count as f64,
+            ..summary
+        })
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.3} to {:.3}, mean {:.3}",
+            self.min, self.max, self.mean
+        )
+    }
+}
+
+/// The two colours hardest to tell apart.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ClosestPair {
+    pub first: String,
+    pub second: String,
+    /// CIEDE2000 difference between them.
+    pub delta_e: f64,
+}
+
+/// Figures for comparing palettes, from [`Palette::stats`]. Translucent colours are
+/// measured as if opaque.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PaletteStats {
+    pub count: usize,
+    /// Number of chromatic colours per 30° of Oklch hue.
+    pub hue_histogram: [usize; HUE_BINS],
+    /// Colours with too little chroma to have a hue.
+    pub achromatic: usize,
+    /// Oklch lightness, 0.0 to 1.0.
+    pub lightness: Option<Summary>,
+    /// Oklch chroma, 0.0 to about 0.37.
+    pub chroma: Option<Summary>,
+    /// Mean CIEDE2000 difference over every pair of colours.
+    pub mean_delta_e: Option<f64>,
+    pub closest: Option<ClosestPair>,
+    /// Share of the sRGB chromaticity triangle spanned by the colours, 0.0 to 1.0.
+    pub srgb_coverage: f64,
+    /// Share of the Display P3 chromaticity triangle spanned by the colours.
+    pub p3_coverage: f64,
+    /// Chromatic colours with red, orange, yellow or pink hues.
+    pub warm: usize,
+    /// Chromatic colours with green, cyan, blue or purple hues.
+    pub cool: usize,
+    pub translucent: usize,
+}
+
+impl PaletteStats {
+    /// Warm against cool colours, from -1.0 (all cool) to 1.0 (all warm).
+    pub fn warmth(&self) -> Option<f64> {
+        let total = self.warm + self.cool;
+        (total > 0).then(|| (self.warm as f64 - self.cool as f64) / total as f64)
+    }
+
+    pub fn to_slint(&self) -> StatsType {
+        let lines = self
+            .lines()
+            .into_iter()
+            .map(|(label, value)| StatType {
+                label: label.into(),
+                value: value.into(),
+            })
+            .collect::<Vec<StatType>>();
+        let bin_width = 360.0 / HUE_BINS as f64;
+        let hues = self
+            .hue_histogram
+            .iter()
+            .enumerate()
+            .map(|(bin, count)| HueBinType {
+                color: Color::from_oklch(0.7, 0.15, (bin as f64 + 0.5) * bin_width).to_slint(),
+                count: *count as i32,
+            })
+            .collect::<Vec<HueBinType>>();
+        StatsType {
+            lines: ModelRc::new(VecModel::from(lines)),
+            hues: ModelRc::new(VecModel::from(hues)),
+            max_count: self.hue_histogram.iter().copied().max().unwrap_or(0) as i32,
+        }
+    }
+
+    /// Label and value of each figure, as shown in the viewer's summary.
+    pub fn lines(&self) -> Vec<(String, String)> {
+        let or_none = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        vec![
+            ("colours".to_string(), self.count.to_string()),
+            (
+                "lightness".to_string(),
+                or_none(self.lightness.map(|summary| summary.to_string())),
+            ),
+            (
+                "chroma".to_string(),
+                or_none(self.chroma.map(|summary| summary.to_string())),
+            ),
+            (
+                "mean ΔE".to_string(),
+                or_none(self.mean_delta_e.map(|delta_e| format!("{:.2}", delta_e))),
+            ),
+            (
+                "closest pair".to_string(),
+                or_none(self.closest.as_ref().map(|pair| {
+                    format!("{} / {} (ΔE {:.2})", pair.first, pair.second, pair.delta_e)
+                })),
+            ),
+            (
+                "gamut coverage".to_string(),
+                format!(
+                    "{:.1}% sRGB, {:.1}% P3",
+                    self.srgb_coverage * 100.0,
+                    self.p3_coverage * 100.0
+                ),
+            ),
+            (
+                "warm / cool".to_string(),
+                format!(
+                    "{} / {}{}",
+                    self.warm,
+                    self.cool,
+                    self.warmth()
+                        .map(|warmth| format!(" ({:+.2})", warmth))
+                        .unwrap_or_default()
+                ),
+            ),
+            ("greys".to_string(), self.achromatic.to_string()),
+            ("translucent".to_string(), self.translucent.to_string()),
+        ]
+    }
+}
+
+impl fmt::Display for PaletteStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (label, value) in self.lines() {
+            writeln!(f, "{:<16}{}", label, value)?;
+        }
+        let histogram = self
+            .hue_histogram
+            .iter()
+            .enumerate()
+            .map(|(bin, count)| format!("{}°:{}", bin * 360 / HUE_BINS, count))
+            .collect::<Vec<_>>();
+        writeln!(f, "{:<16}{}", "hues", histogram.join(" "))
+    }
+}
+
+impl Palette {
+    /// Statistics describing the palette's colours, see [`PaletteStats`].
+    pub fn stats(&self) -> PaletteStats {
+        let colors = self
+            .colors
+            .iter()
+            .map(|(name, color)| (name.as_str(), color.to_opaque()))
+            .collect::<Vec<_>>();
+        let oklch = colors
+            .iter()
+            .map(|(_, color)| color.oklch())
+            .collect::<Vec<_>>();
+
+        let mut hue_histogram = [0; HUE_BINS];
+        let (mut warm, mut cool) = (0, 0);
+        for (_, chroma, hue) in &oklch {
+            if *chroma < ACHROMATIC_CHROMA {
+                continue;
+            }
+            let hue = hue.rem_euclid(360.0);
+            hue_histogram[(hue / (360.0 / HUE_BINS as f64)) as usize % HUE_BINS] += 1;
+            if is_warm(hue) {
+                warm += 1;
+            } else {
+                cool += 1;
+            }
+        }
+
+        let mut total_delta_e = 0.0;
+        let mut pairs = 0;
+        let mut closest: Option<ClosestPair> = None;
+        for (i, (first, a)) in colors.iter().enumerate() {
+            for (second, b) in &colors[i + 1..] {
+                let delta_e = a.delta_e(b);
+                total_delta_e += delta_e;
+                pairs += 1;
+                if closest.as_ref().is_none_or(|pair| delta_e < pair.delta_e) {
+                    closest = Some(ClosestPair {
+                        first: first.to_string(),
+                        second: second.to_string(),
+                        delta_e,
+                    });
+                }
+            }
+        }
+
+        let hull = convex_hull(
+            colors
+                .iter()
+                .filter_map(|(_, color)| chromaticity(color))
+                .collect(),
+        );
+        let hull_area = area(&hull);
+        PaletteStats {
+            count: colors.len(),
+            hue_histogram,
+            achromatic: oklch
+                .iter()
+                .filter(|(_, chroma, _)| *chroma < ACHROMATIC_CHROMA)
+                .count(),
+            lightness: Summary::of(oklch.iter().map(|(lightness, _, _)| *lightness)),
+            chroma: Summary::of(oklch.iter().map(|(_, chroma, _)| *chroma)),
+            mean_delta_e: (pairs > 0).then(|| total_delta_e / pairs as f64),
+            closest,
+            srgb_coverage: (hull_area / area(&SRGB_PRIMARIES)).min(1.0),
+            p3_coverage: (hull_area / area(&P3_PRIMARIES)).min(1.0),
+            warm,
+            cool,
+            translucent: self
+                .colors
+                .values()
+                .filter(|color| color.opacity < 255)
+                .count(),
+        }
+    }
+}
+
+/// Red through yellow, and pink, in Oklch hue.
+fn is_warm(hue: f64) -> bool {
+    !(120.0..330.0).contains(&hue)
+}
+
+/// CIE xy chromaticity, `None` for black which has none.
+fn chromaticity(color: &Color) -> Option<(f64, f64)> {
+    let (x, y, z) = color.xyz();
+    let sum = x + y + z;
+    (sum > 1e-9).then(|| (x / sum, y / sum))
+}
+
+/// Convex hull of `points` in counter-clockwise order, by Andrew's monotone chain.
+fn convex_hull(mut points: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    points.sort_by(|a, b| a.partial_cmp(b).expect("chromaticities are finite"));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let cross = |o: (f64, f64), a: (f64, f64), b: (f64, f64)| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+    let mut hull: Vec<(f64, f64)> = Vec::with_capacity(points.len() * 2);
+    for pass in [points.clone(), points.into_iter().rev().collect()] {
+        let start = hull.len();
+        for point in pass {
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+/// Area of a polygon by the shoelace formula.
+fn area(polygon: &[(f64, f64)]) -> f64 {
+    if polygon.len() < 3 {
+        return 0.0;
+    }
+    let twice = (0..polygon.len())
+        .map(|i| {
+            let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum::<f64>();
+    twice.abs() / 2.0
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use super::*;
+    use crate::testing;
+
+    fn palette(entries: &[(&str, &str)]) -> Palette {
+        testing::palette("Stats", entries)
+    }
+
+    #[test]
+    fn primaries_cover_srgb() {
+        let stats = palette(&[
+            ("red", "#F00"),
+            ("green", "#0F0"),
+            ("blue", "#00F"),
+            ("grey", "#808080"),
+            ("black", "#000"),
+        ])
+        .stats();
+        assert!((stats.srgb_coverage - 1.0).abs() < 0.01);
+        assert!(stats.p3_coverage > 0.7 && stats.p3_coverage < 0.8);
+        assert_eq!(stats.achromatic, 2);
+        assert_eq!((stats.warm, stats.cool), (1, 2));
+        assert_eq!(stats.hue_histogram.iter().sum::<usize>(), 3);
+        assert_eq!(stats.hue_histogram[0], 1);
+        assert_eq!(stats.hue_histogram[4], 1);
+        assert_eq!(stats.hue_histogram[8], 1);
+        let lightness = stats.lightness.unwrap();
+        assert_eq!(lightness.min, 0.0);
+        assert!(lightness.max > 0.86 && lightness.max < 0.87);
+    }
+
+    #[test]
+    fn pairwise_differences() {
+        let stats = palette(&[
+            ("bg", "#1E1E2E"),
+            ("bg-alt", "#202031"),
+            ("red", "#F38BA8"),
+            ("fg", "#CDD6F4C0"),
+        ])
+        .stats();
+        let closest = stats.closest.unwrap();
+        assert_eq!(
+            (closest.first.as_str(), closest.second.as_str()),
+            ("bg", "bg-alt")
+        );
+        assert!(closest.delta_e < 2.0);
+        assert!(stats.mean_delta_e.unwrap() > closest.delta_e);
+        assert_eq!(stats.translucent, 1);
+        assert_eq!(stats.srgb_coverage, stats.srgb_coverage.min(0.1));
+    }
+
+    #[test]
+    fn empty() {
+        let stats = Palette::new("Empty", None).stats();
+        assert_eq!(stats.lightness, None);
+        assert_eq!(stats.mean_delta_e, None);
+        assert_eq!(stats.warmth(), None);
+        assert_eq!(stats.srgb_coverage, 0.0);
+    }
+
+    #[test]
+    fn hull_area() {
+        let hull = convex_hull(vec![
+            (0.0, 0.0),
+            (1.0, 0.0),
+            (0.5, 0.2),
+            (1.0, 1.0),
+            (0.0, 1.0),
+        ]);
+        assert_eq!(hull.len(), 4);
+        assert_eq!(area(&hull), 1.0);
+    }
+}
@@ -0,0 +1,366 @@
+use std::{fmt, str::FromStr};
+
+use uuid::Uuid;
+
+use super::{
+    history::{Edit, History},
+    ColorRole, Palette, Query,
+};
+use crate::color::{Color, ContrastMethod};
+
+/// How far in Oklab `temperature=1` moves a colour towards orange.
+const TEMPERATURE_STEP: (f64, f64) = (0.02, 0.06);
+
+/// A change made to every selected colour by [`Palette::transformed`].
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Transform {
+    /// Rotates Oklch hue by the given degrees.
+    HueShift(f64),
+    /// Multiplies Oklch chroma, so `0.8` is 20% less saturated.
+    ChromaScale(f64),
+    /// Raises Oklch lightness to the given power: above 1 darkens, below 1 lightens,
+    /// leaving black and white in place.
+    LightnessCurve(f64),
+    /// Moves colours towards orange, or towards blue when negative; `1.0` is a strong shift.
+    Temperature(f64),
+    /// Rounds each sRGB channel to the given number of levels.
+    Posterize(u8),
+    /// Removes all chroma, keeping Oklch lightness.
+    Grayscale,
+    /// Moves colours away from the background until they reach the WCAG contrast ratio.
+    ClampContrast(f64),
+}
+
+impl Transform {
+    /// The colour after the transform; opacity is kept.
+    /// [`Transform::ClampContrast`] needs the background.
+    pub fn apply(&self, color: &Color, background: Option<&Color>) -> Color {
+        let (lightness, chroma, hue) = color.oklch();
+        let transformed = match self {
+            Transform::HueShift(degrees) => {
+                Color::from_oklch(lightness, chroma, (hue + degrees).rem_euclid(360.0))
+            }
+            Transform::ChromaScale(factor) => {
+                Color::from_oklch(lightness, chroma * factor.max(0.0), hue)
+            }
+            Transform::LightnessCurve(gamma) => {
+                Color::from_oklch(lightness.clamp(0.0, 1.0).powf(*gamma), chroma, hue)
+            }
+            Transform::Temperature(amount) => {
+                let (l, a, b) = color.oklab();
+                let (step_a, step_b) = TEMPERATURE_STEP;
+                Color::from_oklab(l, a + amount * step_a, b + amount * step_b)
+            }
+            Transform::Posterize(levels) => {
+                let steps = (levels.max(&2) - 1) as f64;
+                let round = |channel: u8| {
+                    ((channel as f64 / 255.0 * steps).round() / steps * 255.0).round() as u8
+                };
+                let (red, green, blue) = color.rgb();
+                Color::new_solid(round(red), round(green), round(blue))
+            }
+            Transform::Grayscale => Color::from_oklch(lightness, 0.0, hue),
+            Transform::ClampContrast(target) => match background {
+                Some(background) => {
+                    color.with_contrast(&background.to_opaque(), *target, ContrastMethod::Wcag)
+                }
+                None => *color,
+            },
+        };
+        Color {
+            opacity: color.opacity,
+            ..transformed
+        }
+    }
+}
+
+impl FromStr for Transform {
+    type Err = String;
+
+    /// `NAME` or `NAME=VALUE`, e.g. `hue-shift=15`, `chroma=0.8` or `grayscale`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = match s.split_once('=') {
+            Some((name, value)) => (name.trim().to_lowercase(), Some(value.trim())),
+            None => (s.trim().to_lowercase(), None),
+        };
+        let number = || -> Result<f64, String> {
+            let value = value.ok_or_else(|| format!("{} needs a value", name))?;
+            match value.strip_suffix('%') {
+                Some(percent) => percent.trim().parse::<f64>().map(|p| p / 100.0),
+                None => value.parse::<f64>(),
+            }
+            .map_err(|_| format!("{}: `{}` is not a number", name, value))
+        };
+        match name.as_str() {
+            "hue-shift" | "hue" => Ok(Transform::HueShift(number()?)),
+            "chroma" | "saturation" => Ok(Transform::ChromaScale(number()?)),
+            "lightness-curve" | "gamma" => match number()? {
+                gamma if gamma > 0.0 => Ok(Transform::LightnessCurve(gamma)),
+                _ => Err(format!("{} needs a value above 0", name)),
+            },
+            "temperature" | "warmth" => Ok(Transform::Temperature(number()?)),
+            "posterize" => match number()? {
+                levels if (2.0..=255.0).contains(&levels) && levels.fract() == 0.0 => {
+                    Ok(Transform::Posterize(levels as u8))
+                }
+                _ => Err(format!(
+                    "{} needs a whole number of levels from 2 to 255",
+                    name
+                )),
+            },
+            "grayscale" | "greyscale" if value.is_none() => Ok(Transform::Grayscale),
+            "clamp-contrast" | "contrast" => Ok(Transform::ClampContrast(number()?)),
+            _ => Err(format!("Unknown transform: {}", s.trim())),
+        }
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transform::HueShift(degrees) => write!(f, "hue-shift={}", degrees),
+            Transform::ChromaScale(factor) => write!(f, "chroma={}", factor),
+            Transform::LightnessCurve(gamma) => write!(f, "lightness-curve={}", gamma),
+            Transform::Temperature(amount) => write!(f, "temperature={}", amount),
+            Transform::Posterize(levels) => write!(f, "posterize={}", levels),
+            Transform::Grayscale => write!(f, "grayscale"),
+            Transform::ClampContrast(target) => write!(f, "clamp-contrast={}", target),
+        }
+    }
+}
+
+impl Palette {
+    /// A new palette with `transforms` applied in order to the colours matching `query`,
+    /// or to every colour. The palette itself is unchanged.
+    ///
+    /// Transformed entries keep their new value rather than their expression; entries
+    /// derived from them follow. Background colours are never moved by
+    /// [`Transform::ClampContrast`], which fails if the palette has no background.
+    pub fn transformed(
+        &self,
+        transforms: &[Transform],
+        query: Option<&Query>,
+    ) -> Result<Palette, String> {
+        let selected = match query {
+            Some(query) => self
+                .select(query)?
+                .into_iter()
+                .map(str::to_string)
+                .collect::<Vec<_>>(),
+            None => self.color_names(),
+        };
+        let background = self.background();
+        let clamps = transforms
+            .iter()
+            .any(|transform| matches!(transform, Transform::ClampContrast(_)));
+        if clamps && background.is_none() {
+            return Err(format!(
+                "{} has no background to keep contrast with",
+                self.name
+            ));
+        }
+        let backgrounds = [ColorRole::Background, ColorRole::SelectionBackground]
+            .iter()
+            .filter_map(|role| self.role_name(*role))
+            .collect::<Vec<_>>();
+
+        let mut palette = self.clone();
+        palette.uuid = Uuid::new_v4();
+        palette.filename = None;
+        palette.history = History::default();
+        for name in &selected {
+            let mut color = self.colors[name.as_str()];
+            for transform in transforms {
+                if matches!(transform, Transform::ClampContrast(_))
+                    && backgrounds.contains(&name.as_str())
+                {
+                    continue;
+                }
+                color = transform.apply(&color, background.as_ref());
+            }
+            palette.expressions.shift_remove(name);
+            palette.colors.insert(name.clone(), color);
+        }
+        palette
+            .resolve_expressions()
+            .map_err(|error| error.to_string())?;
+        Ok(palette)
+    }
+
+    /// Applies `transforms` as [`Palette::transformed`] does, but to the palette itself,
+    /// recording the changed colours as one undoable group of edits. Returns how many
+    /// colours were recolored; on error the palette is left unchanged.
+    pub fn apply_transforms(
+        &mut self,
+        transforms: &[Transform],
+        query: Option<&Query>,
+    ) -> Result<usize, String> {
+        let transformed = self.transformed(transforms, query)?;
+        // Entries that kept their expression follow the colours they are derived from.
+        let edits = transformed
+            .colors
+            .iter()
+            .filter(|(name, color)| {
+                !transformed.expressions.contains_key(*name)
+                    && (self.expressions.contains_key(*name) || self.colors[*name] != **color)
+            })
+            .map(|(name, color)| Edit::Recolor {
+                name: name.clone(),
+                color: *color,
+            })
+            .collect::<Vec<_>>();
+        let count = edits.len();
+        self.begin_edit_group();
+        for edit in edits {
+            if let Err(error) = self.apply(edit) {
+                self.end_edit_group();
+                self.undo();
+                return Err(error.to_string());
+            }
+        }
+        self.end_edit_group();
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod transform_tests {
+    use super::*;
+    use crate::testing;
+
+    fn palette() -> Palette {
+        let mut palette = testing::palette(
+            "Mocha",
+            &[
+                ("background", "#1E1E2E"),
+                ("foreground", "#CDD6F4"),
+                ("red", "#F38BA8"),
+                ("blue", "#89B4FA"),
+                ("comment", "#45475A"),
+            ],
+        );
+        palette
+            .set_expression("bright-red", "lighten($red, 10%)")
+            .unwrap();
+        palette
+    }
+
+    #[test]
+    fn parse() {
+        for text in [
+            "hue-shift=15",
+            "chroma=0.8",
+            "lightness-curve=1.2",
+            "temperature=-0.5",
+            "posterize=4",
+            "grayscale",
+            "clamp-contrast=4.5",
+        ] {
+            assert_eq!(text.parse::<Transform>().unwrap().to_string(), text);
+        }
+        assert_eq!("Chroma = 80%".parse(), Ok(Transform::ChromaScale(0.8)));
+        assert!("posterize=1".parse::<Transform>().is_err());
+        assert!("gamma=0".parse::<Transform>().is_err());
+        assert!("hue-shift".parse::<Transform>().is_err());
+        assert!("sepia".parse::<Transform>().is_err());
+    }
+
+    #[test]
+    fn colour_transforms() {
+        let red = Color::from_hex("#F38BA8");
+        let (lightness, chroma, hue) = red.oklch();
+
+        let shifted = Transform::HueShift(120.0).apply(&red, None).oklch();
+        assert!(((shifted.2 - hue).rem_euclid(360.0) - 120.0).abs() < 2.0);
+        assert!((shifted.0 - lightness).abs() < 0.01);
+
+        let muted = Transform::ChromaScale(0.5).apply(&red, None).oklch();
+        assert!((muted.1 - chroma / 2.0).abs() < 0.01);
+
+        let grey = Transform::Grayscale.apply(&red.with_alpha(0.5), None);
+        assert!(grey.oklch().1 < 0.001);
+        assert_eq!(grey.opacity, 128);
+
+        let darker = Transform::LightnessCurve(2.0).apply(&red, None).oklch();
+        assert!((darker.0 - lightness.powi(2)).abs() < 0.01);
+
+        let warmer = Transform::Temperature(1.0).apply(&Color::from_hex("#808080"), None);
+        let (red, _, blue) = warmer.rgb();
+        assert!(red > blue);
+
+        let posterized = Transform::Posterize(2).apply(&Color::from_hex("#F38BA8"), None);
+        assert_eq!(posterized.hex(), "#FFFFFF");
+    }
+
+    #[test]
+    fn transformed_palette() {
+        let palette = palette();
+        let query = "name ~ \"*red\"".parse::<Query>().unwrap();
+        let warmer = palette
+            .transformed(&[Transform::HueShift(180.0)], Some(&query))
+            .unwrap();
+        assert_ne!(warmer.uuid, palette.uuid);
+        assert_eq!(warmer.get_color("blue"), palette.get_color("blue"));
+        assert_ne!(warmer.get_color("red"), palette.get_color("red"));
+        assert_eq!(warmer.expression("bright-red"), None);
+        assert_eq!(palette.get_color("red").unwrap().hex(), "#F38BA8");
+
+        let derived = palette
+            .transformed(
+                &[Transform::Grayscale],
+                Some(&"name = red".parse().unwrap()),
+            )
+            .unwrap();
+        assert_eq!(derived.expression("bright-red"), Some("lighten($red, 10%)"));
+        assert!(derived.get_color("bright-red").unwrap().oklch().1 < 0.01);
+    }
+
+    #[test]
+    fn applied_transforms_undo_as_one() {
+        let mut palette = palette();
+        let original = palette.colors.clone();
+        let query = "name = red".parse::<Query>().unwrap();
+        let expected = palette
+            .transformed(&[Transform::Grayscale], Some(&query))
+            .unwrap();
+        assert_eq!(
+            palette.apply_transforms(&[Transform::Grayscale], Some(&query)),
+            Ok(1)
+        );
+        assert_eq!(palette.colors, expected.colors);
+        assert_eq!(palette.expression("bright-red"), Some("lighten($red, 10%)"));
+
+        // The now grey `red` has no hue to shift.
+        assert_eq!(
+            palette.apply_transforms(&[Transform::HueShift(30.0)], None),
+            Ok(5)
+        );
+        assert_eq!(palette.expression("bright-red"), None);
+        assert!(palette.undo());
+        assert!(palette.undo());
+        assert_eq!(palette.colors, original);
+        assert_eq!(palette.expression("bright-red"), Some("lighten($red, 10%)"));
+    }
+
+    #[test]
+    fn clamp_contrast() {
+        let palette = palette();
+        let clamped = palette
+            .transformed(&[Transform::ClampContrast(4.5)], None)
+            .unwrap();
+        let background = clamped.background().unwrap();
+        assert_eq!(background, palette.background().unwrap());
+        for (name, color) in &clamped.colors {
+            if name != "background" {
+                assert!(color.contrast_ratio(&background) >= 4.5, "{}", name);
+            }
+        }
+        let mut no_background = Palette::new("Plain", None);
+        no_background
+            .add_color("red".to_string(), Color::from_hex("#F00"))
+            .unwrap();
+        assert!(no_background
+            .transformed(&[Transform::ClampContrast(4.5)], None)
+            .is_err());
+    }
+}
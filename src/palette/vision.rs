@@ -0,0 +1,445 @@
+use std::{fmt, str::FromStr};
+
+use super::{ColorRole, Palette};
+use crate::color::Color;
+use crate::config::writer::color_to_string;
+
+/// A colour-vision deficiency that [`Palette::vision_check`] simulates.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Deficiency {
+    /// No long-wavelength (red) cones.
+    Protanopia,
+    /// No medium-wavelength (green) cones.
+    Deuteranopia,
+    /// No short-wavelength (blue) cones.
+    Tritanopia,
+    /// No colour vision, only lightness.
+    Achromatopsia,
+}
+
+impl Deficiency {
+    pub fn all() -> [Deficiency; 4] {
+        [
+            Deficiency::Protanopia,
+            Deficiency::Deuteranopia,
+            Deficiency::Tritanopia,
+            Deficiency::Achromatopsia,
+        ]
+    }
+
+    /// How `color` looks with this deficiency, using the full-severity matrices of
+    /// Machado, Oliveira and Fernandes (2009) in linear sRGB.
+    pub fn simulate(&self, color: &Color) -> Color {
+        let (r, g, b) = color.linear_rgb();
+        let matrix = match self {
+            Deficiency::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            Deficiency::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            Deficiency::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+            Deficiency::Achromatopsia => {
+                let luminance = color.relative_luminance();
+                return Color::from_linear_rgb(luminance, luminance, luminance, color.opacity);
+            }
+        };
+        let [red, green, blue] = matrix.map(|row| row[0] * r + row[1] * g + row[2] * b);
+        Color::from_linear_rgb(red, green, blue, color.opacity)
+    }
+}
+
+impl FromStr for Deficiency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "protanopia" | "protan" => Ok(Deficiency::Protanopia),
+            "deuteranopia" | "deutan" => Ok(Deficiency::Deuteranopia),
+            "tritanopia" | "tritan" => Ok(Deficiency::Tritanopia),
+            "achromatopsia" | "achromat" => Ok(Deficiency::Achromatopsia),
+            other => Err(format!("Unknown colour-vision deficiency: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for Deficiency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Deficiency::Protanopia => write!(f, "protanopia"),
+            Deficiency::Deuteranopia => write!(f, "deuteranopia"),
+            Deficiency::Tritanopia => write!(f, "tritanopia"),
+            Deficiency::Achromatopsia => write!(f, "achromatopsia"),
+        }
+    }
+}
+
+/// Settings for [`Palette::vision_check`] and [`Palette::colorblind_safe`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct VisionOptions {
+    /// Colours closer than this CIEDE2000 difference once simulated are indistinguishable.
+    pub min_delta_e: f64,
+    pub deficiencies: Vec<Deficiency>,
+}
+
+impl Default for VisionOptions {
+    fn default() -> Self {
+        VisionOptions {
+            min_delta_e: 10.0,
+            deficiencies: Deficiency::all().to_vec(),
+        }
+    }
+}
+
+/// Two colours that are told apart with normal vision but not with `deficiency`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ConfusablePair {
+    pub deficiency: Deficiency,
+    pub first: String,
+    pub second: String,
+    /// CIEDE2000 difference with normal vision.
+    pub delta_e: f64,
+    /// CIEDE2000 difference once both colours are simulated.
+    pub simulated_delta_e: f64,
+}
+
+impl fmt::Display for ConfusablePair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: `{}` and `{}` are ΔE {:.2} apart, {:.2} normally",
+            self.deficiency, self.first, self.second, self.simulated_delta_e, self.delta_e
+        )
+    }
+}
+
+/// A colour [`Palette::colorblind_safe`] moved.
+#[derive(Debug, PartialEq, Clone)]
+pub struct VisionAdjustment {
+    pub name: String,
+    pub before: Color,
+    pub after: Color,
+    /// CIEDE2000 difference between `before` and `after`.
+    pub delta_e: f64,
+}
+
+/// What [`Palette::colorblind_safe`] found and changed.
+#[derive(Debug, PartialEq, Clone)]
+pub struct VisionReport {
+    pub palette: String,
+    /// Pairs confused in the original palette.
+    pub confusable: Vec<ConfusablePair>,
+    pub adjustments: Vec<VisionAdjustment>,
+    /// Pairs still confused in the adjusted palette.
+    pub remaining: Vec<ConfusablePair>,
+}
+
+impl fmt::Display for VisionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} confusable pairs, {} colours adjusted, {} remaining",
+            self.palette,
+            self.confusable.len(),
+            self.adjustments.len(),
+            self.remaining.len()
+        )?;
+        for pair in &self.confusable {
+            write!(f, "\n  {}", pair)?;
+        }
+        for adjustment in &self.adjustments {
+            write!(
+                f,
+                "\n  moved `{}` from {} to {} (ΔE {:.2})",
+                adjustment.name,
+                color_to_string(&adjustment.before),
+                color_to_string(&adjustment.after),
+                adjustment.delta_e
+            )?;
+        }
+        for pair in &self.remaining {
+            write!(f, "\n  still {}", pair)?;
+        }
+        Ok(())
+    }
+}
+
+/// Most colours [`Palette::colorblind_safe`] tries to move before giving up.
+const MAX_ADJUSTMENTS: usize = 64;
+/// Oklch lightness steps tried when moving a colour.
+const LIGHTNESS_STEP: f64 = 0.02;
+const LIGHTNESS_STEPS: usize = 25;
+/// Factors applied to Oklch chroma when moving a colour.
+const CHROMA_FACTORS: [f64; 5] = [1.0, 0.75, 1.25, 0.5, 1.5];
+
+impl Palette {
+    /// Pairs of colours that become indistinguishable with each of `options.deficiencies`,
+    /// grouped by deficiency. Colours already closer than `options.min_delta_e` with
+    /// normal vision are left to [`Palette::lint`]. Opacity is ignored.
+    pub fn vision_check(&self, options: &VisionOptions) -> Vec<ConfusablePair> {
+        let colors = self
+            .colors
+            .iter()
+            .map(|(name, color)| (name, color.to_opaque()))
+            .collect::<Vec<_>>();
+        let mut pairs = Vec::new();
+        for deficiency in &options.deficiencies {
+            let simulated = colors
+                .iter()
+                .map(|(_, color)| deficiency.simulate(color))
+                .collect::<Vec<_>>();
+            for (i, (first, first_color)) in colors.iter().enumerate() {
+                for (j, (second, second_color)) in colors.iter().enumerate().skip(i + 1) {
+                    let delta_e = first_color.delta_e(second_color);
+                    if delta_e < options.min_delta_e {
+                        continue;
+                    }
+                    let simulated_delta_e = simulated[i].delta_e(&simulated[j]);
+                    if simulated_delta_e < options.min_delta_e {
+                        pairs.push(ConfusablePair {
+                            deficiency: *deficiency,
+                            first: first.to_string(),
+                            second: second.to_string(),
+                            delta_e,
+                            simulated_delta_e,
+                        });
+                    }
+                }
+            }
+        }
+        pairs
+    }
+
+    /// A copy of the palette with confusable colours moved apart, and a report.
+    ///
+    /// One colour of each confused pair is moved in Oklch lightness and chroma, keeping
+    /// its hue, to the nearest colour that no deficiency confuses with the others. The
+    /// background and foreground are never moved, so contrast between them is kept.
+    /// Colours derived from a moved colour are re-evaluated after each move. Pairs no
+    /// such colour resolves are reported as remaining.
+    pub fn colorblind_safe(&self, options: &VisionOptions) -> (Palette, VisionReport) {
+        let confusable = self.vision_check(options);
+        let fixed = [ColorRole::Background, ColorRole::Foreground]
+            .iter()
+            .filter_map(|role| self.role_name(*role).map(str::to_string))
+            .collect::<Vec<_>>();
+        let mut palette = self.clone();
+        let mut unresolved: Vec<(String, String)> = Vec::new();
+        let mut adjustments: Vec<VisionAdjustment> = Vec::new();
+        for _ in 0..MAX_ADJUSTMENTS {
+            let pair = palette
+                .vision_check(options)
+                .into_iter()
+                .find(|pair| !unresolved.contains(&(pair.first.clone(), pair.second.clone())));
+            let pair = match pair {
+                Some(pair) => pair,
+                None => break,
+            };
+            let movable = [&pair.second, &pair.first]
+                .into_iter()
+                .filter(|name| !fixed.contains(name))
+                .collect::<Vec<_>>();
+            let moved = movable.into_iter().find_map(|name| {
+                let color = palette.separated(self, name, options)?;
+                Some((name.clone(), color))
+            });
+            let (name, color) = match moved {
+                Some(moved) => moved,
+                None => {
+                    unresolved.push((pair.first, pair.second));
+                    continue;
+                }
+            };
+            let previous = palette.clone();
+            palette.expressions.shift_remove(&name);
+            palette.colors.insert(name.clone(), color);
+            // Colours derived from the moved one follow it, as they do in a transform.
+            if palette.resolve_expressions().is_err() {
+                palette = previous;
+                unresolved.push((pair.first, pair.second));
+                continue;
+            }
+            let before = self.colors[&name];
+            adjustments.retain(|adjustment| adjustment.name != name);
+            adjustments.push(VisionAdjustment {
+                delta_e: before.to_opaque().delta_e(&color.to_opaque()),
+                name,
+                before,
+                after: color,
+            });
+        }
+        let remaining = palette.vision_check(options);
+        let report = VisionReport {
+            palette: self.name.clone(),
+            confusable,
+            adjustments,
+            remaining,
+        };
+        (palette, report)
+    }
+
+    /// The colour nearest to `name`'s colour in `original` that is not confused with any
+    /// other colour of this palette, or `None` if no lightness and chroma does that.
+    fn separated(&self, original: &Palette, name: &str, options: &VisionOptions) -> Option<Color> {
+        let start = original.colors[name];
+        let (lightness, chroma, hue) = start.to_opaque().oklch();
+        let mut candidates = Vec::new();
+        for step in 0..=LIGHTNESS_STEPS {
+            for sign in [1.0, -1.0] {
+                let lightness = lightness + sign * step as f64 * LIGHTNESS_STEP;
+                if !(0.0..=1.0).contains(&lightness) {
+                    continue;
+                }
+                for factor in CHROMA_FACTORS {
+                    candidates.push(Color {
+                        opacity: start.opacity,
+                        ..Color::from_oklch(lightness, chroma * factor, hue)
+                    });
+                }
+            }
+        }
+        candidates.sort_by(|a, b| {
+            start
+                .to_opaque()
+                .delta_e(&a.to_opaque())
+                .total_cmp(&start.to_opaque().delta_e(&b.to_opaque()))
+        });
+        candidates
+            .into_iter()
+            .find(|candidate| !self.confused(original, name, candidate, options))
+    }
+
+    /// Whether `color` in place of `name` would be confused with another colour, by any
+    /// deficiency or with normal vision. Colours `original` already had that close to
+    /// `name` are ignored.
+    fn confused(
+        &self,
+        original: &Palette,
+        name: &str,
+        color: &Color,
+        options: &VisionOptions,
+    ) -> bool {
+        let color = color.to_opaque();
+        let start = original.colors[name].to_opaque();
+        self.colors
+            .iter()
+            .filter(|(other, _)| *other != name)
+            .map(|(_, other)| other.to_opaque())
+            .filter(|other| start.delta_e(other) >= options.min_delta_e)
+            .any(|other| {
+                color.delta_e(&other) < options.min_delta_e
+                    || options.deficiencies.iter().any(|deficiency| {
+                        deficiency
+                            .simulate(&color)
+                            .delta_e(&deficiency.simulate(&other))
+                            < options.min_delta_e
+                    })
+            })
+    }
+}
+
+#[cfg(test)]
+mod vision_tests {
+    use super::*;
+    use crate::color::{BLACK, WHITE};
+    use crate::testing;
+
+    fn chart() -> Palette {
+        testing::palette(
+            "Chart",
+            &[
+                ("background", "#FFFFFF"),
+                ("foreground", "#000000"),
+                ("series-1", "#D62728"),
+                ("series-2", "#2CA02C"),
+                ("series-3", "#1F77B4"),
+            ],
+        )
+    }
+
+    #[test]
+    fn simulate() {
+        for deficiency in Deficiency::all() {
+            assert_eq!(deficiency.simulate(&BLACK), BLACK);
+            assert!(deficiency.simulate(&WHITE).delta_e(&WHITE) < 1.0);
+        }
+        let gray = Deficiency::Achromatopsia.simulate(&Color::from_hex("#D62728"));
+        assert_eq!(gray.red, gray.green);
+        assert_eq!(gray.green, gray.blue);
+        assert_eq!("deutan".parse(), Ok(Deficiency::Deuteranopia));
+        assert!("tetrachromacy".parse::<Deficiency>().is_err());
+    }
+
+    #[test]
+    fn check() {
+        let palette = chart();
+        let pairs = palette.vision_check(&VisionOptions::default());
+        assert!(pairs
+            .iter()
+            .any(|pair| pair.deficiency == Deficiency::Deuteranopia
+                && pair.first == "series-1"
+                && pair.second == "series-2"));
+        assert!(pairs
+            .iter()
+            .all(|pair| pair.simulated_delta_e < 10.0 && pair.delta_e >= 10.0));
+        let options = VisionOptions {
+            min_delta_e: 1.0,
+            deficiencies: vec![Deficiency::Tritanopia],
+        };
+        assert!(palette.vision_check(&options).is_empty());
+    }
+
+    #[test]
+    fn colorblind_safe() {
+        let palette = chart();
+        let options = VisionOptions::default();
+        let (safe, report) = palette.colorblind_safe(&options);
+        assert!(!report.confusable.is_empty());
+        assert!(report.remaining.is_empty(), "{}", report);
+        assert_eq!(safe.vision_check(&options), report.remaining);
+        assert_eq!(safe.colors["background"], palette.colors["background"]);
+        assert_eq!(safe.colors["foreground"], palette.colors["foreground"]);
+        for adjustment in &report.adjustments {
+            assert_eq!(safe.colors[&adjustment.name], adjustment.after);
+            let (_, _, before_hue) = adjustment.before.oklch();
+            let (_, chroma, after_hue) = adjustment.after.oklch();
+            assert!(chroma < 0.01 || (before_hue - after_hue).abs() < 2.0);
+        }
+
+        let (unchanged, report) = safe.colorblind_safe(&options);
+        assert_eq!(unchanged.colors, safe.colors);
+        assert!(report.adjustments.is_empty());
+    }
+
+    #[test]
+    fn derived_colours_follow() {
+        let mut palette = chart();
+        palette
+            .set_expression("series-2-light", "lighten($series-2, 5%)")
+            .unwrap();
+        let (safe, report) = palette.colorblind_safe(&VisionOptions::default());
+        assert!(report
+            .adjustments
+            .iter()
+            .any(|adjustment| adjustment.name == "series-2"));
+        assert_eq!(
+            safe.expression("series-2-light"),
+            Some("lighten($series-2, 5%)")
+        );
+        let mut resolved = safe.clone();
+        resolved.resolve_expressions().unwrap();
+        assert_eq!(resolved.colors, safe.colors);
+        assert_ne!(
+            safe.colors["series-2-light"],
+            palette.colors["series-2-light"]
+        );
+    }
+}
@@ -1,5 +1,16 @@
 // TODO: Css, toml, json, config
+use std::fmt;
+
 use toml::Table;
+use winnow::{
+    ascii::{float, multispace0},
+    combinator::{alt, cut_err, delimited, eof, fail, opt, preceded, separated, terminated},
+    error::{StrContext, StrContextValue},
+    token::{one_of, take_while},
+    PResult, Parser,
+};
+
+use crate::color::Color;
 
 #[allow(dead_code)]
 enum ParserType {
@@ -12,6 +23,171 @@ fn toml_titles(input: String) -> Vec<String> {
     toml.keys().cloned().collect::<Vec<String>>()
 }
 
+/// A palette entry value: a colour, a reference to another entry or a function of those.
+///
+/// ```text
+/// #1e1e2e
+/// $foreground
+/// ${Selection Background}
+/// lighten($color1, 10%)
+/// mix($bg, $fg, 20%)
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expr {
+    Color(Color),
+    Reference(String),
+    /// A number argument; percentages are stored as fractions, so `10%` is `0.1`.
+    Number(f64),
+    Call(String, Vec<Expr>),
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Color(color) => match color.opacity {
+                255 => write!(f, "{}", color.hex()),
+                _ => write!(f, "{}", color.hexa()),
+            },
+            Expr::Reference(name) if name.chars().all(is_name_char) => write!(f, "${}", name),
+            Expr::Reference(name) => write!(f, "${{{}}}", name),
+            // Rounded so that fractions such as `0.07` print back as `7%` rather than
+            // `7.000000000000001%`.
+            Expr::Number(number) => write!(f, "{}%", (number * 100.0 * 1e6).round() / 1e6),
+            Expr::Call(function, args) => {
+                let args = args.iter().map(Expr::to_string).collect::<Vec<_>>();
+                write!(f, "{}({})", function, args.join(", "))
+            }
+        }
+    }
+}
+
+impl Expr {
+    /// Names of every entry the expression refers to.
+    pub fn references(&self) -> Vec<&str> {
+        match self {
+            Expr::Reference(name) => vec![name.as_str()],
+            Expr::Call(_, args) => args.iter().flat_map(Expr::references).collect(),
+            Expr::Color(_) | Expr::Number(_) => vec![],
+        }
+    }
+
+    /// The expression with every reference to `from` pointing at `to` instead.
+    pub fn renamed(&self, from: &str, to: &str) -> Expr {
+        match self {
+            Expr::Reference(name) if name == from => Expr::Reference(to.to_string()),
+            Expr::Call(function, args) => Expr::Call(
+                function.clone(),
+                args.iter().map(|arg| arg.renamed(from, to)).collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+}
+
+/// Parses a palette entry value into an [`Expr`].
+///
+/// A whole value may be a 6 or 8 digit hex colour without the `#`, as pywal and some
+/// themes write them. Shorter ones need the `#`, so words such as `bad` are not colours.
+pub fn parse_expression(input: &str) -> Result<Expr, String> {
+    let bare_hex = delimited(multispace0, bare_hex_digits, (multispace0, eof));
+    alt((bare_hex, terminated(expression, multispace0)))
+        .parse(input)
+        .map_err(|error| {
+            let context = error.inner().to_string();
+            let column = error.offset() + 1;
+            match context.is_empty() {
+                true => format!("unexpected input at column {}", column),
+                false => format!("{} at column {}", context, column),
+            }
+        })
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+fn expression(input: &mut &str) -> PResult<Expr> {
+    preceded(
+        multispace0,
+        alt((call, reference, hex_color, number)).context(StrContext::Expected(
+            StrContextValue::Description("a colour, `$reference` or function"),
+        )),
+    )
+    .parse_next(input)
+}
+
+fn reference(input: &mut &str) -> PResult<Expr> {
+    preceded(
+        '$',
+        cut_err(alt((
+            delimited('{', take_while(1.., |c| c != '}'), '}'),
+            take_while(1.., is_name_char),
+        )))
+        .context(StrContext::Expected(StrContextValue::Description(
+            "a colour name",
+        ))),
+    )
+    .map(|name: &str| Expr::Reference(name.trim().to_string()))
+    .parse_next(input)
+}
+
+fn hex_color(input: &mut &str) -> PResult<Expr> {
+    preceded('#', cut_err(hex_digits))
+        .context(StrContext::Expected(StrContextValue::Description(
+            "3, 6 or 8 hex digits",
+        )))
+        .parse_next(input)
+}
+
+fn hex_digits(input: &mut &str) -> PResult<Expr> {
+    take_while(1.., |c: char| c.is_ascii_hexdigit())
+        .verify(|digits: &str| matches!(digits.len(), 3 | 6 | 8))
+        .map(|digits: &str| Expr::Color(Color::from_hex(digits)))
+        .parse_next(input)
+}
+
+fn bare_hex_digits(input: &mut &str) -> PResult<Expr> {
+    take_while(1.., |c: char| c.is_ascii_hexdigit())
+        .verify(|digits: &str| matches!(digits.len(), 6 | 8))
+        .map(|digits: &str| Expr::Color(Color::from_hex(digits)))
+        .parse_next(input)
+}
+
+/// An amount, either a fraction such as `0.1` or a percentage such as `10%`. A number
+/// above 1 without `%` is refused rather than taken as a fraction, as `10` is likely
+/// meant to be `10%`.
+fn number(input: &mut &str) -> PResult<Expr> {
+    let (value, percent): (f64, Option<char>) =
+        (float, opt(preceded(multispace0, '%'))).parse_next(input)?;
+    match percent {
+        Some(_) => Ok(Expr::Number(value / 100.0)),
+        None if value.abs() <= 1.0 => Ok(Expr::Number(value)),
+        None => cut_err(
+            fail.context(StrContext::Expected(StrContextValue::Description(
+                "a fraction from 0 to 1 or a percentage",
+            ))),
+        )
+        .parse_next(input),
+    }
+}
+
+fn call(input: &mut &str) -> PResult<Expr> {
+    let function = terminated(
+        take_while(1.., |c: char| c.is_ascii_alphabetic() || c == '_'),
+        (multispace0, '('),
+    )
+    .parse_next(input)?;
+    let args = cut_err(terminated(
+        separated(1.., expression, (multispace0, one_of(','))),
+        (multispace0, ')'),
+    ))
+    .context(StrContext::Expected(StrContextValue::Description(
+        "comma separated arguments and `)`",
+    )))
+    .parse_next(input)?;
+    Ok(Expr::Call(function.to_lowercase(), args))
+}
+
 #[cfg(test)]
 mod parser_tests {
     use super::*;
@@ -22,6 +198,89 @@ mod parser_tests {
         let input: String = read_to_string("test/test.toml").expect("File in place.");
         assert_eq!(toml_titles(input), vec!["Color Scheme Title"]);
     }
-}
 
-// {"Color Scheme Title": Table({"background": String("#0000017D"), "foreground": String("#FFFFFF"), "color0": String("#110f1e"), "color1": String("#C14039"), "color2": String("#076D8E"), "color3": String("#5A6592"), "color4": String("#0794B1"), "color5": String("#5BA5B8"), "color6": String("#03B1CC"), "color7": String("#78ddea"), "color8": String("#549aa3"), "color9": String("#C14039"), "color10": String("#076D8E"), "color11": String("#5A6592"), "color12": String("#0794B1"), "color13": String("#5BA5B8"), "color14": String("#03B1CC"), "color15": String("#78ddea")})}
+    #[test]
+    fn parse_literals() {
+        assert_eq!(
+            parse_expression("#FFF"),
+            Ok(Expr::Color(Color::from_hex("#FFF")))
+        );
+        assert_eq!(
+            parse_expression(" 0000017D "),
+            Ok(Expr::Color(Color::from_hex("#0000017D")))
+        );
+        assert!(parse_expression("#FFFF").is_err());
+        assert!(parse_expression("#GGG").is_err());
+        for word in ["bad", "fed", "add", "Face"] {
+            assert!(parse_expression(word).is_err(), "{}", word);
+        }
+        assert_eq!(
+            parse_expression("#bad"),
+            Ok(Expr::Color(Color::from_hex("#BBAADD")))
+        );
+    }
+
+    #[test]
+    fn parse_references() {
+        assert_eq!(
+            parse_expression("$foreground"),
+            Ok(Expr::Reference("foreground".to_string()))
+        );
+        assert_eq!(
+            parse_expression("${Selection Background}"),
+            Ok(Expr::Reference("Selection Background".to_string()))
+        );
+        assert!(parse_expression("$").is_err());
+    }
+
+    #[test]
+    fn parse_calls() {
+        assert_eq!(
+            parse_expression("mix($bg, $fg, 20%)"),
+            Ok(Expr::Call(
+                "mix".to_string(),
+                vec![
+                    Expr::Reference("bg".to_string()),
+                    Expr::Reference("fg".to_string()),
+                    Expr::Number(0.2)
+                ]
+            ))
+        );
+        let nested = parse_expression("Lighten( darken(#000, 0.5) ,10 %)").unwrap();
+        assert_eq!(nested.to_string(), "lighten(darken(#000000, 50%), 10%)");
+        assert_eq!(nested.references(), Vec::<&str>::new());
+        assert_eq!(
+            parse_expression("alpha($bg, 1)"),
+            Ok(Expr::Call(
+                "alpha".to_string(),
+                vec![Expr::Reference("bg".to_string()), Expr::Number(1.0)]
+            ))
+        );
+        let error = parse_expression("lighten($c, 10)").unwrap_err();
+        assert!(
+            error.contains("a fraction from 0 to 1 or a percentage"),
+            "{}",
+            error
+        );
+        assert!(parse_expression("lighten($color1, 10%").is_err());
+        assert!(parse_expression("lighten($color1) extra").is_err());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for input in [
+            "lighten($a, 7%)",
+            "darken($a, 29%)",
+            "mix($bg, $fg, 12.5%)",
+            "alpha(#000000, 0.1%)",
+        ] {
+            let expr = parse_expression(input).unwrap();
+            assert_eq!(expr.to_string(), input);
+            assert_eq!(parse_expression(&expr.to_string()), Ok(expr));
+        }
+        assert_eq!(
+            parse_expression("saturate($a, 0.07)").unwrap().to_string(),
+            "saturate($a, 7%)"
+        );
+    }
+}
@@ -0,0 +1,32 @@
+//! Fixtures shared by the unit tests.
+
+use std::{env, fs, path::PathBuf};
+
+use uuid::Uuid;
+
+use crate::color::Color;
+use crate::palette::Palette;
+
+/// A palette with the given colours in order, each given as `(name, hex)`.
+pub fn palette(name: &str, colors: &[(&str, &str)]) -> Palette {
+    let mut palette = Palette::new(name, None);
+    for (name, hex) in colors {
+        palette
+            .add_color(name.to_string(), Color::from_hex(hex))
+            .unwrap();
+    }
+    palette
+}
+
+/// A new config folder of its own in the temporary folder, with an empty `palettes`
+/// folder in it.
+pub fn config_folder() -> PathBuf {
+    let folder = temp_path("color-palette");
+    fs::create_dir_all(folder.join("palettes")).unwrap();
+    folder
+}
+
+/// A path in the temporary folder that no other test uses, starting with `prefix`.
+pub fn temp_path(prefix: &str) -> PathBuf {
+    env::temp_dir().join(format!("{}-{}", prefix, Uuid::new_v4()))
+}